@@ -0,0 +1,14 @@
+use std::process::Command;
+
+// the tests of the NumPy conversions only run where the interpreter pyo3 links against can import
+// NumPy, and are reported as ignored everywhere else, rather than passing without checking anything
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(numpy)");
+    println!("cargo::rerun-if-changed=build.rs");
+    println!("cargo::rerun-if-env-changed=PYO3_PYTHON");
+    let python = pyo3_build_config::get().executable.clone().unwrap_or_else(|| "python3".into());
+    let numpy = Command::new(python).args(["-c", "import numpy"]).output();
+    if numpy.is_ok_and(|output| output.status.success()) {
+        println!("cargo::rustc-cfg=numpy");
+    }
+}
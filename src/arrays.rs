@@ -0,0 +1,578 @@
+use numpy::{ndarray::{ArrayD, ArrayViewD, IxDyn}, IntoPyArray, PyArray1, PyArrayDyn, PyReadonlyArrayDyn, PyUntypedArray};
+use pyo3::{exceptions::{PyImportError, PyTypeError, PyValueError}, prelude::*};
+use rayon::prelude::*;
+use splinter_rs::{level::High, PartitionRead, Splinter};
+
+// conversions between splinters and NumPy arrays, which read and write the arrays' memory
+// directly rather than boxing every element as a Python int
+
+/// A NumPy array of candidate elements, of any shape
+pub(crate) enum ValueArray<'py> {
+    U32(PyReadonlyArrayDyn<'py, u32>),
+    // int64 is NumPy's default integer dtype on most platforms, so it is accepted as well and
+    // range-checked element by element
+    I64(PyReadonlyArrayDyn<'py, i64>),
+}
+
+impl<'py> ValueArray<'py> {
+    /// Borrows the buffer of a uint32 or int64 NumPy array, naming the calling method in the
+    /// error raised for anything else
+    pub(crate) fn extract(values: &Bound<'py, PyAny>, method: &str) -> PyResult<Self> {
+        Self::try_extract(values).ok_or_else(|| not_an_array(values, method, "uint32 or int64"))
+    }
+
+    /// Borrows the buffer of a uint32 or int64 NumPy array, or returns None for anything else
+    pub(crate) fn try_extract(values: &Bound<'py, PyAny>) -> Option<Self> {
+        if !numpy_loaded(values.py()) {
+            None
+        } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<u32>>() {
+            Some(Self::U32(arr))
+        } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<i64>>() {
+            Some(Self::I64(arr))
+        } else {
+            None
+        }
+    }
+
+    /// Builds an unoptimized Splinter from the elements, or returns the first element which
+    /// does not fit in a u32
+    pub(crate) fn to_splinter(&self) -> Result<Splinter, i64> {
+        match self {
+            Self::U32(arr) => Ok(arr.as_array().iter().copied().collect()),
+            Self::I64(arr) => arr.as_array().iter().map(|&val| u32::try_from(val).map_err(|_| val)).collect(),
+        }
+    }
+
+    /// Builds an unoptimized Splinter from the elements which fit in a u32, along with the
+    /// number of distinct elements which don't
+    pub(crate) fn to_splinter_within(&self) -> (Splinter, usize) {
+        match self {
+            Self::U32(arr) => (arr.as_array().iter().copied().collect(), 0),
+            Self::I64(arr) => {
+                let mut outside = Vec::new();
+                let splinter = arr.as_array().iter().filter_map(|&val| u32::try_from(val).inspect_err(|_| outside.push(val)).ok()).collect();
+                outside.sort_unstable();
+                outside.dedup();
+                (splinter, outside.len())
+            }
+        }
+    }
+}
+
+/// Reads the elements of a uint64, int64 or uint32 NumPy array as 64-bit values, or returns None
+/// for any other object. A negative int64 element is returned as the error
+pub(crate) fn wide_values(values: &Bound<PyAny>) -> Option<Result<Vec<u64>, i64>> {
+    if !numpy_loaded(values.py()) {
+        None
+    } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<u64>>() {
+        Some(Ok(arr.as_array().iter().copied().collect()))
+    } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<i64>>() {
+        Some(arr.as_array().iter().map(|&val| u64::try_from(val).map_err(|_| val)).collect())
+    } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<u32>>() {
+        Some(Ok(arr.as_array().iter().map(|&val| val as u64).collect()))
+    } else {
+        None
+    }
+}
+
+/// Reads the elements of an int32 or int64 NumPy array as 32-bit signed values, or returns None
+/// for any other object. An int64 element outside the range of an i32 is returned as the error
+pub(crate) fn signed_values(values: &Bound<PyAny>) -> Option<Result<Vec<i32>, i64>> {
+    if !numpy_loaded(values.py()) {
+        None
+    } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<i32>>() {
+        Some(Ok(arr.as_array().iter().copied().collect()))
+    } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<i64>>() {
+        Some(arr.as_array().iter().map(|&val| i32::try_from(val).map_err(|_| val)).collect())
+    } else {
+        None
+    }
+}
+
+/// The TypeError raised by a method which only accepts NumPy arrays of the given dtypes
+fn not_an_array(values: &Bound<PyAny>, method: &str, dtypes: &str) -> PyErr {
+    match values.get_type().name() {
+        Ok(name) => PyTypeError::new_err(format!(
+            "{method}() argument must be a numpy array of dtype {dtypes}, but received an object of type {name:#?}"
+        )),
+        Err(e) => e,
+    }
+}
+
+/// Whether obj is a NumPy array of any dtype
+pub(crate) fn is_array(obj: &Bound<PyAny>) -> bool {
+    numpy_loaded(obj.py()) && obj.is_instance_of::<PyUntypedArray>()
+}
+
+// NumPy is an optional dependency, and rust-numpy panics if it can't import it, so arrays are
+// only looked for once NumPy has been imported, as it must have been for an array to exist
+fn numpy_loaded(py: Python) -> bool {
+    py.import("sys")
+        .and_then(|sys| sys.getattr("modules"))
+        .and_then(|modules| modules.contains("numpy"))
+        .unwrap_or(false)
+}
+
+/// Imports NumPy for a method which can only return an array, raising an ImportError naming the
+/// method if it isn't installed, where rust-numpy would panic
+pub(crate) fn require_numpy(py: Python, method: &str) -> PyResult<()> {
+    match py.import("numpy") {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let err = PyImportError::new_err(format!("{method}() requires NumPy, which could not be imported"));
+            err.set_cause(py, Some(e));
+            Err(err)
+        }
+    }
+}
+
+/// Builds an unoptimized Splinter from the elements of a NumPy array, raising a ValueError if
+/// any of them do not fit in a u32
+pub(crate) fn splinter_from_numpy(data: &Bound<PyAny>) -> PyResult<Splinter> {
+    ValueArray::extract(data, "from_numpy")?.to_splinter().map_err(|val| {
+        PyValueError::new_err(format!("from_numpy() received {val}, but Splinter elements must be between 0 and {}", u32::MAX))
+    })
+}
+
+pub(crate) fn to_numpy<'py>(py: Python<'py>, splinter: &impl PartitionRead<High>) -> PyResult<Bound<'py, PyArray1<u32>>> {
+    require_numpy(py, "to_numpy")?;
+    let mut values = Vec::with_capacity(splinter.cardinality());
+    values.extend(splinter.iter());
+    Ok(values.into_pyarray(py))
+}
+
+/// Checks every element of a NumPy array for membership, returning a boolean array of the same
+/// shape. int64 values outside the range of a u32 are reported as absent.
+pub(crate) fn contains_numpy<'py>(
+    py: Python<'py>,
+    splinter: &impl PartitionRead<High>,
+    values: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+    let mask = match ValueArray::extract(values, "contains_numpy")? {
+        ValueArray::U32(arr) => arr.as_array().map(|&val| splinter.contains(val)),
+        ValueArray::I64(arr) => arr.as_array().map(|&val| u32::try_from(val).is_ok_and(|val| splinter.contains(val))),
+    };
+    Ok(mask.into_pyarray(py))
+}
+
+/// Parallel version of `contains_numpy`
+pub(crate) fn contains_numpy_parallel<'py>(
+    py: Python<'py>,
+    splinter: &(impl PartitionRead<High> + Sync),
+    values: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+    let mask = match ValueArray::extract(values, "contains_many_parallel_numpy")? {
+        ValueArray::U32(arr) => par_map(arr.as_array(), |val| splinter.contains(val)),
+        ValueArray::I64(arr) => par_map(arr.as_array(), |val| u32::try_from(val).is_ok_and(|val| splinter.contains(val))),
+    };
+    Ok(mask.into_pyarray(py))
+}
+
+/// Maps every element of an array in parallel, preserving its shape
+pub(crate) fn par_map<T, U, F>(arr: ArrayViewD<T>, f: F) -> ArrayD<U>
+where
+    T: Copy + Send + Sync,
+    U: Send,
+    F: Fn(T) -> U + Send + Sync,
+{
+    let flat: Vec<U> = match arr.as_slice() {
+        Some(slice) => slice.par_iter().map(|&val| f(val)).collect(),
+        // non-contiguous arrays are gathered first so that rayon can split them evenly
+        None => arr.iter().copied().collect::<Vec<T>>().into_par_iter().map(f).collect(),
+    };
+    ArrayD::from_shape_vec(arr.raw_dim(), flat).expect("the mapped array has as many elements as the input")
+}
+
+/// Wraps the values of a list in a one-dimensional array
+fn one_dimensional<T>(values: Vec<T>) -> ArrayD<T> { ArrayD::from_shape_vec(IxDyn(&[values.len()]), values).expect("a list is one-dimensional") }
+
+/// A list or NumPy array of ints, of any shape
+pub(crate) enum Values<'py> {
+    Array(ValueArray<'py>),
+    // the dtype of the values of a SignedSplinter
+    I32(PyReadonlyArrayDyn<'py, i32>),
+    List(ArrayD<i64>),
+}
+
+impl<'py> Values<'py> {
+    /// Borrows a uint32, int64 or int32 NumPy array, or collects any other sequence of ints,
+    /// naming the calling method in the error raised for anything else
+    pub(crate) fn extract(values: &Bound<'py, PyAny>, method: &str) -> PyResult<Self> {
+        if let Some(arr) = Self::try_extract_array(values) {
+            return Ok(arr);
+        }
+        let list: Vec<i64> = values.extract().map_err(|_| match values.get_type().name() {
+            Ok(name) => PyTypeError::new_err(format!(
+                "{method}() argument must be a list or numpy array of integers, but received an object of type {name:#?}"
+            )),
+            Err(e) => e,
+        })?;
+        Ok(Self::List(one_dimensional(list)))
+    }
+
+    /// Borrows a uint32, int64 or int32 NumPy array, naming the calling method in the error
+    /// raised for anything else, lists included
+    pub(crate) fn extract_array(values: &Bound<'py, PyAny>, method: &str) -> PyResult<Self> {
+        Self::try_extract_array(values).ok_or_else(|| not_an_array(values, method, "int32, int64 or uint32"))
+    }
+
+    fn try_extract_array(values: &Bound<'py, PyAny>) -> Option<Self> {
+        if let Some(arr) = ValueArray::try_extract(values) {
+            return Some(Self::Array(arr));
+        }
+        match numpy_loaded(values.py()) {
+            true => values.extract::<PyReadonlyArrayDyn<i32>>().ok().map(Self::I32),
+            false => None,
+        }
+    }
+
+    /// Maps every element, as an i64, into an array of the same shape, in parallel if requested
+    pub(crate) fn map<U: Send>(&self, parallel: bool, f: impl Fn(i64) -> U + Send + Sync) -> ArrayD<U> {
+        fn map<T: Copy + Send + Sync, U: Send>(arr: ArrayViewD<T>, parallel: bool, f: impl Fn(T) -> U + Send + Sync) -> ArrayD<U> {
+            if parallel { par_map(arr, f) } else { arr.map(|&val| f(val)) }
+        }
+        match self {
+            Self::Array(ValueArray::U32(arr)) => map(arr.as_array(), parallel, |val| f(val as i64)),
+            Self::Array(ValueArray::I64(arr)) => map(arr.as_array(), parallel, f),
+            Self::I32(arr) => map(arr.as_array(), parallel, |val| f(val as i64)),
+            Self::List(list) => map(list.view(), parallel, f),
+        }
+    }
+
+    /// Flattens the elements for which mask is true into a one-dimensional array of the same
+    /// dtype, or int64 for a list. NumPy must have been imported
+    pub(crate) fn filter(&self, py: Python, mask: &ArrayD<bool>) -> PyObject {
+        fn filter<T: numpy::Element + Copy>(py: Python, arr: ArrayViewD<T>, mask: &ArrayD<bool>) -> PyObject {
+            let kept: Vec<T> = arr.iter().zip(mask).filter(|(_, &keep)| keep).map(|(&val, _)| val).collect();
+            kept.into_pyarray(py).into_any().unbind()
+        }
+        match self {
+            Self::Array(ValueArray::U32(arr)) => filter(py, arr.as_array(), mask),
+            Self::Array(ValueArray::I64(arr)) => filter(py, arr.as_array(), mask),
+            Self::I32(arr) => filter(py, arr.as_array(), mask),
+            Self::List(list) => filter(py, list.view(), mask),
+        }
+    }
+}
+
+/// Ranks every element of a list or NumPy array. Values below 0 rank 0, and values above the
+/// largest u32 rank as the largest u32.
+pub(crate) fn rank_many<'py>(
+    py: Python<'py>,
+    splinter: &(impl PartitionRead<High> + Sync),
+    values: &Bound<'py, PyAny>,
+    parallel: bool,
+) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+    require_numpy(py, "rank_many")?;
+    let ranks = Values::extract(values, "rank_many")?.map(parallel, |val| match val {
+        ..0 => 0,
+        _ => splinter.rank(val.min(u32::MAX as i64) as u32) as u64,
+    });
+    Ok(ranks.into_pyarray(py))
+}
+
+/// Selects the element at every index of a list or NumPy array, with negative indices counting
+/// back from the end, and -1 for indices out of bounds
+pub(crate) fn select_many<'py>(
+    py: Python<'py>,
+    splinter: &(impl PartitionRead<High> + Sync),
+    indices: &Bound<'py, PyAny>,
+    parallel: bool,
+) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+    require_numpy(py, "select_many")?;
+    let len = splinter.cardinality();
+    let values = Values::extract(indices, "select_many")?.map(parallel, |idx| {
+        crate::select_index(splinter, len, idx).map_or(-1, |val| val as i64)
+    });
+    Ok(values.into_pyarray(py))
+}
+
+/// Finds the index of every element of a list or NumPy array, with -1 for values which are
+/// absent
+pub(crate) fn position_many<'py>(
+    py: Python<'py>,
+    splinter: &(impl PartitionRead<High> + Sync),
+    values: &Bound<'py, PyAny>,
+    parallel: bool,
+) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+    require_numpy(py, "position_many")?;
+    let positions = Values::extract(values, "position_many")?.map(parallel, |val| {
+        u32::try_from(val).ok().and_then(|val| splinter.position(val)).map_or(-1, |pos| pos as i64)
+    });
+    Ok(positions.into_pyarray(py))
+}
+
+/// Returns the elements of a list or NumPy array which are present in the splinter, or absent
+/// from it if present is false, or else their indices into the flattened input
+pub(crate) fn filter<'py>(
+    py: Python<'py>,
+    splinter: &(impl PartitionRead<High> + Sync),
+    values: &Bound<'py, PyAny>,
+    method: &str,
+    present: bool,
+    indices: bool,
+    parallel: bool,
+) -> PyResult<PyObject> {
+    require_numpy(py, method)?;
+    let values = Values::extract(values, method)?;
+    let mask = values.map(parallel, |val| u32::try_from(val).is_ok_and(|val| splinter.contains(val)) == present);
+    Ok(if indices { indices_of(py, &mask) } else { values.filter(py, &mask) })
+}
+
+/// Checks every element of a list or NumPy array for membership, returning a boolean array of
+/// the same shape, or the flattened results packed eight to a byte like numpy.packbits()
+pub(crate) fn contains_mask<'py>(
+    py: Python<'py>,
+    splinter: &(impl PartitionRead<High> + Sync),
+    values: &Bound<'py, PyAny>,
+    packed: bool,
+    parallel: bool,
+) -> PyResult<PyObject> {
+    require_numpy(py, "contains_mask")?;
+    let mask = Values::extract(values, "contains_mask")?
+        .map(parallel, |val| u32::try_from(val).is_ok_and(|val| splinter.contains(val)));
+    Ok(mask_to_py(py, mask, packed))
+}
+
+/// Returns a boolean mask as an array, or with the flattened results packed eight to a byte
+/// like numpy.packbits(). NumPy must have been imported
+pub(crate) fn mask_to_py(py: Python, mask: ArrayD<bool>, packed: bool) -> PyObject {
+    if !packed {
+        return mask.into_pyarray(py).into_any().unbind();
+    }
+    // like numpy.packbits(), the first of every eight values goes in the highest bit
+    let flat: Vec<bool> = mask.into_iter().collect();
+    let bytes: Vec<u8> = flat
+        .chunks(8)
+        .map(|bits| bits.iter().enumerate().fold(0, |byte, (i, &bit)| byte | ((bit as u8) << (7 - i))))
+        .collect();
+    bytes.into_pyarray(py).into_any().unbind()
+}
+
+/// The int64 indices into a flattened array of the elements for which mask is true. NumPy must
+/// have been imported
+pub(crate) fn indices_of(py: Python, mask: &ArrayD<bool>) -> PyObject {
+    let positions: Vec<i64> = mask.iter().enumerate().filter(|(_, &keep)| keep).map(|(i, _)| i as i64).collect();
+    positions.into_pyarray(py).into_any().unbind()
+}
+
+/// A list or NumPy array of candidate elements of a Splinter64, of any shape. Every element is
+/// read as an i128, which holds uint64 and int64 values alike
+pub(crate) enum WideValues<'py> {
+    U64(PyReadonlyArrayDyn<'py, u64>),
+    I64(PyReadonlyArrayDyn<'py, i64>),
+    U32(PyReadonlyArrayDyn<'py, u32>),
+    // a list is read as uint64 if it can be, as numpy.array() would, or else as int64
+    U64List(ArrayD<u64>),
+    I64List(ArrayD<i64>),
+}
+
+impl<'py> WideValues<'py> {
+    /// Borrows a uint64, int64 or uint32 NumPy array, or collects any other sequence of ints,
+    /// naming the calling method in the error raised for anything else
+    pub(crate) fn extract(values: &Bound<'py, PyAny>, method: &str) -> PyResult<Self> {
+        if let Some(arr) = Self::try_extract_array(values) {
+            return Ok(arr);
+        }
+        if let Ok(vals) = values.extract::<Vec<u64>>() {
+            return Ok(Self::U64List(one_dimensional(vals)));
+        }
+        let vals: Vec<i64> = values.extract().map_err(|_| match values.get_type().name() {
+            Ok(name) => PyTypeError::new_err(format!(
+                "{method}() argument must be a list or numpy array of integers between -2**63 and 2**64 - 1, but received an object of type {name:#?}"
+            )),
+            Err(e) => e,
+        })?;
+        Ok(Self::I64List(one_dimensional(vals)))
+    }
+
+    /// Borrows a uint64, int64 or uint32 NumPy array, naming the calling method in the error
+    /// raised for anything else, lists included
+    pub(crate) fn extract_array(values: &Bound<'py, PyAny>, method: &str) -> PyResult<Self> {
+        Self::try_extract_array(values).ok_or_else(|| not_an_array(values, method, "uint64, int64 or uint32"))
+    }
+
+    fn try_extract_array(values: &Bound<'py, PyAny>) -> Option<Self> {
+        if !numpy_loaded(values.py()) {
+            None
+        } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<u64>>() {
+            Some(Self::U64(arr))
+        } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<i64>>() {
+            Some(Self::I64(arr))
+        } else if let Ok(arr) = values.extract::<PyReadonlyArrayDyn<u32>>() {
+            Some(Self::U32(arr))
+        } else {
+            None
+        }
+    }
+
+    /// Maps every element, as an i128, into an array of the same shape, in parallel if requested
+    pub(crate) fn map<U: Send>(&self, parallel: bool, f: impl Fn(i128) -> U + Send + Sync) -> ArrayD<U> {
+        fn map<T: Copy + Into<i128> + Send + Sync, U: Send>(arr: ArrayViewD<T>, parallel: bool, f: impl Fn(i128) -> U + Send + Sync) -> ArrayD<U> {
+            if parallel { par_map(arr, |val| f(val.into())) } else { arr.map(|&val| f(val.into())) }
+        }
+        match self {
+            Self::U64(arr) => map(arr.as_array(), parallel, f),
+            Self::I64(arr) => map(arr.as_array(), parallel, f),
+            Self::U32(arr) => map(arr.as_array(), parallel, f),
+            Self::U64List(list) => map(list.view(), parallel, f),
+            Self::I64List(list) => map(list.view(), parallel, f),
+        }
+    }
+
+    /// Flattens the elements for which mask is true into a one-dimensional array of the same
+    /// dtype, which for a list is the dtype it was read as. NumPy must have been imported
+    pub(crate) fn filter(&self, py: Python, mask: &ArrayD<bool>) -> PyObject {
+        fn filter<T: numpy::Element + Copy>(py: Python, arr: ArrayViewD<T>, mask: &ArrayD<bool>) -> PyObject {
+            let kept: Vec<T> = arr.iter().zip(mask).filter(|(_, &keep)| keep).map(|(&val, _)| val).collect();
+            kept.into_pyarray(py).into_any().unbind()
+        }
+        match self {
+            Self::U64(arr) => filter(py, arr.as_array(), mask),
+            Self::I64(arr) => filter(py, arr.as_array(), mask),
+            Self::U32(arr) => filter(py, arr.as_array(), mask),
+            Self::U64List(list) => filter(py, list.view(), mask),
+            Self::I64List(list) => filter(py, list.view(), mask),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::ffi::c_str;
+
+    use crate::testing::run_python;
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn answers_batch_queries_about_lists() {
+        run_python(c_str!(
+            r#"
+for s in [Splinter.from_list([1, 2, 3, 2**32 - 1]), FrozenSplinter.from_list([1, 2, 3, 2**32 - 1])]:
+    assert s.rank_many([-1, 0, 1, 2, 4, 2**32 - 1, 2**40]).tolist() == [0, 0, 1, 2, 3, 4, 4]
+    assert s.rank_many([2, 3], parallel=True).tolist() == [2, 3] and s.rank_many([]).tolist() == []
+    assert s.select_many([0, -1, 4, -5]).tolist() == [1, 2**32 - 1, -1, -1]
+    assert s.position_many([3, 4, -1, 2**32 - 1], parallel=True).tolist() == [2, -1, -1, 3]
+
+for s in [Splinter64.from_list([1, 2**40]), Splinter64.from_list([1, 2**40]).freeze()]:
+    assert s.rank_many([0, 1, 2**40, 2**64 - 1]).tolist() == [0, 1, 2, 2]
+    assert s.select_many([0, -1]).tolist() == [1, 2**40] and s.position_many([2**40, 3]).tolist() == [1, -1]
+
+for s in [SignedSplinter.from_list([-3, 5]), SignedSplinter.from_list([-3, 5]).freeze()]:
+    assert s.rank_many([-4, -3, 0, 5, 2**40]).tolist() == [0, 1, 1, 2, 2]
+    assert s.select_many([0, -1]).tolist() == [-3, 5] and s.position_many([5, 4]).tolist() == [1, -1]
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn filters_lists() {
+        run_python(c_str!(
+            r#"
+import numpy as np
+
+for s in [Splinter.from_list([1, 5, 9]), FrozenSplinter.from_list([1, 5, 9])]:
+    present = s.filter_present([9, -1, 1, 2**40, 4])
+    assert present.dtype == np.int64 and present.tolist() == [9, 1]
+    assert s.filter_absent([9, -1, 1, 2**40, 4], parallel=True).tolist() == [-1, 2**40, 4]
+    assert s.filter_present([9, 4, 1], indices=True).tolist() == [0, 2] and s.filter_absent([]).tolist() == []
+    assert s.contains_mask([1, 2, -1, 5]).tolist() == [True, False, False, True]
+    packed = s.contains_mask([1, 2, 5, 6, 7, 8, 9, 0, 1], packed=True)
+    assert packed.dtype == np.uint8 and packed.tolist() == [0b10100010, 0b10000000]
+
+for s in [Splinter64.from_list([1, 2**40]), SignedSplinter.from_list([-3, 1])]:
+    assert s.filter_present([1, 2, -3, 2**40]).tolist() == [v for v in [1, 2, -3, 2**40] if v in s]
+    assert s.filter_absent([1, 2], indices=True).tolist() == [1]
+    assert s.contains_mask([1, 0], packed=True).tolist() == [0b10000000]
+    assert s.contains_numpy(np.array([1, 0])).tolist() == [True, False]
+    assert s.freeze().contains_mask([0, 1]).tolist() == [False, True]
+"#
+        ));
+    }
+
+    #[test]
+    fn checks_only_numpy_arrays_with_contains_numpy() {
+        run_python(c_str!(
+            r#"
+for s in [Splinter.from_list([1]), Splinter64.from_list([1]), SignedSplinter.from_list([-1])]:
+    for s in [s, s.freeze()]:
+        for bad in [[1, 0], (1,), range(2)]:
+            try:
+                s.contains_numpy(bad)
+                raise AssertionError(f"{type(s).__name__}.contains_numpy() accepted {bad!r}")
+            except TypeError as e:
+                assert "contains_numpy() argument must be a numpy array" in str(e), e
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(numpy, ignore = "NumPy is installed")]
+    fn raises_import_errors_without_numpy() {
+        run_python(c_str!(
+            r#"
+for s in [Splinter.from_list([1]), Splinter64.from_list([1]), SignedSplinter.from_list([-1])]:
+    for s in [s, s.freeze()]:
+        for name, call in [
+            ("to_numpy", lambda: s.to_numpy()),
+            ("filter_present", lambda: s.filter_present([1, 2])),
+            ("filter_absent", lambda: s.filter_absent([1, 2], indices=True)),
+            ("contains_mask", lambda: s.contains_mask([1, 2])),
+            ("contains_mask", lambda: s.contains_mask([1, 2], packed=True, parallel=True)),
+            ("rank_many", lambda: s.rank_many([1, 2])),
+            ("select_many", lambda: s.select_many([0], parallel=True)),
+            ("position_many", lambda: s.position_many([1])),
+        ]:
+            try:
+                call()
+                raise AssertionError(f"{type(s).__name__}.{name}() returned without NumPy")
+            except ImportError as e:
+                assert f"{name}() requires NumPy" in str(e)
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn reads_and_writes_numpy_arrays() {
+        run_python(c_str!(
+            r#"
+import numpy as np
+
+values = [0, 1, 70000, 2**32 - 1]
+for cls in [Splinter, FrozenSplinter]:
+    for dtype in [np.uint32, np.int64]:
+        s = cls.from_numpy(np.array([[2**32 - 1, 1], [70000, 0]], dtype=dtype))
+        out = s.to_numpy()
+        assert out.dtype == np.uint32 and out.tolist() == values and s.to_list() == values
+        assert s.contains_numpy(np.array([[1, 2]], dtype=dtype)).tolist() == [[True, False]]
+        assert (s & np.array([1, 2], dtype=dtype)).to_list() == [1]
+
+    # int64 values which no Splinter can hold are rejected on the way in and absent otherwise
+    for bad in [-1, 2**32, -2**63]:
+        try:
+            cls.from_numpy(np.array([1, bad], dtype=np.int64))
+            raise AssertionError(f"from_numpy() accepted {bad}")
+        except ValueError:
+            pass
+    probes = np.array([[-1, 1], [2**32, 2**32 - 1]], dtype=np.int64)
+    assert s.contains_numpy(probes).tolist() == [[False, True], [False, True]]
+    assert s.contains_many_parallel_numpy(probes).tolist() == [[False, True], [False, True]]
+    assert s.contains_mask(probes, packed=True).dtype == np.uint8
+    present = s.filter_present(probes)
+    assert present.dtype == np.int64 and present.tolist() == [1, 2**32 - 1]
+    assert s.filter_absent(probes, indices=True).tolist() == [0, 2]
+    assert s.rank_many(probes).dtype == np.uint64 and s.rank_many(probes).tolist() == [[0, 2], [4, 4]]
+    assert s.position_many(probes).tolist() == [[-1, 1], [-1, 3]]
+    for bad in [np.array([1.5]), np.array([1], dtype=np.uint64), [1, 2]]:
+        try:
+            cls.from_numpy(bad)
+            raise AssertionError(f"from_numpy() accepted {bad!r}")
+        except TypeError:
+            pass
+assert Splinter().to_numpy().dtype == np.uint32 and Splinter().to_numpy().tolist() == []
+"#
+        ));
+    }
+}
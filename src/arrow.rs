@@ -0,0 +1,716 @@
+use std::{ffi::{c_char, c_int, c_void, CStr}, mem, ptr, slice, sync::Arc};
+
+use bytes::Bytes;
+use pyo3::{exceptions::{PyTypeError, PyValueError}, prelude::*, types::PyCapsule};
+use splinter_rs::{level::High, PartitionRead, Splinter};
+
+use crate::SplinterArg;
+
+// Exchanging arrays with Arrow libraries such as pyarrow, polars and DuckDB through the Arrow C
+// Data Interface, wrapped in capsules as the Arrow PyCapsule Interface describes
+// (https://arrow.apache.org/docs/format/CDataInterface/PyCapsuleInterface.html).
+//
+// Arrays are read from any object with an `__arrow_c_array__` or `__arrow_c_stream__` method, so
+// no Arrow library is needed on this side. The C structs are moved out of their capsules and
+// released once nothing reads from their buffers any more, which for a column of serialized
+// splinters is once every splinter read from it is gone. Exported arrays own their buffers, and
+// stay alive until every consumer has released them.
+
+// the field of an exported binary column may hold nulls
+const FLAG_NULLABLE: i64 = 2;
+
+/// The ArrowSchema struct of the C Data Interface, which describes the type of an array
+#[repr(C)]
+struct ArrowSchema {
+    format: *const c_char,
+    name: *const c_char,
+    metadata: *const c_char,
+    flags: i64,
+    n_children: i64,
+    children: *mut *mut ArrowSchema,
+    dictionary: *mut ArrowSchema,
+    release: Option<unsafe extern "C" fn(*mut ArrowSchema)>,
+    private_data: *mut c_void,
+}
+
+/// The ArrowArray struct of the C Data Interface, which holds the buffers of an array
+#[repr(C)]
+struct ArrowArray {
+    length: i64,
+    null_count: i64,
+    offset: i64,
+    n_buffers: i64,
+    n_children: i64,
+    buffers: *mut *const c_void,
+    children: *mut *mut ArrowArray,
+    dictionary: *mut ArrowArray,
+    release: Option<unsafe extern "C" fn(*mut ArrowArray)>,
+    private_data: *mut c_void,
+}
+
+/// The ArrowArrayStream struct of the C stream interface, which hands out a schema and then any
+/// number of arrays of that type
+#[repr(C)]
+struct ArrowArrayStream {
+    get_schema: Option<unsafe extern "C" fn(*mut ArrowArrayStream, *mut ArrowSchema) -> c_int>,
+    get_next: Option<unsafe extern "C" fn(*mut ArrowArrayStream, *mut ArrowArray) -> c_int>,
+    get_last_error: Option<unsafe extern "C" fn(*mut ArrowArrayStream) -> *const c_char>,
+    release: Option<unsafe extern "C" fn(*mut ArrowArrayStream)>,
+    private_data: *mut c_void,
+}
+
+/// A struct of the C interfaces, which is released by calling its release callback
+trait Released: Sized {
+    /// Returns a released struct, which owns nothing
+    fn released() -> Self {
+        // SAFETY: every field is an integer, a pointer or an optional function pointer, for all
+        // of which zero is valid, and a struct with no release callback is marked released
+        unsafe { mem::zeroed() }
+    }
+
+    fn is_released(&self) -> bool;
+}
+
+macro_rules! release_on_drop {
+    ($($ty:ty),*) => {$(
+        impl Released for $ty {
+            fn is_released(&self) -> bool { self.release.is_none() }
+        }
+
+        impl Drop for $ty {
+            fn drop(&mut self) {
+                if let Some(release) = self.release {
+                    // SAFETY: the producer set the callback to release this very struct
+                    unsafe { release(self) }
+                }
+            }
+        }
+
+        // SAFETY: the C Data Interface requires that structs can be released from any thread,
+        // and nothing writes to their buffers while they are shared
+        unsafe impl Send for $ty {}
+        unsafe impl Sync for $ty {}
+    )*};
+}
+
+release_on_drop!(ArrowSchema, ArrowArray, ArrowArrayStream);
+
+/// Moves a struct out of a capsule, leaving a released one behind for the capsule's destructor
+fn take<T: Released>(capsule: &Bound<PyAny>, name: &CStr) -> PyResult<T> {
+    let capsule = capsule.downcast::<PyCapsule>()?;
+    if capsule.name()? != Some(name) {
+        return Err(PyValueError::new_err(format!("expected a PyCapsule named {name:?}")));
+    }
+    // SAFETY: the capsule's name tells us what it points to, and the value left behind releases
+    // nothing when the capsule is destroyed
+    let value = unsafe { ptr::replace(capsule.pointer() as *mut T, T::released()) };
+    if value.is_released() {
+        return Err(PyValueError::new_err(format!("the PyCapsule named {name:?} has already been consumed")));
+    }
+    Ok(value)
+}
+
+/// The arrays behind an object exporting the Arrow PyCapsule Interface, which are all of one type
+struct Chunks {
+    format: String,
+    arrays: Vec<ArrowArray>,
+}
+
+/// Imports an array, or every array of a stream, such as the chunks of a pyarrow ChunkedArray
+fn import(obj: &Bound<PyAny>, method: &str) -> PyResult<Chunks> {
+    let (schema, arrays) = if obj.hasattr("__arrow_c_array__")? {
+        let (schema, array): (Bound<PyAny>, Bound<PyAny>) = obj.call_method0("__arrow_c_array__")?.extract()?;
+        (take::<ArrowSchema>(&schema, c"arrow_schema")?, vec![take::<ArrowArray>(&array, c"arrow_array")?])
+    } else if obj.hasattr("__arrow_c_stream__")? {
+        let mut stream = take::<ArrowArrayStream>(&obj.call_method0("__arrow_c_stream__")?, c"arrow_array_stream")?;
+        let (get_schema, get_next) = stream.get_schema.zip(stream.get_next).ok_or_else(|| {
+            PyValueError::new_err(format!("{method}() received an Arrow stream without callbacks"))
+        })?;
+        let mut schema = ArrowSchema::released();
+        // SAFETY: the stream is live, and its callbacks write a struct to the second argument
+        let code = unsafe { get_schema(&mut stream, &mut schema) };
+        stream_result(&mut stream, code, method)?;
+        let mut arrays = Vec::new();
+        loop {
+            let mut array = ArrowArray::released();
+            let code = unsafe { get_next(&mut stream, &mut array) };
+            stream_result(&mut stream, code, method)?;
+            // the end of the stream is marked by a released array
+            if array.is_released() {
+                break;
+            }
+            arrays.push(array);
+        }
+        (schema, arrays)
+    } else {
+        return Err(PyTypeError::new_err(format!(
+            "{method}() argument must be an Arrow array exporting __arrow_c_array__ or __arrow_c_stream__, but received an object of type {:#?}",
+            obj.get_type().name()?
+        )));
+    };
+
+    // SAFETY: the format of a live schema is a null-terminated string
+    let format = unsafe { CStr::from_ptr(schema.format) }.to_string_lossy().into_owned();
+    if !schema.dictionary.is_null() {
+        return Err(PyTypeError::new_err(format!("{method}() does not accept dictionary-encoded arrays")));
+    }
+    Ok(Chunks { format, arrays })
+}
+
+/// Turns the error code of a stream callback into a ValueError carrying the stream's message
+fn stream_result(stream: &mut ArrowArrayStream, code: c_int, method: &str) -> PyResult<()> {
+    if code == 0 {
+        return Ok(());
+    }
+    let message = match stream.get_last_error {
+        // SAFETY: the last error is null or a null-terminated string owned by the stream
+        Some(get_last_error) => match unsafe { get_last_error(stream) } {
+            message if message.is_null() => format!("error code {code}"),
+            message => unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned(),
+        },
+        None => format!("error code {code}"),
+    };
+    Err(PyValueError::new_err(format!("{method}() could not read the Arrow stream: {message}")))
+}
+
+impl ArrowArray {
+    /// Borrows the first len elements of one of the array's buffers, which must be at least that
+    /// long for the array's type
+    fn buffer<T>(&self, idx: usize, len: usize) -> PyResult<&[T]> {
+        if len == 0 {
+            return Ok(&[]);
+        }
+        let data = match usize::try_from(self.n_buffers).is_ok_and(|n_buffers| idx < n_buffers) {
+            // SAFETY: buffers holds n_buffers pointers
+            true => unsafe { *self.buffers.add(idx) as *const T },
+            false => ptr::null(),
+        };
+        if data.is_null() {
+            return Err(PyValueError::new_err(format!("the Arrow array is missing buffer {idx}")));
+        }
+        if len.checked_mul(mem::size_of::<T>()).is_none_or(|size| size > isize::MAX as usize) {
+            return Err(PyValueError::new_err(format!("buffer {idx} of the Arrow array is too long")));
+        }
+        if !data.is_aligned() {
+            return Err(PyValueError::new_err(format!("buffer {idx} of the Arrow array is not aligned")));
+        }
+        // SAFETY: the producer guarantees the buffer holds every element the array refers to
+        Ok(unsafe { slice::from_raw_parts(data, len) })
+    }
+
+    /// Returns the range of elements of the buffers which make up the array
+    fn span(&self) -> PyResult<(usize, usize)> {
+        let start = usize::try_from(self.offset).ok();
+        let end = start.zip(usize::try_from(self.length).ok()).and_then(|(start, len)| start.checked_add(len));
+        start.zip(end).ok_or_else(|| {
+            PyValueError::new_err(format!("the Arrow array has an invalid offset {} or length {}", self.offset, self.length))
+        })
+    }
+
+    /// Checks whether each element of the array is non-null
+    fn validity(&self) -> PyResult<impl Fn(usize) -> bool + '_> {
+        let (start, end) = self.span()?;
+        let bitmap = match self.null_count != 0 && self.n_buffers > 0 && unsafe { !(*self.buffers).is_null() } {
+            true => Some(self.buffer::<u8>(0, end.div_ceil(8))?),
+            false => None,
+        };
+        Ok(move |idx: usize| bitmap.is_none_or(|bitmap| bitmap[(start + idx) / 8] & (1 << ((start + idx) % 8)) != 0))
+    }
+}
+
+/// Builds an unoptimized Splinter from an Arrow array of type uint32
+pub(crate) fn splinter_from_arrow(obj: &Bound<PyAny>) -> PyResult<Splinter> {
+    let chunks = import(obj, "from_arrow")?;
+    if chunks.format != "I" {
+        return Err(PyTypeError::new_err(format!(
+            "from_arrow() argument must be an Arrow array of type uint32, but received one of format {:?}",
+            chunks.format
+        )));
+    }
+    let mut splinter = Splinter::EMPTY;
+    for array in &chunks.arrays {
+        let (start, end) = array.span()?;
+        let is_valid = array.validity()?;
+        if (0..end - start).any(|idx| !is_valid(idx)) {
+            return Err(PyValueError::new_err("from_arrow() received an array holding nulls, which a Splinter can't hold"));
+        }
+        splinter.extend(array.buffer::<u32>(1, end)?[start..].iter().copied());
+    }
+    Ok(splinter)
+}
+
+/// A buffer of an imported array, which keeps the whole array alive
+struct BufferOwner {
+    _array: Arc<ArrowArray>,
+    data: *const u8,
+    len: usize,
+}
+
+// SAFETY: the buffer is only ever read, and the array it belongs to can be released anywhere
+unsafe impl Send for BufferOwner {}
+
+impl AsRef<[u8]> for BufferOwner {
+    fn as_ref(&self) -> &[u8] {
+        match self.len {
+            0 => &[],
+            // SAFETY: the array, and with it the buffer, lives as long as the owner does
+            len => unsafe { slice::from_raw_parts(self.data, len) },
+        }
+    }
+}
+
+/// Wraps the first len bytes of a buffer of an array in `Bytes` without copying them
+fn shared(array: &Arc<ArrowArray>, idx: usize, len: usize) -> PyResult<Bytes> {
+    let data = array.buffer::<u8>(idx, len)?.as_ptr();
+    Ok(Bytes::from_owner(BufferOwner { _array: array.clone(), data, len }))
+}
+
+/// Reads every element of an Arrow binary, large binary or binary view array, without copying
+/// any element longer than 12 bytes. Nulls are returned as None
+pub(crate) fn binary_column(obj: &Bound<PyAny>) -> PyResult<Vec<Option<Bytes>>> {
+    let chunks = import(obj, "from_arrow_binary")?;
+    let mut column = Vec::new();
+    for array in chunks.arrays {
+        let array = Arc::new(array);
+        let (start, end) = array.span()?;
+        let is_valid = array.validity()?;
+        let values = match chunks.format.as_str() {
+            "z" => offset_values(&array, &array.buffer::<i32>(1, end + 1)?[start..])?,
+            "Z" => offset_values(&array, &array.buffer::<i64>(1, end + 1)?[start..])?,
+            "vz" => view_values(&array, &is_valid)?,
+            format => {
+                return Err(PyTypeError::new_err(format!(
+                    "from_arrow_binary() argument must be an Arrow array of type binary, large_binary or binary_view, but received one of format {format:?}"
+                )))
+            }
+        };
+        column.extend(values.into_iter().enumerate().map(|(idx, value)| Some(value).filter(|_| is_valid(idx))));
+    }
+    Ok(column)
+}
+
+/// Slices the elements of a binary array out of its data buffer, given the offsets of its
+/// elements and the end of the last one
+fn offset_values<O: Copy + Into<i64>>(array: &Arc<ArrowArray>, offsets: &[O]) -> PyResult<Vec<Bytes>> {
+    let offsets = offsets
+        .iter()
+        .map(|&offset| usize::try_from(offset.into()))
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| PyValueError::new_err("the Arrow array holds negative offsets"))?;
+    if offsets.windows(2).any(|w| w[0] > w[1]) {
+        return Err(PyValueError::new_err("the Arrow array holds offsets which are out of order"));
+    }
+    let data = shared(array, 2, offsets.last().copied().unwrap_or(0))?;
+    Ok(offsets.windows(2).map(|w| data.slice(w[0]..w[1])).collect())
+}
+
+/// Reads the elements of a binary view array, each of which is 16 bytes holding either the
+/// element itself or the buffer and position where it is stored. Nulls are read as empty
+fn view_values(array: &Arc<ArrowArray>, is_valid: &impl Fn(usize) -> bool) -> PyResult<Vec<Bytes>> {
+    let (start, end) = array.span()?;
+    let views = &array.buffer::<[u8; 16]>(1, end)?[start..];
+    // the variadic data buffers come after the views, and their lengths in one last buffer
+    let n_buffers = usize::try_from(array.n_buffers).ok().filter(|&n_buffers| n_buffers >= 3).ok_or_else(|| {
+        PyValueError::new_err(format!("the Arrow binary view array has {} buffers, but needs at least 3", array.n_buffers))
+    })?;
+    let lengths = array.buffer::<i64>(n_buffers - 1, n_buffers - 3)?;
+    let buffers = lengths
+        .iter()
+        .enumerate()
+        .map(|(idx, &len)| {
+            let len = usize::try_from(len).map_err(|_| PyValueError::new_err("the Arrow array has a data buffer of negative length"))?;
+            shared(array, 2 + idx, len)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let outside = || PyValueError::new_err("the Arrow array holds a view outside of its data buffers");
+    let read = |view: &[u8; 16], at: usize| {
+        let field = i32::from_ne_bytes(view[at..at + 4].try_into().expect("view fields are 4 bytes"));
+        usize::try_from(field).map_err(|_| outside())
+    };
+    views
+        .iter()
+        .enumerate()
+        .map(|(idx, view)| match read(view, 0)? {
+            _ if !is_valid(idx) => Ok(Bytes::new()),
+            len if len <= 12 => Ok(Bytes::copy_from_slice(&view[4..4 + len])),
+            len => {
+                let (buffer, offset) = (read(view, 8)?, read(view, 12)?);
+                let end = offset.checked_add(len).ok_or_else(outside)?;
+                buffers.get(buffer).filter(|data| end <= data.len()).map(|data| data.slice(offset..end)).ok_or_else(outside)
+            }
+        })
+        .collect()
+}
+
+/// A buffer of an exported array, typed so that it is aligned for its elements
+enum Buffer {
+    // an absent validity buffer, for an array without nulls
+    Absent,
+    U8(Vec<u8>),
+    U32(Vec<u32>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+}
+
+impl Buffer {
+    fn as_ptr(&self) -> *const c_void {
+        match self {
+            Buffer::Absent => ptr::null(),
+            Buffer::U8(buf) => buf.as_ptr() as *const c_void,
+            Buffer::U32(buf) => buf.as_ptr() as *const c_void,
+            Buffer::I32(buf) => buf.as_ptr() as *const c_void,
+            Buffer::I64(buf) => buf.as_ptr() as *const c_void,
+        }
+    }
+}
+
+/// The contents of an exported array, which are shared by every export of it
+struct Exported {
+    format: &'static CStr,
+    type_name: &'static str,
+    flags: i64,
+    length: usize,
+    null_count: usize,
+    buffers: Vec<Buffer>,
+}
+
+/// The private data of an exported ArrowArray, which keeps its buffers alive
+struct ArrayPrivate {
+    _contents: Arc<Exported>,
+    buffers: Vec<*const c_void>,
+}
+
+/// The private data of an exported ArrowArrayStream, which hands out the array once
+struct StreamPrivate {
+    contents: Arc<Exported>,
+    done: bool,
+}
+
+unsafe extern "C" fn release_schema(schema: *mut ArrowSchema) { (*schema).release = None; }
+
+unsafe extern "C" fn release_array(array: *mut ArrowArray) {
+    drop(Box::from_raw((*array).private_data as *mut ArrayPrivate));
+    (*array).release = None;
+}
+
+unsafe extern "C" fn stream_schema(stream: *mut ArrowArrayStream, out: *mut ArrowSchema) -> c_int {
+    let private = &*((*stream).private_data as *const StreamPrivate);
+    ptr::write(out, export_schema(&private.contents));
+    0
+}
+
+unsafe extern "C" fn stream_next(stream: *mut ArrowArrayStream, out: *mut ArrowArray) -> c_int {
+    let private = &mut *((*stream).private_data as *mut StreamPrivate);
+    let array = match mem::replace(&mut private.done, true) {
+        true => ArrowArray::released(),
+        false => export_array(&private.contents),
+    };
+    ptr::write(out, array);
+    0
+}
+
+unsafe extern "C" fn stream_error(_stream: *mut ArrowArrayStream) -> *const c_char { ptr::null() }
+
+unsafe extern "C" fn release_stream(stream: *mut ArrowArrayStream) {
+    drop(Box::from_raw((*stream).private_data as *mut StreamPrivate));
+    (*stream).release = None;
+}
+
+fn export_schema(contents: &Exported) -> ArrowSchema {
+    ArrowSchema {
+        format: contents.format.as_ptr(),
+        flags: contents.flags,
+        release: Some(release_schema),
+        ..ArrowSchema::released()
+    }
+}
+
+fn export_array(contents: &Arc<Exported>) -> ArrowArray {
+    let buffers = contents.buffers.iter().map(Buffer::as_ptr).collect();
+    let mut private = Box::new(ArrayPrivate { _contents: contents.clone(), buffers });
+    ArrowArray {
+        length: contents.length as i64,
+        null_count: contents.null_count as i64,
+        n_buffers: private.buffers.len() as i64,
+        buffers: private.buffers.as_mut_ptr(),
+        release: Some(release_array),
+        private_data: Box::into_raw(private) as *mut c_void,
+        ..ArrowArray::released()
+    }
+}
+
+/// Wraps a struct in a capsule, which releases it when destroyed unless a consumer has moved it
+/// out first
+fn capsule<'py, T: Released + Send + 'static>(py: Python<'py>, value: T, name: &CStr) -> PyResult<Bound<'py, PyCapsule>> {
+    PyCapsule::new_with_destructor(py, value, Some(name.to_owned()), |value, _| drop(value))
+}
+
+#[pyclass(name = "ArrowArray", module = "splynters", frozen)]
+/// An Arrow array exported from splynters, which Arrow libraries can import without copying
+/// through the Arrow PyCapsule Interface, e.g. with `pyarrow.array()` or `polars.Series()`
+pub struct ArrowArrayWrapper(Arc<Exported>);
+
+impl ArrowArrayWrapper {
+    /// Exports a uint32 array
+    fn uint32(values: Vec<u32>) -> Self {
+        Self(Arc::new(Exported {
+            format: c"I",
+            type_name: "uint32",
+            flags: 0,
+            length: values.len(),
+            null_count: 0,
+            buffers: vec![Buffer::Absent, Buffer::U32(values)],
+        }))
+    }
+
+    /// Exports a binary array, or a large binary array if it holds more than 2 GiB of data, in
+    /// which None elements are null
+    fn binary(values: Vec<Option<Bytes>>) -> Self {
+        let null_count = values.iter().filter(|value| value.is_none()).count();
+        let validity = match null_count {
+            0 => Buffer::Absent,
+            _ => {
+                let mut bitmap = vec![0u8; values.len().div_ceil(8)];
+                for (idx, _) in values.iter().enumerate().filter(|(_, value)| value.is_some()) {
+                    bitmap[idx / 8] |= 1 << (idx % 8);
+                }
+                Buffer::U8(bitmap)
+            }
+        };
+        let ends = values.iter().scan(0, |end, value| {
+            *end += value.as_ref().map_or(0, Bytes::len);
+            Some(*end)
+        });
+        let total = values.iter().flatten().map(Bytes::len).sum::<usize>();
+        let (format, type_name, offsets) = match i32::try_from(total) {
+            Ok(_) => (c"z", "binary", Buffer::I32(std::iter::once(0).chain(ends.map(|end| end as i32)).collect())),
+            Err(_) => (c"Z", "large_binary", Buffer::I64(std::iter::once(0).chain(ends.map(|end| end as i64)).collect())),
+        };
+        let mut data = Vec::with_capacity(total);
+        values.iter().flatten().for_each(|value| data.extend_from_slice(value));
+        Self(Arc::new(Exported {
+            format,
+            type_name,
+            flags: FLAG_NULLABLE,
+            length: values.len(),
+            null_count,
+            buffers: vec![validity, offsets, Buffer::U8(data)],
+        }))
+    }
+}
+
+#[pymethods]
+impl ArrowArrayWrapper {
+    fn __len__(&self) -> usize { self.0.length }
+
+    fn __repr__(&self) -> String { format!("ArrowArray(type = {}, len = {})", self.0.type_name, self.0.length) }
+
+    /// Exports the type of the array as an ArrowSchema capsule
+    fn __arrow_c_schema__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyCapsule>> {
+        capsule(py, export_schema(&self.0), c"arrow_schema")
+    }
+
+    #[pyo3(signature = (requested_schema = None))]
+    /// Exports the array as a pair of ArrowSchema and ArrowArray capsules. The array is only
+    /// ever exported in its own type, so requested_schema is ignored
+    fn __arrow_c_array__<'py>(
+        &self,
+        py: Python<'py>,
+        requested_schema: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<(Bound<'py, PyCapsule>, Bound<'py, PyCapsule>)> {
+        let _ = requested_schema;
+        Ok((capsule(py, export_schema(&self.0), c"arrow_schema")?, capsule(py, export_array(&self.0), c"arrow_array")?))
+    }
+
+    #[pyo3(signature = (requested_schema = None))]
+    /// Exports the array as an ArrowArrayStream capsule holding a single array. As with
+    /// __arrow_c_array__, requested_schema is ignored
+    fn __arrow_c_stream__<'py>(&self, py: Python<'py>, requested_schema: Option<Bound<'py, PyAny>>) -> PyResult<Bound<'py, PyCapsule>> {
+        let _ = requested_schema;
+        let private = Box::new(StreamPrivate { contents: self.0.clone(), done: false });
+        let stream = ArrowArrayStream {
+            get_schema: Some(stream_schema),
+            get_next: Some(stream_next),
+            get_last_error: Some(stream_error),
+            release: Some(release_stream),
+            private_data: Box::into_raw(private) as *mut c_void,
+        };
+        capsule(py, stream, c"arrow_array_stream")
+    }
+}
+
+/// Exports the elements of a splinter, in ascending order, as a uint32 array
+pub(crate) fn to_arrow(splinter: &impl PartitionRead<High>) -> ArrowArrayWrapper {
+    let mut values = Vec::with_capacity(splinter.cardinality());
+    values.extend(splinter.iter());
+    ArrowArrayWrapper::uint32(values)
+}
+
+#[pyfunction]
+/// Exports many Splinters at once as an Arrow binary column of their serialized forms, as
+/// written by `to_bytes()`, which `from_arrow_binary()` reads back.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter | None]): The Splinters to export, where
+///     None becomes a null
+///
+/// Returns:
+///     ArrowArray: A binary array, or a large_binary array if the Splinters add up to more than
+///     2 GiB, which Arrow libraries can import without copying
+pub fn to_arrow_binary(splinters: &Bound<PyAny>) -> PyResult<ArrowArrayWrapper> {
+    let values = splinters
+        .try_iter()?
+        .map(|item| {
+            let item = item?;
+            match item.extract::<Option<SplinterArg>>() {
+                Ok(arg) => Ok(arg.map(|arg| match arg {
+                    SplinterArg::Splinter(wrapper) => wrapper.encoded(),
+                    SplinterArg::Frozen(wrapper) => wrapper.0.inner().clone(),
+                })),
+                Err(_) => Err(PyTypeError::new_err(format!(
+                    "to_arrow_binary() argument must be an iterable of Splinters or None, but it contained an object of type {:#?}",
+                    item.get_type().name()?
+                ))),
+            }
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(ArrowArrayWrapper::binary(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::ffi::c_str;
+    use splinter_rs::Splinter;
+
+    use super::*;
+
+    /// An unowned array over the given buffers, which must outlive it
+    fn array(length: i64, offset: i64, buffers: &mut [*const c_void]) -> Arc<ArrowArray> {
+        Arc::new(ArrowArray {
+            length,
+            offset,
+            n_buffers: buffers.len() as i64,
+            buffers: buffers.as_mut_ptr(),
+            ..ArrowArray::released()
+        })
+    }
+
+    fn view(len: i32, prefix: &[u8], buffer: i32, offset: i32) -> [u8; 16] {
+        let mut view = [0u8; 16];
+        view[..4].copy_from_slice(&len.to_ne_bytes());
+        view[4..4 + prefix.len()].copy_from_slice(prefix);
+        if len > 12 {
+            view[8..12].copy_from_slice(&buffer.to_ne_bytes());
+            view[12..].copy_from_slice(&offset.to_ne_bytes());
+        }
+        view
+    }
+
+    #[test]
+    fn reads_offsets_and_views() {
+        let data = b"helloworld".to_vec();
+        let offsets: Vec<i32> = vec![0, 5, 5, 10];
+        let mut buffers = [ptr::null(), offsets.as_ptr() as *const c_void, data.as_ptr() as *const c_void];
+        let arr = array(2, 1, &mut buffers);
+        let (start, end) = arr.span().unwrap();
+        let values = offset_values(&arr, &arr.buffer::<i32>(1, end + 1).unwrap()[start..]).unwrap();
+        assert_eq!(values, [Bytes::new(), Bytes::from_static(b"world")]);
+
+        let long = b"a value longer than twelve bytes".to_vec();
+        let views = [view(5, b"short", 0, 0), view(long.len() as i32 - 2, b"valu", 0, 2)];
+        let lengths: Vec<i64> = vec![long.len() as i64];
+        let mut buffers = [
+            ptr::null(),
+            views.as_ptr() as *const c_void,
+            long.as_ptr() as *const c_void,
+            lengths.as_ptr() as *const c_void,
+        ];
+        let arr = array(2, 0, &mut buffers);
+        let values = view_values(&arr, &|_| true).unwrap();
+        assert_eq!(values, [Bytes::from_static(b"short"), Bytes::copy_from_slice(&long[2..])]);
+        // a null view is never read
+        assert_eq!(view_values(&arr, &|idx| idx == 0).unwrap()[1], Bytes::new());
+    }
+
+    #[test]
+    fn rejects_malformed_arrays() {
+        let data = b"hello".to_vec();
+        let offsets: Vec<i32> = vec![0, -4];
+        let mut buffers = [ptr::null(), offsets.as_ptr() as *const c_void, data.as_ptr() as *const c_void];
+        let arr = array(1, 0, &mut buffers);
+        assert!(offset_values(&arr, arr.buffer::<i32>(1, 2).unwrap()).is_err());
+        assert!(array(-1, 0, &mut buffers).span().is_err());
+        assert!(array(1, -1, &mut buffers).span().is_err());
+        let (_, end) = array(i64::MAX, i64::MAX, &mut buffers).span().unwrap();
+        assert!(array(1, 0, &mut buffers).buffer::<i32>(1, end).is_err());
+
+        // a buffer which isn't there, including when n_buffers is negative
+        let mut negative = array(1, 0, &mut buffers);
+        Arc::get_mut(&mut negative).unwrap().n_buffers = -1;
+        assert!(negative.buffer::<u8>(0, 1).is_err());
+        assert!(array(1, 0, &mut []).buffer::<u8>(0, 1).is_err());
+
+        // binary views without their lengths buffer, or pointing outside their data
+        let views = [view(20, b"", 0, 0)];
+        for n_buffers in 0..3 {
+            let mut buffers = [ptr::null(), views.as_ptr() as *const c_void, ptr::null()];
+            assert!(view_values(&array(1, 0, &mut buffers[..n_buffers]), &|_| true).is_err());
+        }
+        let lengths: Vec<i64> = vec![data.len() as i64];
+        for bad in [view(-20, b"", 0, 0), view(20, b"", 0, -4), view(20, b"", 1, 0), view(20, b"", 0, i32::MAX)] {
+            let views = [bad];
+            let mut buffers = [
+                ptr::null(),
+                views.as_ptr() as *const c_void,
+                data.as_ptr() as *const c_void,
+                lengths.as_ptr() as *const c_void,
+            ];
+            assert!(view_values(&array(1, 0, &mut buffers), &|_| true).is_err());
+        }
+        let lengths: Vec<i64> = vec![-1];
+        let mut buffers =
+            [ptr::null(), views.as_ptr() as *const c_void, data.as_ptr() as *const c_void, lengths.as_ptr() as *const c_void];
+        assert!(view_values(&array(1, 0, &mut buffers), &|_| true).is_err());
+    }
+
+    #[test]
+    fn round_trips_through_capsules() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let column = vec![Some(Bytes::from_static(b"abc")), None, Some(Bytes::new()), Some(Bytes::from_static(b"de"))];
+            let exported = ArrowArrayWrapper::binary(column.clone());
+            let contents = exported.0.clone();
+            let obj = Bound::new(py, exported).unwrap().into_any();
+            assert_eq!(binary_column(&obj).unwrap(), column);
+
+            // the same array read through a stream, as from a chunked array
+            let locals = pyo3::types::PyDict::new(py);
+            locals.set_item("array", &obj).unwrap();
+            py.run(
+                c_str!("class Stream:\n    def __arrow_c_stream__(self):\n        return array.__arrow_c_stream__()\nstream = Stream()"),
+                Some(&locals),
+                None,
+            )
+            .unwrap();
+            let stream = locals.get_item("stream").unwrap().unwrap();
+            assert_eq!(binary_column(&stream).unwrap(), column);
+
+            // the wrapper is gone and every struct exported from it released, leaving one reference
+            locals.clear();
+            drop((obj, stream, locals));
+            assert_eq!(Arc::strong_count(&contents), 1);
+
+            let splinter = Splinter::from_iter([1, 5, 1 << 20]);
+            let obj = Bound::new(py, to_arrow(&splinter)).unwrap().into_any();
+            assert_eq!(splinter_from_arrow(&obj).unwrap().iter().collect::<Vec<_>>(), [1, 5, 1 << 20]);
+            assert!(binary_column(&obj).is_err());
+
+            // a capsule can only be consumed once
+            let (_, array) = ArrowArrayWrapper::uint32(vec![1]).__arrow_c_array__(py, None).unwrap();
+            assert!(take::<ArrowArray>(&array, c"arrow_array").is_ok());
+            assert!(take::<ArrowArray>(&array, c"arrow_array").is_err());
+            assert!(take::<ArrowArray>(&array, c"arrow_schema").is_err());
+        });
+    }
+}
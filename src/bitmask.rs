@@ -0,0 +1,275 @@
+use bytes::Bytes;
+use pyo3::{exceptions::{PyOverflowError, PyValueError}, prelude::*};
+use splinter_rs::{Splinter, SplinterRef};
+
+use crate::{codec, format::{self, read_be, smallest_form, Form, Node, Parsed}};
+
+// Converting between splinters and dense bitmasks, where bit i is set if i is in the splinter,
+// as written by numpy's packbits() or held in an Arrow validity buffer.
+//
+// A bitmask is read 64 bits at a time and encoded directly into the splinter format, from the
+// bottom of the tree up: a partition with every bit set is a Full node, an empty one is left out
+// of its parent, and the rest are stored as a tree, a bitmap, a list of values or a list of
+// runs, whichever is smallest, breaking ties the way splinter-rs's optimize() does. Writing a
+// bitmask walks the partition tree the other way, filling in whole words for bitmaps, runs and
+// full partitions.
+
+// the number of 64-bit words in a block
+const BLOCK_WORDS: usize = 4;
+
+/// Reads the bit_order argument, returning whether the first bit of each byte is its highest
+pub(crate) fn msb_first(bit_order: &str) -> PyResult<bool> {
+    match bit_order {
+        "little" => Ok(false),
+        "big" => Ok(true),
+        _ => Err(PyValueError::new_err(format!("bit_order must be 'little' or 'big', but received {bit_order:?}"))),
+    }
+}
+
+/// Reads a bitmask into a splinter, in which value i is present if bit i of the mask is set
+pub(crate) fn decode(mask: &[u8], msb_first: bool) -> PyResult<SplinterRef<Bytes>> {
+    let (mask, rest) = mask.split_at(mask.len().min(1 << 29));
+    if let Some(pos) = rest.iter().position(|&byte| byte != 0) {
+        let bit = (1u64 << 32) + 8 * pos as u64 + first_bit(rest[pos], msb_first) as u64;
+        return Err(PyOverflowError::new_err(format!(
+            "from_bitmask() received a bitmask setting bit {bit}, but Splinter elements must be between 0 and {}",
+            u32::MAX
+        )));
+    }
+
+    let words: Vec<u64> = mask
+        .chunks(8)
+        .map(|chunk| {
+            let mut bytes = [0u8; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            match msb_first {
+                true => u64::from_be_bytes(bytes).reverse_bits(),
+                false => u64::from_le_bytes(bytes),
+            }
+        })
+        .collect();
+    let mut buf = Vec::new();
+    if put_mask(&mut buf, 4, &words).is_none() {
+        return Ok(codec::encode_to_ref(&Splinter::EMPTY));
+    }
+    Ok(format::decode(buf))
+}
+
+/// Returns the position within a byte of its first set bit
+fn first_bit(byte: u8, msb_first: bool) -> u32 {
+    if msb_first { byte.leading_zeros() } else { byte.trailing_zeros() }
+}
+
+/// Appends the encoding of a partition with values `width` bytes wide, holding the bits of
+/// words, which may stop short of the end of the partition, in whichever form is smallest.
+///
+/// Returns the number of values and runs of values in the partition, or None, having appended
+/// nothing, if no bit is set.
+fn put_mask(buf: &mut Vec<u8>, width: usize, words: &[u64]) -> Option<(usize, usize)> {
+    let full_len = BLOCK_WORDS << (8 * (width - 1));
+    if words.len() == full_len && words.iter().all(|&word| word == u64::MAX) {
+        format::put_full(buf);
+        return Some((64 * full_len, 1));
+    }
+
+    let start = buf.len();
+    let (count, num_runs, tree_size) = if width == 1 {
+        // a run starts at every set bit whose predecessor, possibly in the previous word, isn't set
+        let mut carry = 0;
+        let (mut count, mut num_runs) = (0, 0);
+        for &word in words {
+            count += word.count_ones() as usize;
+            num_runs += (word & !(word << 1 | carry)).count_ones() as usize;
+            carry = word >> 63;
+        }
+        // blocks can't hold trees
+        (count, num_runs, usize::MAX)
+    } else {
+        let (mut count, mut num_runs) = (0, 0);
+        let mut children = Vec::new();
+        let chunks: Vec<&[u64]> = words.chunks(full_len >> 8).collect();
+        for (segment, child) in chunks.iter().enumerate() {
+            let Some((child_count, child_runs)) = put_mask(buf, width - 1, child) else { continue };
+            children.push((segment as u8, buf.len()));
+            count += child_count;
+            num_runs += child_runs;
+            // a run reaching the end of one child carries on into the next if it starts with one
+            let ends_set = child.len() == full_len >> 8 && child[child.len() - 1] >> 63 == 1;
+            if ends_set && chunks.get(segment + 1).is_some_and(|next| next[0] & 1 == 1) {
+                num_runs -= 1;
+            }
+        }
+        if children.is_empty() {
+            return None;
+        }
+        format::put_tree(buf, width, &children);
+        (count, num_runs, buf.len() - start)
+    };
+    if count == 0 {
+        return None;
+    }
+
+    let form = smallest_form(width, count as u64, num_runs, tree_size);
+    if form == Form::Tree {
+        return Some((count, num_runs));
+    }
+    buf.truncate(start);
+    match form {
+        Form::Vec => format::put_vec(buf, width, set_bits(words)),
+        Form::Bitmap => format::put_bitmap(buf, width, words),
+        _ => {
+            let mut values = set_bits(words).peekable();
+            let runs = std::iter::from_fn(|| {
+                let start = values.next()?;
+                let mut end = start;
+                while values.next_if_eq(&(end + 1)).is_some() {
+                    end += 1;
+                }
+                Some((start, end))
+            });
+            format::put_run(buf, width, runs);
+        }
+    }
+    Some((count, num_runs))
+}
+
+/// Iterates over the positions of the set bits of words, in ascending order
+fn set_bits(words: &[u64]) -> impl Iterator<Item = u32> + '_ {
+    words.iter().enumerate().flat_map(|(i, &word)| {
+        std::iter::successors(Some(word).filter(|&w| w != 0), |&w| Some(w & (w - 1)).filter(|&w| w != 0))
+            .map(move |w| 64 * i as u32 + w.trailing_zeros())
+    })
+}
+
+/// Reads the length argument of to_bitmask(), which defaults to just past the largest value
+pub(crate) fn mask_len(max: Option<u32>, length: Option<u64>) -> PyResult<u64> {
+    let min_len = max.map_or(0, |max| max as u64 + 1);
+    match length {
+        None => Ok(min_len),
+        Some(length) if length >= min_len => Ok(length),
+        Some(length) => Err(PyValueError::new_err(format!(
+            "to_bitmask() received a length of {length}, but the largest element is {}",
+            min_len - 1
+        ))),
+    }
+}
+
+/// Writes an encoded splinter as a bitmask of `length` bits, every value of which is below length
+pub(crate) fn encode(splinter: &[u8], length: u64, msb_first: bool) -> Vec<u8> {
+    let mut words = vec![0u64; length.div_ceil(64) as usize];
+    fill(&Parsed::new(splinter).root(), 4, 0, &mut words);
+    let mut out: Vec<u8> = words
+        .iter()
+        .flat_map(|&word| match msb_first {
+            true => word.reverse_bits().to_be_bytes(),
+            false => word.to_le_bytes(),
+        })
+        .collect();
+    out.truncate(length.div_ceil(8) as usize);
+    out
+}
+
+/// Sets the bits of every value in a partition
+fn fill(node: &Node, width: usize, base: u32, words: &mut [u64]) {
+    match node {
+        Node::Empty => {}
+        Node::Full => set_range(words, base as u64, base as u64 + (1 << (8 * width)) - 1),
+        // bitmaps share the layout of a little-endian bitmask, and start on a word boundary
+        Node::Bitmap(bitmap) => {
+            let start = (base / 64) as usize;
+            for (word, chunk) in words.iter_mut().skip(start).zip(bitmap.chunks(8)) {
+                *word |= u64::from_le_bytes(chunk.try_into().expect("bitmaps are whole words"));
+            }
+        }
+        Node::Vec(values) => {
+            for value in values.chunks(width).map(|v| (base | read_be(v)) as usize) {
+                words[value / 64] |= 1 << (value % 64);
+            }
+        }
+        Node::Run(runs) => {
+            for run in runs.chunks(2 * width) {
+                set_range(words, (base | read_be(&run[..width])) as u64, (base | read_be(&run[width..])) as u64);
+            }
+        }
+        Node::Tree { .. } => {
+            let shift = 8 * (width - 1) as u32;
+            for (segment, child) in node.children(width) {
+                fill(&child, width - 1, base | (segment as u32) << shift, words);
+            }
+        }
+    }
+}
+
+/// Sets every bit from lo to hi inclusive, a word at a time
+pub(crate) fn set_range(words: &mut [u64], lo: u64, hi: u64) {
+    let (first, last) = ((lo / 64) as usize, (hi / 64) as usize);
+    for (i, word) in words.iter_mut().enumerate().take(last + 1).skip(first) {
+        let from = if i == first { lo % 64 } else { 0 };
+        let to = if i == last { hi % 64 } else { 63 };
+        *word |= (u64::MAX >> (63 - to + from)) << from;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use splinter_rs::{PartitionRead, Splinter};
+
+    use super::*;
+    use crate::testing::{cases, optimized};
+
+    #[test]
+    fn round_trips_in_both_bit_orders() {
+        // every case but those reaching the top of the range, whose bitmasks take 512 MiB
+        for splinter in cases().into_iter().filter(|splinter| splinter.last().is_none_or(|max| max < 1 << 25)) {
+            let values: Vec<u32> = splinter.iter().collect();
+            let length = bitmask_len(&splinter);
+            let bytes = codec::encode(&splinter);
+            for msb_first in [false, true] {
+                let mask = encode(&bytes, length, msb_first);
+                assert_eq!(mask.len() as u64, length.div_ceil(8));
+                let decoded = decode(&mask, msb_first).unwrap();
+                assert_eq!(decoded.iter().collect::<Vec<_>>(), values);
+                // decoding picks the smallest form of each partition, which optimize() may miss
+                assert!(decoded.inner().len() <= bytes.len());
+            }
+        }
+    }
+
+    fn bitmask_len(splinter: &Splinter) -> u64 {
+        mask_len(splinter.last(), None).unwrap()
+    }
+
+    #[test]
+    fn reads_bit_orders() {
+        assert_eq!(decode(&[0b0000_0101, 0b1000_0000], false).unwrap().iter().collect::<Vec<_>>(), [0, 2, 15]);
+        assert_eq!(decode(&[0b0000_0101, 0b1000_0000], true).unwrap().iter().collect::<Vec<_>>(), [5, 7, 8]);
+        assert!(msb_first("middle").is_err());
+    }
+
+    #[test]
+    fn handles_lengths_which_are_not_whole_bytes() {
+        let splinter = optimized([0, 3, 12]);
+        let bytes = codec::encode(&splinter);
+        let little = encode(&bytes, 13, false);
+        assert_eq!(little, [0b0000_1001, 0b0001_0000]);
+        let big = encode(&bytes, 13, true);
+        assert_eq!(big, [0b1001_0000, 0b0000_1000]);
+        for (mask, msb_first) in [(little, false), (big, true)] {
+            assert_eq!(decode(&mask, msb_first).unwrap().iter().collect::<Vec<_>>(), [0, 3, 12]);
+        }
+
+        // a mask ending partway through a word or a block
+        let mask = vec![0xFF; 1001];
+        assert_eq!(decode(&mask, false).unwrap().iter().collect::<Vec<_>>(), (0..8008).collect::<Vec<_>>());
+        assert_eq!(encode(&codec::encode(&optimized(0..8008)), 8008, true), mask);
+    }
+
+    #[test]
+    fn checks_lengths() {
+        assert_eq!(mask_len(None, None).unwrap(), 0);
+        assert_eq!(mask_len(Some(12), None).unwrap(), 13);
+        assert_eq!(mask_len(Some(12), Some(100)).unwrap(), 100);
+        assert!(mask_len(Some(12), Some(12)).is_err());
+        assert!(encode(&codec::encode(&Splinter::EMPTY), 0, false).is_empty());
+    }
+}
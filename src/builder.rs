@@ -0,0 +1,270 @@
+use pyo3::{exceptions::{PyTypeError, PyValueError}, prelude::*};
+use splinter_rs::{CowSplinter, PartitionRead, PartitionWrite, Splinter};
+
+use crate::{iter::{count_within, value_range}, ranges, SplinterWrapper};
+
+// splinter-rs inserts values several times faster in ascending order than in arbitrary order, so
+// unsorted values are buffered and sorted before they are inserted, this many at a time. Tests
+// use a smaller buffer, so that they fill it quickly
+const MAX_PENDING: usize = if cfg!(test) { 1 << 12 } else { 1 << 22 };
+
+/// Accumulates insertions and removals, and builds an optimized Splinter from them all at once.
+///
+/// Splinter.add() and friends optimize the Splinter after every call, which visits every
+/// element. A SplinterBuilder defers that work to a single pass in build(), and sorts values
+/// before inserting them. It can also be used as a context manager, as in
+/// `with SplinterBuilder() as b:`, which builds the Splinter on exit so that a later build()
+/// only has to copy it.
+///
+/// Args:
+///     sorted (bool): Promise that values are added in ascending order. They are then inserted
+///     straight away instead of being buffered and sorted. Adding a value smaller than one
+///     added before it raises a ValueError.
+#[pyclass(name="SplinterBuilder", module="splynters")]
+pub struct SplinterBuilder {
+    splinter: Splinter,
+    // inserted values waiting to be sorted into the splinter, unless the input is sorted
+    pending: Vec<u32>,
+    sorted: bool,
+    // the largest value added so far, when the input is sorted
+    last: Option<u32>,
+    // whether the splinter has changed since it was last optimized
+    dirty: bool,
+}
+
+impl SplinterBuilder {
+    fn push(&mut self, value: u32) -> PyResult<()> {
+        if !self.sorted {
+            self.pending.push(value);
+            if self.pending.len() >= MAX_PENDING {
+                self.flush();
+            }
+            return Ok(());
+        }
+        self.check_order(value)?;
+        self.last = Some(value);
+        self.splinter.insert(value);
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn check_order(&self, value: u32) -> PyResult<()> {
+        match self.last {
+            Some(last) if value < last => Err(PyValueError::new_err(format!(
+                "SplinterBuilder(sorted=True) received {value} after {last}, but values must be added in ascending order"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.pending.sort_unstable();
+        self.pending.dedup();
+        for &value in &self.pending {
+            self.splinter.insert(value);
+        }
+        self.pending.clear();
+        self.dirty = true;
+    }
+}
+
+#[pymethods]
+impl SplinterBuilder {
+    #[new]
+    #[pyo3(signature = (sorted = false))]
+    pub fn __new__(sorted: bool) -> Self {
+        Self { splinter: Splinter::EMPTY, pending: Vec::new(), sorted, last: None, dirty: false }
+    }
+
+    /// Queues a value to be inserted into the Splinter.
+    ///
+    /// This method is overloaded. It can accept either a single integer or any iterable of
+    /// integers. A `range` with a step of 1 or -1 is inserted as a whole.
+    ///
+    /// Args:
+    ///     values (int | Iterable[int] | range): The value or values to insert.
+    pub fn add(&mut self, values: &Bound<PyAny>) -> PyResult<()> {
+        if let Ok(value) = values.extract::<u32>() {
+            self.push(value)
+        } else if let Some((start, stop)) = ranges::contiguous(values)? {
+            let range = ranges::insertable(start, stop, "add")?;
+            if self.sorted {
+                self.check_order(*range.start())?;
+                self.last = Some(*range.end());
+            }
+            self.flush();
+            ranges::insert_range(&mut self.splinter, range);
+            self.dirty = true;
+            Ok(())
+        } else if let Ok(iter) = values.try_iter() {
+            iter.into_iter().try_for_each(|value| self.push(value?.extract()?))
+        } else {
+            Err(PyTypeError::new_err(
+                format!(
+                    "add() argument must be an integer or an iterable of integers, but received an object of type {:#?}",
+                    values.get_type().name()?
+                )
+            ))
+        }
+    }
+
+    /// Removes a value from the Splinter, or does nothing if the value is missing.
+    ///
+    /// Values added before the call are removed, while values added after it are kept.
+    ///
+    /// Args:
+    ///     values (int | Iterable[int] | range): The value or values to remove.
+    pub fn discard(&mut self, values: &Bound<PyAny>) -> PyResult<()> {
+        if let Some((start, stop)) = ranges::contiguous(values)? {
+            self.flush();
+            let range = value_range(start, stop);
+            if count_within(&self.splinter, &range) > 0 {
+                ranges::remove_range(&mut self.splinter, range);
+                self.dirty = true;
+            }
+            return Ok(());
+        }
+        let mut removed: Vec<u32> = if let Ok(value) = values.extract::<u32>() {
+            vec![value]
+        } else if let Ok(iter) = values.try_iter() {
+            iter.into_iter().map(|value| value?.extract()).collect::<PyResult<_>>()?
+        } else {
+            return Err(PyTypeError::new_err(
+                format!(
+                    "discard() argument must be an integer or an iterable of integers, but received an object of type {:#?}",
+                    values.get_type().name()?
+                )
+            ));
+        };
+        self.flush();
+        removed.sort_unstable();
+        for value in removed {
+            self.dirty |= self.splinter.remove(value);
+        }
+        Ok(())
+    }
+
+    /// Inserts any queued values, optimizes the result, and returns it as a new Splinter.
+    ///
+    /// The builder keeps its contents, so it can go on being used and built again. Building
+    /// again without further changes skips the optimization.
+    ///
+    /// Returns:
+    ///     Splinter: A Splinter holding every value added and not since discarded
+    pub fn build(&mut self) -> SplinterWrapper {
+        self.flush();
+        if self.dirty {
+            ranges::optimize(&mut self.splinter);
+            self.dirty = false;
+        }
+        SplinterWrapper::new(CowSplinter::from_owned(self.splinter.clone()))
+    }
+
+    fn __len__(&mut self) -> usize {
+        self.flush();
+        self.splinter.cardinality()
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> { slf }
+
+    // builds the splinter on a clean exit, so that build() afterwards only has to copy it
+    fn __exit__(&mut self, exc_type: &Bound<PyAny>, _exc_value: &Bound<PyAny>, _traceback: &Bound<PyAny>) {
+        if exc_type.is_none() {
+            self.build();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use pyo3::{ffi::c_str, types::PyDict, IntoPyObjectExt};
+
+    use super::*;
+    use crate::testing::run_python;
+
+    #[test]
+    fn applies_adds_and_discards_in_order_across_a_flush() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut builder = SplinterBuilder::__new__(false);
+            let value = |value: u32| value.into_bound_py_any(py).unwrap();
+            let values = |values: Vec<u32>| values.into_bound_py_any(py).unwrap();
+            builder.add(&value(7)).unwrap();
+            builder.discard(&value(7)).unwrap();
+            builder.add(&value(7)).unwrap();
+            builder.discard(&values(vec![7])).unwrap();
+            // the last of these fills the buffer, which sorts and inserts them
+            for value in (1..=MAX_PENDING as u32).rev() {
+                builder.push(value * 2).unwrap();
+            }
+            assert!(builder.pending.is_empty());
+            builder.add(&value(3)).unwrap();
+            builder.discard(&values(vec![3, 4, 6])).unwrap();
+            builder.add(&value(4)).unwrap();
+            builder.discard(&py.eval(c_str!("range(100, 200)"), None, None).unwrap()).unwrap();
+            builder.add(&value(150)).unwrap();
+
+            let mut expected: BTreeSet<u32> = (1..=MAX_PENDING as u32).map(|value| value * 2).collect();
+            expected.retain(|&value| value != 6 && !(100..200).contains(&value));
+            expected.extend([4, 150]);
+            let built = builder.build();
+            assert_eq!(built.0.cardinality(), expected.len());
+            assert!(built.0.iter().eq(expected.iter().copied()));
+        });
+    }
+
+    #[test]
+    fn rejects_values_out_of_order_when_sorted() {
+        run_python(c_str!(
+            r#"
+b = SplinterBuilder(sorted=True)
+b.add([1, 5])
+b.add(5)
+for bad in [3, [6, 4], range(2, 4)]:
+    try:
+        b.add(bad)
+        raise AssertionError("add() accepted", bad)
+    except ValueError as e:
+        assert "ascending order" in str(e), e
+b.add(range(7, 10))
+try:
+    b.add(8)
+    raise AssertionError("add() accepted a value inside a range added before it")
+except ValueError:
+    pass
+# values before the one out of order are kept
+assert b.build().to_list() == [1, 5, 6, 7, 8, 9]
+assert SplinterBuilder().add([3, 1, 2]) is None
+"#
+        ));
+    }
+
+    #[test]
+    fn builds_on_exit_unless_an_exception_was_raised() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let builder = Py::new(py, SplinterBuilder::__new__(false)).unwrap();
+            let locals = PyDict::new(py);
+            locals.set_item("b", &builder).unwrap();
+            py.run(c_str!("with b:\n    b.add([3, 1, 2])"), None, Some(&locals)).unwrap();
+            {
+                let built = builder.borrow(py);
+                assert!(built.pending.is_empty() && !built.dirty);
+                assert_eq!(built.splinter.iter().collect::<Vec<_>>(), [1, 2, 3]);
+            }
+            // a later build() has nothing left to optimize
+            assert_eq!(builder.borrow_mut(py).build().0.cardinality(), 3);
+
+            let code = c_str!("try:\n    with b:\n        b.add([5, 4])\n        raise KeyError\nexcept KeyError:\n    pass");
+            py.run(code, None, Some(&locals)).unwrap();
+            let unbuilt = builder.borrow(py);
+            assert_eq!(unbuilt.pending, [5, 4]);
+            assert_eq!(unbuilt.splinter.cardinality(), 3);
+        });
+    }
+}
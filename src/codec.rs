@@ -0,0 +1,157 @@
+use std::{ops::{BitAndAssign, Deref}, sync::OnceLock};
+
+use bytes::Bytes;
+use splinter_rs::{level::High, CowSplinter, Encodable, PartitionRead, Splinter, SplinterRef};
+
+use crate::{format, ranges};
+
+// Encoding splinters into bytes.
+//
+// splinter-rs 0.7 can't read back its own encoding of a tree with exactly 32 children, so owned
+// splinters are encoded through here instead, which fixes up those trees as described in format.
+
+/// Encodes an owned splinter into bytes which splinter-rs decodes back to the same values
+pub(crate) fn encode(splinter: &Splinter) -> Bytes { format::list_segments(splinter.encode_to_bytes()) }
+
+/// Encodes an owned splinter straight into a SplinterRef
+pub(crate) fn encode_to_ref(splinter: &Splinter) -> SplinterRef<Bytes> {
+    SplinterRef::from_bytes(encode(splinter)).expect("encoded splinters are always valid")
+}
+
+/// Returns the bytes of a splinter, which are shared as-is if it's already serialized
+pub(crate) fn encode_cow(splinter: &CowSplinter<Bytes>) -> Bytes {
+    match splinter {
+        CowSplinter::Ref(splinter_ref) => splinter_ref.inner().clone(),
+        CowSplinter::Owned(splinter) => encode(splinter),
+    }
+}
+
+/// A splinter along with its encoding, which is computed the first time it's needed and dropped
+/// whenever the splinter changes, so that read-only queries on an owned splinter only pay for
+/// encoding it once between modifications.
+///
+/// The splinter can only be modified through `to_mut()`, which drops the encoding.
+#[derive(Clone, Default)]
+pub(crate) struct CachedSplinter {
+    splinter: CowSplinter<Bytes>,
+    encoded: OnceLock<Bytes>,
+}
+
+impl CachedSplinter {
+    /// Returns the bytes of the splinter, encoding it if it's owned and has changed since it was
+    /// last encoded
+    pub(crate) fn encoded(&self) -> Bytes {
+        match &self.splinter {
+            CowSplinter::Ref(splinter_ref) => splinter_ref.inner().clone(),
+            CowSplinter::Owned(splinter) => self.encoded.get_or_init(|| encode(splinter)).clone(),
+        }
+    }
+
+    /// Returns the bytes of the splinter if they're at hand, without encoding it
+    pub(crate) fn encoded_if_cached(&self) -> Option<Bytes> {
+        match &self.splinter {
+            CowSplinter::Ref(splinter_ref) => Some(splinter_ref.inner().clone()),
+            CowSplinter::Owned(_) => self.encoded.get().cloned(),
+        }
+    }
+
+    /// Borrows the splinter for modification, decoding it first if it's serialized
+    pub(crate) fn to_mut(&mut self) -> &mut Splinter {
+        self.encoded.take();
+        self.splinter.to_mut()
+    }
+
+    /// Optimizes the splinter with ranges::optimize(), keeping the encoding it comes out with
+    pub(crate) fn optimize(&mut self) {
+        let encoded = ranges::optimize(self.to_mut());
+        self.encoded.get_or_init(|| encoded);
+    }
+}
+
+impl From<CowSplinter<Bytes>> for CachedSplinter {
+    fn from(splinter: CowSplinter<Bytes>) -> Self { Self { splinter, encoded: OnceLock::new() } }
+}
+
+impl From<CachedSplinter> for Splinter {
+    fn from(cached: CachedSplinter) -> Self { cached.splinter.into() }
+}
+
+// two splinters are equal if they hold the same values, whether or not either has been encoded
+impl PartialEq for CachedSplinter {
+    fn eq(&self, other: &Self) -> bool { self.splinter == other.splinter }
+}
+
+impl Deref for CachedSplinter {
+    type Target = CowSplinter<Bytes>;
+
+    fn deref(&self) -> &Self::Target { &self.splinter }
+}
+
+impl BitAndAssign<&CachedSplinter> for Splinter {
+    fn bitand_assign(&mut self, rhs: &CachedSplinter) { *self &= &rhs.splinter }
+}
+
+impl PartitionRead<High> for CachedSplinter {
+    fn cardinality(&self) -> usize { self.splinter.cardinality() }
+    fn is_empty(&self) -> bool { self.splinter.is_empty() }
+    fn contains(&self, value: u32) -> bool { self.splinter.contains(value) }
+    fn position(&self, value: u32) -> Option<usize> { self.splinter.position(value) }
+    fn rank(&self, value: u32) -> usize { self.splinter.rank(value) }
+    fn select(&self, idx: usize) -> Option<u32> { self.splinter.select(idx) }
+    fn last(&self) -> Option<u32> { self.splinter.last() }
+    fn iter(&self) -> impl Iterator<Item = u32> { self.splinter.iter() }
+}
+
+#[cfg(test)]
+mod tests {
+    use splinter_rs::PartitionWrite;
+
+    use super::*;
+    use crate::{format::{BITMAP, EMPTY, FULL, RUN, TREE, VEC}, testing::{cases, kinds, optimized}};
+
+    #[test]
+    fn round_trips_every_kind_of_partition() {
+        let mut seen = Vec::new();
+        for splinter in cases() {
+            let bytes = encode(&splinter);
+            let decoded = SplinterRef::from_bytes(bytes.clone()).unwrap();
+            assert_eq!(decoded.iter().collect::<Vec<_>>(), splinter.iter().collect::<Vec<_>>());
+            assert_eq!(decoded.decode_to_splinter(), splinter);
+            assert_eq!(bytes.len(), splinter.encode_to_bytes().len());
+            seen.extend(kinds(&bytes));
+        }
+        for kind in [EMPTY, FULL, BITMAP, VEC, RUN, TREE] {
+            assert!(seen.iter().any(|&(k, _)| k == kind), "no partition of kind {kind}");
+        }
+        for width in 2..=4 {
+            assert!(seen.contains(&(TREE, width)), "no tree of width {width}");
+        }
+    }
+
+    #[test]
+    fn shares_the_bytes_of_serialized_splinters() {
+        let bytes = encode(&optimized(0..100));
+        let cow = CowSplinter::Ref(SplinterRef::from_bytes(bytes.clone()).unwrap());
+        assert_eq!(encode_cow(&cow).as_ptr(), bytes.as_ptr());
+        assert_eq!(encode_cow(&CowSplinter::Owned(optimized(0..100))), bytes);
+    }
+
+    #[test]
+    fn caches_the_encoding_until_modified() {
+        let mut cached = CachedSplinter::from(CowSplinter::Owned(optimized(0..100)));
+        let first = cached.encoded();
+        assert_eq!(cached.encoded().as_ptr(), first.as_ptr());
+        assert_eq!(first, encode(&optimized(0..100)));
+
+        cached.to_mut().insert(500);
+        let second = cached.encoded();
+        assert_ne!(second.as_ptr(), first.as_ptr());
+        assert_eq!(second, encode(&Splinter::from_iter((0..100).chain([500]))));
+
+        // a serialized splinter is decoded by to_mut(), and from then on encoded afresh
+        let mut cached = CachedSplinter::from(CowSplinter::Ref(SplinterRef::from_bytes(first.clone()).unwrap()));
+        assert_eq!(cached.encoded().as_ptr(), first.as_ptr());
+        cached.to_mut().remove(0);
+        assert_eq!(cached.encoded(), encode(&Splinter::from_iter(1..100)));
+    }
+}
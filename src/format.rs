@@ -0,0 +1,574 @@
+use std::borrow::Cow;
+
+use bytes::Bytes;
+use crc64fast_nvme::Digest;
+use splinter_rs::{Splinter, SplinterRef};
+
+use crate::{bitmask, codec};
+
+// The encoding of splinter-rs 0.7, read and written directly.
+//
+// splinter-rs only exposes iterators which borrow the splinter they came from, and set
+// operations, range operations and optimize() which walk a splinter value by value. The
+// iterators, set queries, range operations and conversions of this crate walk and build the
+// encoded partition tree instead, through the parser and writers here. Nothing outside of this
+// module depends on the layout of the encoding, and the tests below check both against
+// encode_to_bytes() for every kind of partition and segment list, which is why Cargo.toml pins
+// splinter-rs exactly.
+//
+// An encoded splinter is its root partition followed by a footer. A partition is read from its
+// end: its last byte gives its kind, and the bytes before hold, with values `width` bytes wide
+// and big-endian, where width is 4 at the root and shrinks by one byte at each level down:
+//
+//   Empty, Full:  nothing
+//   Bitmap:       one bit per value of the partition, least significant bit first
+//   Vec:          the values in ascending order, then their number minus one
+//   Run:          the first and last value of each run in ascending order, then their number minus one
+//   Tree:         its children one after the other, then the distance from the end of each to the
+//                 end of the last, then the leading bytes of the children, then their number
+//                 minus one, in one byte
+//
+// The leading bytes of the children of a tree are stored as a list for up to 32 children, as a
+// bitmap for more, and not at all for 256. splinter-rs 0.7 writes a bitmap for exactly 32 but
+// reads a list back, so a splinter holding such a tree comes back from its bytes with the wrong
+// values; list_segments() rewrites those bitmaps into the lists splinter-rs expects. Both take
+// 32 bytes, so nothing else moves.
+//
+// Partitions are parsed defensively: a partition which doesn't parse reads as None, and whoever
+// asked falls back to the values splinter-rs itself reads out of the splinter.
+
+// checksum and magic number at the end of every encoded splinter
+pub(crate) const FOOTER_SIZE: usize = 12;
+
+// the last four bytes of every encoded splinter
+const MAGIC: [u8; 4] = [0x59, 0x11, 0xA7, 0xE2];
+
+// partition kinds, stored in the last byte of every encoded partition
+pub(crate) const EMPTY: u8 = 0b000;
+pub(crate) const FULL: u8 = 0b001;
+pub(crate) const BITMAP: u8 = 0b010;
+pub(crate) const VEC: u8 = 0b011;
+pub(crate) const RUN: u8 = 0b100;
+pub(crate) const TREE: u8 = 0b101;
+
+/// An encoded partition, borrowed from the splinter's bytes.
+///
+/// Every slice holds big-endian values `width` bytes wide, where `width` is 4 at the root of the
+/// tree and shrinks by one byte at each level down.
+pub(crate) enum Node<'a> {
+    Empty,
+    Full,
+    Bitmap(&'a [u8]),
+    Vec(&'a [u8]),
+    // (start, end) pairs of inclusive runs
+    Run(&'a [u8]),
+    Tree { segments: Segments<'a>, offsets: &'a [u8], children: &'a [u8] },
+}
+
+/// The leading bytes of the children of a tree partition
+pub(crate) enum Segments<'a> {
+    Full,
+    Vec(&'a [u8]),
+    Bitmap(&'a [u8]),
+}
+
+pub(crate) fn read_be(bytes: &[u8]) -> u32 { bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u32) }
+
+fn split_suffix(data: &[u8], len: usize) -> Option<(&[u8], &[u8])> { Some(data.split_at(data.len().checked_sub(len)?)) }
+
+/// Splits off a length prefix, which is stored minus one
+fn split_len(data: &[u8], width: usize) -> Option<(&[u8], usize)> {
+    let (data, len) = split_suffix(data, width)?;
+    Some((data, read_be(len) as usize + 1))
+}
+
+impl<'a> Node<'a> {
+    /// Decodes the root partition of an encoded splinter, footer and all
+    pub(crate) fn root(bytes: &'a [u8]) -> Option<Self> {
+        Node::from_suffix(&bytes[..bytes.len().checked_sub(FOOTER_SIZE)?], 4)
+    }
+
+    /// Decodes the partition stored at the end of data, or returns None if it's cut short or of
+    /// an unknown kind. Its children are only decoded when they're read.
+    pub(crate) fn from_suffix(data: &'a [u8], width: usize) -> Option<Self> {
+        let (&kind, data) = data.split_last()?;
+        Some(match kind {
+            EMPTY => Node::Empty,
+            FULL => Node::Full,
+            BITMAP => Node::Bitmap(split_suffix(data, 1 << (8 * width - 3))?.1),
+            VEC => {
+                let (data, len) = split_len(data, width)?;
+                Node::Vec(split_suffix(data, len * width)?.1)
+            }
+            RUN => {
+                let (data, len) = split_len(data, width)?;
+                Node::Run(split_suffix(data, 2 * len * width)?.1)
+            }
+            // the partitions at the bottom of the tree hold their values themselves
+            TREE if width > 1 => {
+                let (data, num_children) = split_len(data, 1)?;
+                // like splinter-rs, up to 32 children are read as a list, which list_segments()
+                // makes sure of for trees with exactly 32
+                let (data, segments) = match num_children {
+                    256 => (data, Segments::Full),
+                    n if n <= 32 => { let (data, s) = split_suffix(data, n)?; (data, Segments::Vec(s)) }
+                    n => {
+                        let (data, s) = split_suffix(data, 32)?;
+                        if s.iter().map(|b| b.count_ones() as usize).sum::<usize>() != n { return None }
+                        (data, Segments::Bitmap(s))
+                    }
+                };
+                let (children, offsets) = split_suffix(data, num_children * width)?;
+                Node::Tree { segments, offsets, children }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Returns true if every partition below this one parses
+    pub(crate) fn well_formed(&self, width: usize) -> bool {
+        let Node::Tree { offsets, children, .. } = self else { return true };
+        (0..offsets.len() / width).all(|idx| {
+            child_data(offsets, children, idx, width)
+                .and_then(|data| Node::from_suffix(data, width - 1))
+                .is_some_and(|child| child.well_formed(width - 1))
+        })
+    }
+
+    /// Yields the leading byte and partition of each child of a tree partition, in order, and
+    /// nothing for any other kind of partition. Children which don't parse are skipped, which
+    /// never happens below the root of a Parsed splinter.
+    pub(crate) fn children(&self, width: usize) -> impl Iterator<Item = (u8, Node<'a>)> + '_ {
+        let tree = match self {
+            Node::Tree { segments, offsets, children } => Some((segments, *offsets, *children)),
+            _ => None,
+        };
+        tree.into_iter().flat_map(move |(segments, offsets, children)| {
+            segments.iter().enumerate().filter_map(move |(idx, segment)| {
+                Some((segment, Node::from_suffix(child_data(offsets, children, idx, width)?, width - 1)?))
+            })
+        })
+    }
+
+    /// Returns the child of a tree partition with the given leading byte, if it has one
+    pub(crate) fn child(&self, width: usize, segment: u8) -> Option<Node<'a>> {
+        match self {
+            Node::Tree { segments, offsets, children } => segments
+                .position(segment)
+                .and_then(|idx| Node::from_suffix(child_data(offsets, children, idx, width)?, width - 1)),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the encoded child at idx of a tree partition, or None if its offset is out of bounds
+pub(crate) fn child_data<'a>(offsets: &[u8], children: &'a [u8], idx: usize, width: usize) -> Option<&'a [u8]> {
+    // offsets count backwards from the end of the children
+    let offset = read_be(offsets.get(idx * width..(idx + 1) * width)?) as usize;
+    children.get(..children.len().checked_sub(offset)?)
+}
+
+impl Segments<'_> {
+    pub(crate) fn to_vec(&self) -> Vec<u8> {
+        match self {
+            Segments::Full => (0..=u8::MAX).collect(),
+            Segments::Vec(segments) => segments.to_vec(),
+            Segments::Bitmap(bitmap) => (0..=u8::MAX).filter(|&s| bitmap[s as usize / 8] & (1 << (s % 8)) != 0).collect(),
+        }
+    }
+
+    pub(crate) fn contains(&self, segment: u8) -> bool {
+        match self {
+            Segments::Full => true,
+            Segments::Vec(segments) => segments.binary_search(&segment).is_ok(),
+            Segments::Bitmap(bitmap) => bitmap[segment as usize / 8] & (1 << (segment % 8)) != 0,
+        }
+    }
+
+    /// Yields the segments in ascending order, without collecting them like to_vec()
+    pub(crate) fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        // lists are read directly, while the other kinds are tested segment by segment
+        let (list, unlisted): (&[u8], usize) = match self {
+            Segments::Vec(segments) => (segments, 0),
+            _ => (&[], 256),
+        };
+        list.iter().copied().chain((0..=u8::MAX).take(unlisted).filter(|&s| self.contains(s)))
+    }
+
+    /// Returns the index of the child with the given leading byte
+    pub(crate) fn position(&self, segment: u8) -> Option<usize> {
+        match self {
+            Segments::Full => Some(segment as usize),
+            Segments::Vec(segments) => segments.binary_search(&segment).ok(),
+            Segments::Bitmap(bitmap) => self.contains(segment).then(|| {
+                let (byte, bit) = (segment as usize / 8, segment % 8);
+                let before: u32 = bitmap[..byte].iter().map(|b| b.count_ones()).sum();
+                (before + (bitmap[byte] & ((1 << bit) - 1)).count_ones()) as usize
+            }),
+        }
+    }
+}
+
+/// Re-encodes a splinter from the values splinter-rs reads out of it, for when its partitions
+/// don't parse here. splinter-rs checks the root of every splinter it's handed, so bytes which
+/// it rejects never make it into one; they read as empty.
+pub(crate) fn reencode(bytes: &[u8]) -> Bytes {
+    let splinter = SplinterRef::from_bytes(Bytes::copy_from_slice(bytes)).map_or(Splinter::EMPTY, |s| s.decode_to_splinter());
+    codec::encode(&splinter)
+}
+
+/// An encoded splinter every partition of which is known to parse, for the walks over the
+/// whole tree in overlap, bitmask, roaring and ranges. Encodings which don't parse are
+/// replaced with what splinter-rs reads out of them.
+pub(crate) struct Parsed<'a>(Cow<'a, [u8]>);
+
+impl<'a> Parsed<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        match Node::root(bytes).is_some_and(|root| root.well_formed(4)) {
+            true => Parsed(Cow::Borrowed(bytes)),
+            false => Parsed(Cow::Owned(reencode(bytes).into())),
+        }
+    }
+
+    /// The root partition, which only reads as empty if codec::encode wrote something unreadable
+    pub(crate) fn root(&self) -> Node<'_> { Node::root(&self.0).unwrap_or(Node::Empty) }
+}
+
+/// Rewrites the segments of every tree with 32 children in the bytes splinter-rs encoded from
+/// a splinter, which it stores as a bitmap, into the list it reads them back as
+pub(crate) fn list_segments(bytes: Bytes) -> Bytes {
+    let mut bitmaps = Vec::new();
+    find_bitmaps(&bytes, &bytes[..bytes.len() - FOOTER_SIZE], 4, &mut bitmaps);
+    if bitmaps.is_empty() {
+        return bytes;
+    }
+
+    let mut data = Vec::from(bytes);
+    data.truncate(data.len() - FOOTER_SIZE);
+    for pos in bitmaps {
+        let segments = &mut data[pos..pos + 32];
+        let bitmap: [u8; 32] = segments.try_into().expect("segment stores are 32 bytes");
+        let list = (0..=u8::MAX).filter(|&s| bitmap[s as usize / 8] & (1 << (s % 8)) != 0);
+        segments.iter_mut().zip(list).for_each(|(byte, segment)| *byte = segment);
+    }
+    finish(data)
+}
+
+/// Collects the position within buf of the segments of every tree in data with 32 children
+fn find_bitmaps(buf: &[u8], data: &[u8], width: usize, out: &mut Vec<usize>) {
+    let Some(Node::Tree { segments, offsets, children }) = Node::from_suffix(data, width) else { return };
+    if let Segments::Vec(segments) = segments {
+        if segments.len() == 32 {
+            out.push(segments.as_ptr() as usize - buf.as_ptr() as usize);
+        }
+    }
+    for data in (0..offsets.len() / width).filter_map(|idx| child_data(offsets, children, idx, width)) {
+        find_bitmaps(buf, data, width - 1, out);
+    }
+}
+
+/// Appends the checksum and magic number which end every encoded splinter
+pub(crate) fn finish(mut data: Vec<u8>) -> Bytes {
+    let mut checksum = Digest::new();
+    checksum.write(&data);
+    data.extend(checksum.sum64().to_le_bytes());
+    data.extend(MAGIC);
+    Bytes::from(data)
+}
+
+/// Finishes an encoded root partition written with the functions below into a SplinterRef
+pub(crate) fn decode(buf: Vec<u8>) -> SplinterRef<Bytes> {
+    SplinterRef::from_bytes(finish(buf)).expect("the partitions written here are always valid")
+}
+
+fn put_value(buf: &mut Vec<u8>, width: usize, value: u32) { buf.extend_from_slice(&value.to_be_bytes()[4 - width..]) }
+
+/// Appends a full partition
+pub(crate) fn put_full(buf: &mut Vec<u8>) { buf.push(FULL) }
+
+/// Appends a partition with values `width` bytes wide holding a list of values, which must be
+/// ascending and of which there must be at least one
+pub(crate) fn put_vec(buf: &mut Vec<u8>, width: usize, values: impl IntoIterator<Item = u32>) {
+    let mut len = 0;
+    values.into_iter().for_each(|value| {
+        put_value(buf, width, value);
+        len += 1;
+    });
+    put_value(buf, width, len - 1);
+    buf.push(VEC);
+}
+
+/// Appends a bitmap partition with values `width` bytes wide, in which value i is present if bit
+/// i of words is set. Words may stop short of the end of the partition.
+pub(crate) fn put_bitmap(buf: &mut Vec<u8>, width: usize, words: &[u64]) {
+    let end = buf.len() + (1 << (8 * width - 3));
+    words.iter().for_each(|word| buf.extend(word.to_le_bytes()));
+    buf.resize(end, 0);
+    buf.push(BITMAP);
+}
+
+/// Appends a partition with values `width` bytes wide holding inclusive runs of values, which
+/// must be ascending and non-overlapping and of which there must be at least one
+pub(crate) fn put_run(buf: &mut Vec<u8>, width: usize, runs: impl IntoIterator<Item = (u32, u32)>) {
+    let mut len = 0;
+    runs.into_iter().for_each(|(lo, hi)| {
+        put_value(buf, width, lo);
+        put_value(buf, width, hi);
+        len += 1;
+    });
+    put_value(buf, width, len - 1);
+    buf.push(RUN);
+}
+
+/// Appends a partition with values `width` bytes wide holding the given runs, with every child
+/// they cover completely stored as a full partition and partial blocks as runs.
+///
+/// The runs are inclusive, relative to the start of the partition, sorted and non-overlapping.
+pub(crate) fn put_runs(buf: &mut Vec<u8>, width: usize, runs: &[(u32, u32)]) {
+    let shift = 8 * (width as u32 - 1);
+    let child_max = (1u32 << shift) - 1;
+    if runs == [(0, child_max | (0xFF << shift))] {
+        put_full(buf);
+        return;
+    }
+    if width == 1 {
+        // blocks can't hold trees
+        put_run(buf, width, runs.iter().copied());
+        return;
+    }
+
+    // split the runs up between the children they overlap, most of which they cover completely
+    let mut children: Vec<(u32, Vec<(u32, u32)>)> = Vec::new();
+    for &(lo, hi) in runs {
+        for segment in lo >> shift..=hi >> shift {
+            let base = segment << shift;
+            let run = (lo.max(base) - base, hi.min(base | child_max) - base);
+            match children.last_mut() {
+                Some((last, child)) if *last == segment => child.push(run),
+                _ => children.push((segment, vec![run])),
+            }
+        }
+    }
+    let mut ends = Vec::with_capacity(children.len());
+    for (segment, child) in &children {
+        put_runs(buf, width - 1, child);
+        ends.push((*segment as u8, buf.len()));
+    }
+    put_tree(buf, width, &ends);
+}
+
+/// Finishes the encoding of a tree partition with values `width` bytes wide, once its children
+/// have been appended to buf.
+///
+/// Every child is given by its segment and the position in buf where its encoding ends.
+pub(crate) fn put_tree(buf: &mut Vec<u8>, width: usize, children: &[(u8, usize)]) {
+    // offsets count backwards from the end of the children
+    let children_end = buf.len();
+    for &(_, end) in children {
+        put_value(buf, width, (children_end - end) as u32);
+    }
+    let segments = children.iter().map(|&(segment, _)| segment);
+    match children.len() {
+        256 => {}
+        n if n <= 32 => buf.extend(segments),
+        _ => {
+            let mut bitmap = [0u8; 32];
+            segments.for_each(|segment| bitmap[segment as usize / 8] |= 1 << (segment % 8));
+            buf.extend(bitmap);
+        }
+    }
+    buf.push((children.len() - 1) as u8);
+    buf.push(TREE);
+}
+
+/// The ways a partition can be stored
+#[derive(PartialEq)]
+pub(crate) enum Form {
+    Full,
+    Tree,
+    Vec,
+    Bitmap,
+    Run,
+}
+
+/// Picks the smallest way to store a partition with values `width` bytes wide, given the number
+/// of values and runs of values it holds and the size of its encoding as a tree, if it has one
+pub(crate) fn smallest_form(width: usize, count: u64, num_runs: usize, tree_size: usize) -> Form {
+    if count == 1 << (8 * width) {
+        return Form::Full;
+    }
+    // every form but the bitmap also stores its length, and splinter-rs prefers them in this
+    // order between forms of the same size
+    let sizes = [
+        (Form::Tree, tree_size),
+        (Form::Vec, (count as usize).saturating_add(1).saturating_mul(width) + 1),
+        (Form::Bitmap, (1 << (8 * width - 3)) + 1),
+        (Form::Run, (2 * num_runs + 1) * width + 1),
+    ];
+    let smallest = sizes.iter().map(|&(_, size)| size).min().expect("there are four forms");
+    sizes.into_iter().find(|&(_, size)| size == smallest).expect("one of them is smallest").0
+}
+
+/// Appends a partition with values `width` bytes wide holding the given runs in one of the forms
+/// which store values directly, rather than in children
+pub(crate) fn put_form(buf: &mut Vec<u8>, width: usize, form: Form, runs: &[(u32, u32)]) {
+    match form {
+        Form::Full => put_full(buf),
+        Form::Vec => put_vec(buf, width, runs.iter().flat_map(|&(lo, hi)| lo..=hi)),
+        Form::Bitmap => {
+            let mut words = vec![0u64; 1 << (8 * width - 6)];
+            runs.iter().for_each(|&(lo, hi)| bitmask::set_range(&mut words, lo as u64, hi as u64));
+            put_bitmap(buf, width, &words);
+        }
+        Form::Run => put_run(buf, width, runs.iter().copied()),
+        Form::Tree => unreachable!("trees are written from their children"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use splinter_rs::{Encodable, PartitionRead};
+
+    use super::*;
+    use crate::{ranges, testing::{cases, optimized}};
+
+    /// Every splinter of cases(), along with trees whose children span every kind of segment list
+    /// at every width
+    fn splinters() -> Vec<Splinter> {
+        let spread = |count: u32, shift: u32| optimized((0..count).flat_map(move |s| (0..50).map(move |v| (s << shift) | (v * 2))));
+        let mut splinters = cases();
+        for shift in [8, 16, 24] {
+            splinters.extend([spread(5, shift), spread(32, shift), spread(33, shift), spread(200, shift), spread(256, shift)]);
+        }
+        // sparse enough that a tree with every child beats a bitmap at width 2
+        splinters.push(optimized((0..256).flat_map(|s| (0..5).map(move |v| (s << 8) | (v * 40)))));
+        splinters
+    }
+
+    /// Writes a parsed partition back out with the writers above, in the form it was read in,
+    /// noting the kind and width of every partition, and how the segments of every tree are stored
+    fn rewrite(buf: &mut Vec<u8>, node: &Node, width: usize, seen: &mut Vec<(u8, &str, usize)>) {
+        let (kind, form) = match node {
+            Node::Empty => (EMPTY, None),
+            Node::Full => (FULL, Some(Form::Full)),
+            Node::Bitmap(_) => (BITMAP, Some(Form::Bitmap)),
+            Node::Vec(_) => (VEC, Some(Form::Vec)),
+            Node::Run(_) => (RUN, Some(Form::Run)),
+            Node::Tree { segments, .. } => {
+                let mut children = Vec::new();
+                for (segment, child) in node.children(width) {
+                    rewrite(buf, &child, width - 1, seen);
+                    children.push((segment, buf.len()));
+                }
+                let stored = match segments { Segments::Full => "full", Segments::Vec(_) => "list", Segments::Bitmap(_) => "bitmap" };
+                seen.push((TREE, stored, width));
+                return put_tree(buf, width, &children);
+            }
+        };
+        seen.push((kind, "", width));
+        match form {
+            Some(form) => put_form(buf, width, form, &node.runs(width)),
+            None => buf.push(EMPTY),
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_what_splinter_rs_encodes() {
+        let mut seen = Vec::new();
+        for splinter in splinters() {
+            let bytes = list_segments(splinter.encode_to_bytes());
+            let root = Node::root(&bytes).expect("splinter-rs encodings parse");
+            assert!(root.well_formed(4));
+            let runs: Vec<(u32, u32)> = root.runs(4);
+            let values: Vec<u32> = runs.iter().flat_map(|&(lo, hi)| lo..=hi).collect();
+            assert_eq!(values, splinter.iter().collect::<Vec<_>>());
+
+            // every partition written back in the form it was read in comes out byte for byte
+            let mut buf = Vec::new();
+            rewrite(&mut buf, &root, 4, &mut seen);
+            assert_eq!(finish(buf), bytes);
+        }
+        for kind in [EMPTY, FULL, BITMAP, VEC, RUN] {
+            assert!(seen.iter().any(|&(k, _, _)| k == kind), "no partition of kind {kind}");
+        }
+        for width in 2..=4 {
+            for stored in ["full", "list", "bitmap"] {
+                assert!(seen.contains(&(TREE, stored, width)), "no tree of width {width} with its segments stored as a {stored}");
+            }
+        }
+    }
+
+    #[test]
+    fn lists_the_segments_of_trees_with_32_children() {
+        for shift in [8, 16, 24] {
+            let splinter = optimized((0..32).flat_map(|s| (0..50).map(move |v| (s << shift) | (v * 2))));
+            // splinter-rs reads its own encoding of these back wrong, which list_segments() fixes
+            let raw = splinter.encode_to_bytes();
+            assert_ne!(SplinterRef::from_bytes(raw.clone()).unwrap().iter().collect::<Vec<_>>(), splinter.iter().collect::<Vec<_>>());
+            let fixed = SplinterRef::from_bytes(list_segments(raw.clone())).unwrap();
+            assert_eq!(fixed.iter().collect::<Vec<_>>(), splinter.iter().collect::<Vec<_>>());
+            assert_eq!(list_segments(raw.clone()).len(), raw.len());
+        }
+        // encodings without such trees are left as they are
+        let splinter = optimized((0..31).flat_map(|s| (0..50).map(move |v| (s << 8) | (v * 2))));
+        assert_eq!(list_segments(splinter.encode_to_bytes()), splinter.encode_to_bytes());
+    }
+
+    #[test]
+    fn writes_runs_which_splinter_rs_reads_back() {
+        for splinter in splinters() {
+            let runs = Node::root(&list_segments(splinter.encode_to_bytes())).unwrap().runs(4);
+            if runs.is_empty() {
+                continue;
+            }
+            let mut buf = Vec::new();
+            put_runs(&mut buf, 4, &runs);
+            let written = decode(buf);
+            assert_eq!(written.iter().collect::<Vec<_>>(), splinter.iter().collect::<Vec<_>>());
+            assert_eq!(written.decode_to_splinter(), splinter);
+        }
+    }
+
+    #[test]
+    fn picks_the_forms_splinter_rs_does() {
+        // a root holding each form, once splinter-rs has optimized it
+        let roots = [
+            (ranges::splinter_from_range(0..=u32::MAX), FULL),
+            (optimized([1, 5, 9]), VEC),
+            (optimized((10..20).chain(30..40)), RUN),
+            (optimized((0..5000).map(|i| i * 3)), TREE),
+        ];
+        for (splinter, kind) in roots {
+            let bytes = list_segments(splinter.encode_to_bytes());
+            assert_eq!(bytes[bytes.len() - FOOTER_SIZE - 1], kind);
+            let runs = Node::root(&bytes).unwrap().runs(4);
+            let count = runs.iter().map(|&(lo, hi)| (hi - lo) as u64 + 1).sum();
+            let tree_size = if kind == TREE { bytes.len() - FOOTER_SIZE } else { usize::MAX };
+            let form = smallest_form(4, count, runs.len(), tree_size);
+            assert!(matches!((form, kind), (Form::Full, FULL) | (Form::Vec, VEC) | (Form::Run, RUN) | (Form::Tree, TREE)));
+        }
+        // a bitmap only pays off further down, where it's smaller
+        assert!(smallest_form(1, 100, 50, usize::MAX) == Form::Bitmap);
+    }
+
+    #[test]
+    fn falls_back_to_splinter_rs() {
+        // a tree whose segment bitmap lists fewer children than it holds, which splinter-rs reads
+        // the listed ones of while this parser gives up on it
+        let splinter = optimized((0..40).flat_map(|i| (0..300).map(move |j| (i << 24) | (j * 3))));
+        let mut data = codec::encode(&splinter).to_vec();
+        assert_eq!(data[data.len() - FOOTER_SIZE - 1], TREE);
+        data.truncate(data.len() - FOOTER_SIZE);
+        let segments = data.len() - 2 - 32;
+        data[segments + 4] &= !0x80;
+        let bytes = finish(data);
+        assert!(Node::root(&bytes).is_none());
+
+        let parsed = Parsed::new(&bytes);
+        assert!(matches!(parsed.0, Cow::Owned(_)));
+        assert_eq!(parsed.root().cardinality(4), 39 * 300);
+        assert!(matches!(Parsed::new(&codec::encode(&splinter)).0, Cow::Borrowed(_)));
+        assert!(Node::root(&[0; FOOTER_SIZE - 1]).is_none());
+        assert!(Node::root(&[0; FOOTER_SIZE]).is_none());
+    }
+}
@@ -0,0 +1,575 @@
+use std::{path::PathBuf, sync::OnceLock};
+
+use bytes::Bytes;
+use numpy::{PyArray1, PyArrayDyn};
+use pyo3::{exceptions::PyValueError, prelude::*, types::{PyBytes, PyInt, PyTuple, PyType}, PyTypeInfo};
+use splinter_rs::{CowSplinter, Encodable, Optimizable, PartitionRead, Splinter, SplinterRef};
+
+use crate::{arrays, arrow, bitmask, bytes_from_buffer, codec, iter::{count_within, value_range}, ranges, contains, contains_many_parallel, get_item, last, mmap_bytes, next_absent, overlap, roaring, position, predecessor, select, successor, ArrowArrayWrapper, BoolOrVec, Operand, QueryArg, SetArg, SetOperand, SplinterArg, SplinterIter, SplinterWrapper, UintOrVec};
+
+/// An immutable Splinter which is always queried directly from its serialized form.
+///
+/// A FrozenSplinter never decodes into an owned Splinter, so read-heavy code can rely on it
+/// staying compact and zero-copy. Set operations return new FrozenSplinters.
+///
+/// FrozenSplinters are hashable, and can be used as dict keys and set members.
+#[pyclass(name="FrozenSplinter", module="splynters")]
+#[derive(Clone)]
+pub struct FrozenSplinterWrapper(
+    pub(crate) SplinterRef<Bytes>,
+    // content hash, computed on the first call to __hash__
+    OnceLock<isize>,
+);
+
+impl FrozenSplinterWrapper {
+    pub(crate) fn new(splinter_ref: SplinterRef<Bytes>) -> Self { Self(splinter_ref, OnceLock::new()) }
+
+    /// Optimizes and encodes an owned splinter into a FrozenSplinter
+    pub(crate) fn from_owned(mut splinter: Splinter) -> Self {
+        splinter.optimize();
+        Self::new(codec::encode_to_ref(&splinter))
+    }
+
+    /// Hashes the elements the way CPython hashes a frozenset, so that a FrozenSplinter hashes
+    /// the same as any frozenset it compares equal to. The hash doesn't depend on the order of
+    /// the elements, so it also agrees however the FrozenSplinter was built and encoded.
+    ///
+    /// This walks every element, rather than hashing the encoding, since only a hash of the
+    /// elements themselves can agree with frozenset's.
+    fn content_hash(&self) -> isize { frozenset_hash(self.0.iter().map(|val| int_hash(val as i128)), self.0.cardinality()) }
+}
+
+// mirrors frozenset_hash() in CPython's Objects/setobject.c, given the hashes of the elements
+pub(crate) fn frozenset_hash(hashes: impl Iterator<Item = usize>, len: usize) -> isize {
+    let shuffle = |h: usize| ((h ^ 89869747) ^ (h << 16)).wrapping_mul(3644798167);
+
+    let mut hash = hashes.fold(0, |hash, h| hash ^ shuffle(h));
+    hash ^= (len + 1).wrapping_mul(1927868237);
+    hash ^= (hash >> 11) ^ (hash >> 25);
+    hash = hash.wrapping_mul(69069).wrapping_add(907133923);
+    if hash == usize::MAX {
+        hash = 590923713;
+    }
+    hash as isize
+}
+
+/// Hashes an int the way CPython does, as its remainder modulo a Mersenne prime with the sign
+/// of the int, except that -1 is reserved for errors and becomes -2
+pub(crate) fn int_hash(value: i128) -> usize {
+    const MODULUS: u128 = if usize::BITS == 64 { (1 << 61) - 1 } else { (1 << 31) - 1 };
+    let hash = (value.unsigned_abs() % MODULUS) as isize;
+    match value < 0 {
+        true if hash == 1 => -2isize as usize,
+        true => -hash as usize,
+        false => hash as usize,
+    }
+}
+
+#[pymethods]
+impl FrozenSplinterWrapper {
+    #[new]
+    pub fn __new__() -> Self { Self::from_owned(Splinter::EMPTY) }
+    pub fn __len__(&self) -> usize { self.0.cardinality() }
+    pub fn __sizeof__(&self) -> usize { self.0.encoded_size() }
+    pub fn __repr__(&self) -> String {
+        format!("FrozenSplinterWrapper(len = {}, compressed_byte_size = {})", self.0.cardinality(), self.0.encoded_size())
+    }
+    fn __iter__(&self) -> SplinterIter { SplinterIter::frozen(&self.0, 0..=u32::MAX, false) }
+    fn __reversed__(&self) -> SplinterIter { SplinterIter::frozen(&self.0, 0..=u32::MAX, true) }
+
+    #[pyo3(signature = (value, reverse = false))]
+    /// Iterates over the elements greater than or equal to value in ascending order, or, if
+    /// reverse is set, over the elements less than or equal to value in descending order.
+    fn iter_from(&self, value: i64, reverse: bool) -> SplinterIter {
+        let range = if reverse { value_range(0, value.saturating_add(1)) } else { value_range(value, 1 << 32) };
+        SplinterIter::frozen(&self.0, range, reverse)
+    }
+
+    #[pyo3(signature = (start, stop, reverse = false))]
+    /// Iterates over the elements in the range [start, stop), in ascending order or in
+    /// descending order if reverse is set.
+    fn iter_range(&self, start: i64, stop: i64, reverse: bool) -> SplinterIter {
+        SplinterIter::frozen(&self.0, value_range(start, stop), reverse)
+    }
+
+    /// Returns an element or list of elements based on the input index or slice
+    ///
+    /// Operates according to Python's slice syntax: [start:stop:step]
+    /// Supports selection by negative indices and negative steps
+    fn __getitem__(&self, index: &Bound<PyAny>) -> PyResult<UintOrVec> { get_item(&self.0, index) }
+
+    #[staticmethod]
+    /// Constructs a FrozenSplinter from an iterator of unsigned integers.
+    ///
+    /// A `range` with a step of 1 or -1 is encoded as a whole, without visiting each value.
+    ///
+    /// Args:
+    ///     data list[int] | range: The iterator from which to construct the FrozenSplinter.
+    ///
+    /// Returns:
+    ///     FrozenSplinter: A FrozenSplinter object constructed from the input items
+    pub fn from_list(data: &Bound<PyAny>) -> PyResult<Self> {
+        if let Some((start, stop)) = ranges::contiguous(data)? {
+            return Self::from_range(start, stop);
+        }
+        Ok(Self::from_owned(Splinter::from_iter(data.extract::<Vec<u32>>()?)))
+    }
+
+    #[staticmethod]
+    /// Constructs a FrozenSplinter holding every integer in [start, stop), like
+    /// `range(start, stop)`.
+    ///
+    /// Args:
+    ///     start (int): The first value in the FrozenSplinter.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     FrozenSplinter: A FrozenSplinter containing the range
+    ///
+    /// Raises:
+    ///     OverflowError: If the range reaches outside of 0 to 2**32 - 1
+    pub fn from_range(start: i64, stop: i64) -> PyResult<Self> {
+        Ok(Self::new(ranges::encode_range(ranges::insertable(start, stop, "from_range")?)))
+    }
+    pub fn to_list(&self) -> Vec<u32> { self.0.iter().collect() }
+
+    #[staticmethod]
+    /// Constructs a FrozenSplinter from a uint32 or int64 NumPy array, reading its buffer
+    /// directly rather than converting each element to a Python int.
+    pub fn from_numpy(data: &Bound<PyAny>) -> PyResult<Self> { Ok(Self::from_owned(arrays::splinter_from_numpy(data)?)) }
+
+    /// Returns the elements of the FrozenSplinter, in ascending order, as a uint32 NumPy array.
+    pub fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<u32>>> { arrays::to_numpy(py, &self.0) }
+
+    #[staticmethod]
+    /// Constructs a FrozenSplinter from an Arrow array of type uint32, read through the Arrow
+    /// PyCapsule Interface.
+    pub fn from_arrow(array: &Bound<PyAny>) -> PyResult<Self> { Ok(Self::from_owned(arrow::splinter_from_arrow(array)?)) }
+
+    /// Returns the elements of the FrozenSplinter, in ascending order, as an Arrow array of
+    /// type uint32.
+    pub fn to_arrow(&self) -> ArrowArrayWrapper { arrow::to_arrow(&self.0) }
+
+    #[staticmethod]
+    /// Reads an Arrow binary column of serialized Splinters into a list of FrozenSplinters,
+    /// which read directly from the column's memory.
+    ///
+    /// Args:
+    ///     column: Any object with an `__arrow_c_array__` or `__arrow_c_stream__` method
+    ///     exporting a binary, large_binary or binary_view column.
+    ///
+    /// Returns:
+    ///     list[FrozenSplinter | None]: A FrozenSplinter for every element, or None for every null
+    pub fn from_arrow_binary(column: &Bound<PyAny>) -> PyResult<Vec<Option<Self>>> {
+        arrow::binary_column(column)?
+            .into_iter()
+            .enumerate()
+            .map(|(idx, bytes)| {
+                bytes.map(|bytes| match SplinterRef::from_bytes(bytes) {
+                    Ok(splinter) => Ok(Self::new(splinter)),
+                    Err(e) => Err(PyValueError::new_err(format!("FrozenSplinter could not be constructed from element {idx} of the column: {e}"))),
+                })
+                .transpose()
+            })
+            .collect()
+    }
+
+    pub fn to_bytes(&self, py: Python) -> Py<PyBytes> { PyBytes::new(py, self.0.inner()).into() }
+
+    #[classmethod]
+    /// Constructs a FrozenSplinter from raw byte data.
+    ///
+    /// Accepts any object exporting the buffer protocol. As with Splinter.from_bytes(), only a
+    /// read-only buffer is shared: the FrozenSplinter holds a reference to the source object
+    /// and reads directly from its memory. A writable buffer is copied.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): The byte data from which to construct the FrozenSplinter.
+    ///
+    /// Returns:
+    ///     FrozenSplinter: A FrozenSplinter object, or else an error explaining why
+    ///     construction failed.
+    ///
+    /// Raises:
+    ///     ValueError: If the data is not a serialized Splinter, or fails its checksum
+    pub fn from_bytes(
+        _cls: &Bound<'_, PyType>,
+        data: &Bound<'_, PyAny>,
+    ) -> PyResult<Self> {
+        let splinter_ref = SplinterRef::from_bytes(bytes_from_buffer(data)?).map_err(|e| {
+            PyValueError::new_err(format!("FrozenSplinter could not be constructed from bytes: {e}"))
+        })?;
+
+        Ok(Self::new(splinter_ref))
+    }
+
+    #[classmethod]
+    /// Opens a serialized Splinter file by memory-mapping it.
+    ///
+    /// Queries read directly from the mapped pages. Opening the file verifies its checksum,
+    /// which reads through the mapping once. The file must not be modified or truncated
+    /// while the FrozenSplinter is in use.
+    ///
+    /// Args:
+    ///     path (str | os.PathLike): The path of a file written with to_bytes().
+    ///
+    /// Returns:
+    ///     FrozenSplinter: A FrozenSplinter backed by the memory-mapped file.
+    ///
+    /// Raises:
+    ///     OSError: If the file can't be opened
+    ///     ValueError: If the file doesn't hold a serialized Splinter, such as when it's truncated
+    ///         or corrupted
+    pub fn open(
+        _cls: &Bound<'_, PyType>,
+        path: PathBuf,
+    ) -> PyResult<Self> {
+        let splinter_ref = SplinterRef::from_bytes(mmap_bytes(&path)?).map_err(|e| {
+            PyValueError::new_err(format!("FrozenSplinter could not be constructed from {}: {e}", path.display()))
+        })?;
+
+        Ok(Self::new(splinter_ref))
+    }
+
+    #[classmethod]
+    /// Constructs a FrozenSplinter from a bitmap in the Roaring portable serialization format.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): A serialized 32-bit Roaring bitmap.
+    ///
+    /// Returns:
+    ///     FrozenSplinter: A FrozenSplinter holding the same values
+    ///
+    /// Raises:
+    ///     ValueError: If the data is not a valid Roaring bitmap
+    pub fn from_roaring_bytes(
+        _cls: &Bound<'_, PyType>,
+        data: &Bound<'_, PyAny>,
+    ) -> PyResult<Self> {
+        let bytes = bytes_from_buffer(data)?;
+        let splinter = roaring::decode(&bytes).map_err(|e| {
+            PyValueError::new_err(format!("FrozenSplinter could not be constructed from Roaring bytes: {e}"))
+        })?;
+        Ok(Self::new(codec::encode_to_ref(&splinter)))
+    }
+
+    /// Serializes the FrozenSplinter in the Roaring portable serialization format, which any
+    /// Roaring library can read.
+    ///
+    /// Returns:
+    ///     bytes: The serialized Roaring bitmap
+    pub fn to_roaring_bytes(&self, py: Python) -> Py<PyBytes> { PyBytes::new(py, &roaring::encode(self.0.inner())).into() }
+
+    #[classmethod]
+    #[pyo3(signature = (data, bit_order = "little"))]
+    /// Constructs a FrozenSplinter from a dense bitmask, holding every i for which bit i of the
+    /// mask is set.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): The packed bits of the mask.
+    ///     bit_order (str): "little" if the first bit of each byte is its lowest, as in Arrow,
+    ///     or "big" if it's the highest, as in numpy's packbits() by default.
+    ///
+    /// Returns:
+    ///     FrozenSplinter: A FrozenSplinter holding the positions of the set bits
+    ///
+    /// Raises:
+    ///     ValueError: If bit_order is neither "little" nor "big"
+    ///     OverflowError: If a bit beyond 2**32 - 1 is set
+    pub fn from_bitmask(
+        _cls: &Bound<'_, PyType>,
+        data: &Bound<'_, PyAny>,
+        bit_order: &str,
+    ) -> PyResult<Self> {
+        let msb_first = bitmask::msb_first(bit_order)?;
+        let bytes = bytes_from_buffer(data)?;
+        Ok(Self::new(bitmask::decode(&bytes, msb_first)?))
+    }
+
+    #[pyo3(signature = (length = None, bit_order = "little"))]
+    /// Writes the FrozenSplinter as a dense bitmask, in which bit i is set if i is in it.
+    ///
+    /// Args:
+    ///     length (int | None): The number of bits in the mask, which is padded to a whole
+    ///     number of bytes with unset bits. Defaults to one more than the largest element.
+    ///     bit_order (str): "little" or "big", as for from_bitmask().
+    ///
+    /// Returns:
+    ///     bytes: The packed bits of the mask
+    ///
+    /// Raises:
+    ///     ValueError: If length is not greater than the largest element, or bit_order is
+    ///     neither "little" nor "big"
+    pub fn to_bitmask(&self, py: Python, length: Option<u64>, bit_order: &str) -> PyResult<Py<PyBytes>> {
+        let msb_first = bitmask::msb_first(bit_order)?;
+        let length = bitmask::mask_len(last(&self.0), length)?;
+        Ok(PyBytes::new(py, &bitmask::encode(self.0.inner(), length, msb_first)).into())
+    }
+
+    /// Checks if the bitmap contains a single value or multiple values.
+    ///
+    /// Args:
+    ///     value (int | list[int]): The value or values to check for.
+    ///
+    /// Returns:
+    ///     bool | list[bool]: A single boolean if the input was a single integer,
+    ///     or a list of booleans if the input was a list.
+    pub fn contains(&self, value: &Bound<PyAny>) -> PyResult<BoolOrVec> { contains(&self.0, value) }
+
+    /// Checks if the bitmap contains multiple values in parallel.
+    ///
+    /// Args:
+    ///     values list[int]: The values values to check for.
+    ///
+    /// Returns:
+    ///     list[bool]: A list of booleans.
+    pub fn contains_many_parallel(&self, values: Vec<u32>) -> Vec<bool> { contains_many_parallel(&self.0, values) }
+
+    /// Checks if the bitmap contains each element of a uint32 or int64 NumPy array, returning
+    /// a boolean array of the same shape.
+    pub fn contains_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        arrays::contains_numpy(py, &self.0, values)
+    }
+
+    /// Parallel version of contains_numpy(), worthwhile for at least 10,000 values.
+    pub fn contains_many_parallel_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        arrays::contains_numpy_parallel(py, &self.0, values)
+    }
+
+    /// Checks if the FrozenSplinter contains each of a list or NumPy array of values,
+    /// returning a boolean array, or packed bits in the layout of numpy.packbits().
+    #[pyo3(signature = (values, packed = false, parallel = false))]
+    pub fn contains_mask(&self, py: Python, values: &Bound<PyAny>, packed: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::contains_mask(py, &self.0, values, packed, parallel)
+    }
+
+    /// Returns the values which are in the FrozenSplinter, or their indices.
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_present(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::filter(py, &self.0, values, "filter_present", true, indices, parallel)
+    }
+
+    /// Returns the values which are not in the FrozenSplinter, or their indices.
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_absent(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::filter(py, &self.0, values, "filter_absent", false, indices, parallel)
+    }
+
+    fn __contains__(&self, value: &Bound<PyAny>) -> PyResult<bool> {
+        match value.extract() {
+            Ok(value) => Ok(self.0.contains(value)),
+            // like a set, which holds no such int, rather than raising
+            Err(_) if value.is_instance_of::<PyInt>() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the number of elements in the FrozenSplinter that are less than or equal to
+    /// the given value.
+    pub fn rank(&self, value: u32) -> usize { self.0.rank(value) }
+
+    /// Returns the element at the given index in the sorted sequence, or None if it is out of
+    /// bounds.
+    pub fn select(&self, idx: &Bound<PyAny>) -> PyResult<Option<u32>> { select(&self.0, idx) }
+
+    pub fn position(&self, value: u32) -> PyResult<usize> { position(&self.0, value) }
+
+    /// Ranks each of a list or NumPy array of values, returning a uint64 array.
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn rank_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        arrays::rank_many(py, &self.0, values, parallel)
+    }
+
+    /// Selects the element at each of a list or NumPy array of indices, returning an int64
+    /// array with -1 for indices out of bounds.
+    #[pyo3(signature = (indices, parallel = false))]
+    pub fn select_many<'py>(&self, py: Python<'py>, indices: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        arrays::select_many(py, &self.0, indices, parallel)
+    }
+
+    /// Finds the index of each of a list or NumPy array of values, returning an int64 array
+    /// with -1 for values which are absent.
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn position_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        arrays::position_many(py, &self.0, values, parallel)
+    }
+
+    /// Returns the smallest element of the FrozenSplinter, or None if it is empty.
+    pub fn min(&self) -> Option<u32> { self.0.select(0) }
+
+    /// Returns the largest element of the FrozenSplinter, or None if it is empty.
+    pub fn max(&self) -> Option<u32> { last(&self.0) }
+
+    /// Returns the smallest element greater than the given value, or None if there is none.
+    pub fn successor(&self, value: i64) -> Option<u32> { successor(&self.0, value) }
+
+    /// Returns the largest element less than the given value, or None if there is none.
+    pub fn predecessor(&self, value: i64) -> Option<u32> { predecessor(&self.0, value) }
+
+    /// Returns the smallest value greater than or equal to the given value which is not in the
+    /// FrozenSplinter, or None if every value from there up to 2**32 - 1 is present.
+    pub fn next_absent(&self, value: i64) -> Option<u32> { next_absent(&self.0, value) }
+
+    /// Returns the elements of the FrozenSplinter between lo and hi as a new FrozenSplinter.
+    ///
+    /// Unlike slicing with `[]`, which selects by position and returns a list, this selects by
+    /// value, following Python's `range()` convention of including lo and excluding hi.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to include.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     FrozenSplinter: The elements within [lo, hi)
+    pub fn range(&self, lo: i64, hi: i64) -> Self { Self::from_owned(ranges::slice(&self.0, value_range(lo, hi))) }
+
+    /// Counts the elements of the FrozenSplinter between lo and hi, including lo and excluding
+    /// hi, without visiting the elements in between.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to count.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     int: The number of elements within [lo, hi)
+    pub fn range_count(&self, lo: i64, hi: i64) -> usize { count_within(&self.0, &value_range(lo, hi)) }
+
+    // bitwise set operators: as with frozenset, the result takes the type of the left operand.
+    // The right-hand side may also be a set, frozenset, range, list or NumPy array of ints
+    fn __and__(&self, rhs: Operand) -> Self {
+        let mut out = self.0.decode_to_splinter();
+        with_set_arg!(rhs.0, |r| out &= r);
+        Self::from_owned(out)
+    }
+    fn __or__(&self, rhs: Operand) -> PyResult<Self> {
+        let mut out = self.0.decode_to_splinter();
+        with_set_arg!(rhs.whole("|")?, |r| out |= r);
+        Ok(Self::from_owned(out))
+    }
+    fn __xor__(&self, rhs: Operand) -> PyResult<Self> {
+        let mut out = self.0.decode_to_splinter();
+        with_set_arg!(rhs.whole("^")?, |r| out ^= r);
+        Ok(Self::from_owned(out))
+    }
+    fn __sub__(&self, rhs: Operand) -> Self {
+        let mut out = self.0.decode_to_splinter();
+        with_set_arg!(rhs.0, |r| out -= r);
+        Self::from_owned(out)
+    }
+
+    fn __rand__(&self, rhs: Operand) -> Self { self.__and__(rhs) }
+    fn __ror__(&self, rhs: Operand) -> PyResult<Self> { self.__or__(rhs) }
+    fn __rxor__(&self, rhs: Operand) -> PyResult<Self> { self.__xor__(rhs) }
+    // computes rhs - self
+    fn __rsub__(&self, rhs: Operand) -> PyResult<Self> {
+        let mut out = rhs.whole("-")?.into_splinter();
+        out -= &self.0;
+        Ok(Self::from_owned(out))
+    }
+
+    // set comparison operations
+    fn __eq__(&self, rhs: SetOperand) -> bool { with_set_arg!(rhs.0, |r| *r == self.0) }
+    fn __ne__(&self, rhs: SetOperand) -> bool { !self.__eq__(rhs) }
+    fn __le__(&self, rhs: Operand) -> bool { overlap::is_subset(self.0.inner(), &rhs.0.encoded()) }
+    fn __lt__(&self, rhs: Operand) -> bool { (rhs.1 || self.0.cardinality() < rhs.0.cardinality()) && self.__le__(rhs) }
+    fn __ge__(&self, rhs: Operand) -> bool { !rhs.1 && overlap::is_subset(&rhs.0.encoded(), self.0.inner()) }
+    fn __gt__(&self, rhs: Operand) -> bool { !rhs.1 && self.0.cardinality() > rhs.0.cardinality() && self.__ge__(rhs) }
+
+    // defers NumPy's element-wise operators to the reflected operators above
+    #[classattr]
+    fn __array_ufunc__(py: Python) -> PyObject { py.None() }
+
+    /// Returns a hash of the FrozenSplinter's contents.
+    ///
+    /// The hash is computed once from the elements and cached. It agrees with ==, both
+    /// between FrozenSplinters however they were constructed, and with an equal frozenset, so
+    /// either can look up the other in a dict or set. Keeping it consistent with frozenset is
+    /// why the first call visits every element, rather than hashing the serialized bytes.
+    fn __hash__(&self) -> isize { *self.1.get_or_init(|| self.content_hash()) }
+
+    /// tells pickle to rebuild the FrozenSplinter from its serialized bytes
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(PyObject, Py<PyTuple>)> {
+        let constructor = Self::type_object(py).getattr("from_bytes")?.unbind();
+        let args = PyTuple::new(py, [self.to_bytes(py)])?.unbind();
+        Ok((constructor, args))
+    }
+
+    // copy protocol
+    fn copy(&self) -> Self { self.clone() }
+    fn __copy__(&self) -> Self { self.clone() }
+
+    /// Returns a mutable Splinter holding the same elements.
+    ///
+    /// The Splinter shares this FrozenSplinter's buffer until it is first modified.
+    ///
+    /// Returns:
+    ///     Splinter: a mutable copy of this FrozenSplinter
+    pub fn thaw(&self) -> SplinterWrapper { SplinterWrapper::new(CowSplinter::from_ref(self.0.clone())) }
+
+    /// Returns true if self and rhs have no overlap, and false otherwise.
+    ///
+    /// self is already serialized. A Splinter rhs is encoded at most once between modifications,
+    /// while an iterable rhs is collected and encoded on every call; the same goes for the
+    /// other comparisons and the *_len methods below.
+    fn isdisjoint(&self, rhs: QueryArg) -> bool { overlap::is_disjoint(self.0.inner(), &rhs.0.encoded()) }
+
+    /// Returns true if self is a subset of rhs, and false otherwise.
+    fn issubset(&self, rhs: QueryArg) -> bool { overlap::is_subset(self.0.inner(), &rhs.0.encoded()) }
+
+    /// Returns true if self is a superset of rhs, and false otherwise.
+    fn issuperset(&self, rhs: QueryArg) -> bool { rhs.1 == 0 && overlap::is_subset(&rhs.0.encoded(), self.0.inner()) }
+
+    /// Returns len(self & rhs), without building the intersection.
+    fn intersection_len(&self, rhs: QueryArg) -> usize { overlap::intersection_len(self.0.inner(), &rhs.0.encoded()) }
+
+    /// Returns len(self | rhs), without building the union.
+    fn union_len(&self, rhs: QueryArg) -> usize {
+        self.0.cardinality() + rhs.cardinality() - self.intersection_len(rhs)
+    }
+
+    /// Returns len(self - rhs), without building the difference.
+    fn difference_len(&self, rhs: QueryArg) -> usize { self.0.cardinality() - self.intersection_len(rhs) }
+
+    /// Returns the Jaccard similarity of self and rhs, which is 1.0 if both are empty.
+    fn jaccard(&self, rhs: QueryArg) -> f64 {
+        let (len, rhs_len) = (self.0.cardinality(), rhs.cardinality());
+        overlap::jaccard(self.intersection_len(rhs), len, rhs_len)
+    }
+
+    /// Returns the union of one or more Splinters as a FrozenSplinter
+    #[pyo3(signature = (*rhs))]
+    fn union(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> {
+        let mut result = self.0.decode_to_splinter();
+        for other in rhs.iter() {
+            with_set_arg!(other.extract::<SetArg>()?, |r| result |= r);
+        }
+        Ok(Self::from_owned(result))
+    }
+
+    /// Returns the intersection of one or more Splinters as a FrozenSplinter
+    #[pyo3(signature = (*rhs))]
+    fn intersection(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> {
+        let mut result = self.0.decode_to_splinter();
+        for other in rhs.iter() {
+            with_set_arg!(other.extract::<QueryArg>()?.0, |r| result &= r);
+        }
+        Ok(Self::from_owned(result))
+    }
+
+    /// Returns the elements of self which are in none of the others as a FrozenSplinter
+    #[pyo3(signature = (*rhs))]
+    fn difference(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> {
+        let mut result = self.0.decode_to_splinter();
+        for other in rhs.iter() {
+            with_set_arg!(other.extract::<QueryArg>()?.0, |r| result -= r);
+        }
+        Ok(Self::from_owned(result))
+    }
+
+    /// Returns the elements which are in exactly one of self and rhs as a FrozenSplinter
+    fn symmetric_difference(&self, rhs: SetArg) -> Self {
+        let mut result = self.0.decode_to_splinter();
+        with_set_arg!(rhs, |r| result ^= r);
+        Self::from_owned(result)
+    }
+}
@@ -0,0 +1,441 @@
+use std::ops::RangeInclusive;
+
+use bytes::Bytes;
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use splinter_rs::{level::High, CowSplinter, PartitionRead, SplinterRef};
+
+use crate::{codec::CachedSplinter, format::{child_data, read_be, reencode, Node}, SplinterWrapper};
+
+// Streaming iteration over the serialized form of a splinter.
+//
+// splinter-rs only exposes iterators which borrow the splinter they came from, which a Python
+// iterator can't hold on to. Instead, SplinterIter keeps its own handle on the encoded bytes and
+// walks the partition tree directly, decoding a small batch of values at a time starting from
+// the last value it yielded.
+//
+// The partitions are read through format, and a partition which doesn't parse there is read
+// from the values splinter-rs itself reads out of the splinter.
+
+// the first batch is kept small so that breaking out of a loop early stays cheap
+const MIN_BATCH: usize = 64;
+const MAX_BATCH: usize = 4096;
+
+/// Finds the first index in 0..len for which pred is false, given that pred is true for some
+/// prefix of the range
+pub(crate) fn partition_point(len: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) { lo = mid + 1 } else { hi = mid }
+    }
+    lo
+}
+
+impl Node<'_> {
+    /// Appends the values of this partition within [lo, hi] to out, in ascending order or in
+    /// descending order if rev is set, until out holds limit values.
+    ///
+    /// lo and hi are relative to the start of the partition, and base is added to every value
+    /// before it is written. Returns None if a partition below this one doesn't parse, in which
+    /// case out holds some of the values read before it.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn collect(&self, width: usize, base: u32, lo: u32, hi: u32, rev: bool, out: &mut Vec<u32>, limit: usize) -> Option<()> {
+        match self {
+            Node::Empty => {}
+            Node::Full => extend(out, limit, base, lo..=hi, rev),
+            Node::Bitmap(bitmap) => {
+                let (first, last) = (lo as usize / 8, hi as usize / 8);
+                let mut bytes = (first..=last).map(|i| {
+                    let mut bits = bitmap[i];
+                    if i == first { bits &= 0xFF << (lo % 8) }
+                    if i == last { bits &= 0xFF >> (7 - hi % 8) }
+                    (i, bits)
+                });
+                while let Some((i, mut bits)) = if rev { bytes.next_back() } else { bytes.next() } {
+                    while bits != 0 {
+                        if out.len() == limit { return Some(()) }
+                        let bit = if rev { 7 - bits.leading_zeros() } else { bits.trailing_zeros() };
+                        out.push(base | (i * 8) as u32 | bit);
+                        bits &= !(1 << bit);
+                    }
+                }
+            }
+            Node::Vec(values) => {
+                let value = |i: usize| read_be(&values[i * width..(i + 1) * width]);
+                let len = values.len() / width;
+                if rev {
+                    let end = partition_point(len, |i| value(i) <= hi);
+                    for i in (0..end).rev() {
+                        let v = value(i);
+                        if v < lo || out.len() == limit { return Some(()) }
+                        out.push(base | v);
+                    }
+                } else {
+                    let start = partition_point(len, |i| value(i) < lo);
+                    for i in start..len {
+                        let v = value(i);
+                        if v > hi || out.len() == limit { return Some(()) }
+                        out.push(base | v);
+                    }
+                }
+            }
+            Node::Run(runs) => {
+                let bound = |i: usize, j: usize| read_be(&runs[(2 * i + j) * width..(2 * i + j + 1) * width]);
+                let len = runs.len() / (2 * width);
+                let mut indices = partition_point(len, |i| bound(i, 1) < lo)..partition_point(len, |i| bound(i, 0) <= hi);
+                while let Some(i) = if rev { indices.next_back() } else { indices.next() } {
+                    if out.len() == limit { return Some(()) }
+                    extend(out, limit, base, bound(i, 0).max(lo)..=bound(i, 1).min(hi), rev);
+                }
+            }
+            Node::Tree { segments, offsets, children } => {
+                let shift = 8 * (width - 1) as u32;
+                let child_max = (1u32 << shift) - 1;
+                let segments = segments.to_vec();
+                let mut indices = 0..segments.len();
+                while let Some(idx) = if rev { indices.next_back() } else { indices.next() } {
+                    let seg_lo = (segments[idx] as u32) << shift;
+                    let seg_hi = seg_lo | child_max;
+                    if seg_hi < lo || seg_lo > hi { continue }
+                    if out.len() == limit { return Some(()) }
+
+                    let child = Node::from_suffix(child_data(offsets, children, idx, width)?, width - 1)?;
+                    child.collect(width - 1, base | seg_lo, lo.max(seg_lo) - seg_lo, hi.min(seg_hi) - seg_lo, rev, out, limit)?;
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+/// Appends a run of values to out, up to limit values
+fn extend(out: &mut Vec<u32>, limit: usize, base: u32, values: RangeInclusive<u32>, rev: bool) {
+    let room = limit - out.len();
+    if rev {
+        out.extend(values.rev().take(room).map(|v| base | v));
+    } else {
+        out.extend(values.take(room).map(|v| base | v));
+    }
+}
+
+/// Yields the elements of an encoded splinter within a range, in ascending or descending order
+pub(crate) struct Cursor {
+    data: Bytes,
+    // the values which have yet to be decoded
+    range: RangeInclusive<u32>,
+    rev: bool,
+    exhausted: bool,
+    batch: Vec<u32>,
+    pos: usize,
+}
+
+impl Cursor {
+    pub(crate) fn new(data: Bytes, range: RangeInclusive<u32>, rev: bool) -> Self {
+        let exhausted = range.is_empty();
+        Self { data, range, rev, exhausted, batch: Vec::new(), pos: 0 }
+    }
+
+    fn refill(&mut self) {
+        let limit = (self.batch.len() * 2).clamp(MIN_BATCH, MAX_BATCH);
+        self.batch.clear();
+        self.pos = 0;
+
+        let (lo, hi) = (*self.range.start(), *self.range.end());
+        if self.read(lo, hi, limit).is_none() {
+            // carry on through the values splinter-rs reads out of the splinter instead
+            self.batch.clear();
+            self.data = reencode(&self.data);
+            if self.read(lo, hi, limit).is_none() {
+                self.batch.clear();
+            }
+        }
+
+        // a short batch means the partition tree ran out of values in range
+        match self.batch.last() {
+            Some(&last) if self.batch.len() == limit && !self.rev && last < hi => self.range = last + 1..=hi,
+            Some(&last) if self.batch.len() == limit && self.rev && last > lo => self.range = lo..=last - 1,
+            _ => self.exhausted = true,
+        }
+    }
+
+    /// Decodes the values within [lo, hi] into the batch, or returns None if a partition doesn't parse
+    fn read(&mut self, lo: u32, hi: u32, limit: usize) -> Option<()> {
+        Node::root(&self.data)?.collect(4, 0, lo, hi, self.rev, &mut self.batch, limit)
+    }
+}
+
+impl Iterator for Cursor {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.pos == self.batch.len() {
+            if self.exhausted {
+                return None;
+            }
+            self.refill();
+        }
+        let value = self.batch.get(self.pos).copied()?;
+        self.pos += 1;
+        Some(value)
+    }
+}
+
+/// The position of an iteration over a Splinter's values, shared by SplinterIter and
+/// SignedSplinterIter. Elements are decoded lazily, a batch at a time.
+pub(crate) struct IterState {
+    // the first elements of a Splinter which hasn't been encoded since it last changed, read from
+    // it directly so that a loop which stops early never pays for encoding the whole of it
+    head: std::vec::IntoIter<u32>,
+    // reads the rest, once head runs out. Until then, range and rev describe what's left
+    cursor: Option<Cursor>,
+    range: RangeInclusive<u32>,
+    rev: bool,
+    len: usize,
+    remaining: usize,
+}
+
+impl IterState {
+    pub(crate) fn new(splinter: &CachedSplinter, range: RangeInclusive<u32>, rev: bool) -> Self {
+        let remaining = count_within(splinter, &range);
+        let mut state = Self { head: Vec::new().into_iter(), cursor: None, range, rev, len: splinter.cardinality(), remaining };
+        // a Splinter backed by serialized bytes is read in place, as is one whose encoding is
+        // cached. Otherwise encoding it is put off until the first batch has been used up
+        match splinter.encoded_if_cached() {
+            Some(data) => state.cursor = Some(Cursor::new(data, state.range.clone(), rev)),
+            None => state.read_head(splinter),
+        }
+        state
+    }
+
+    fn frozen(splinter: &SplinterRef<Bytes>, range: RangeInclusive<u32>, rev: bool) -> Self {
+        Self {
+            head: Vec::new().into_iter(),
+            cursor: Some(Cursor::new(splinter.inner().clone(), range.clone(), rev)),
+            remaining: count_within(splinter, &range),
+            range,
+            rev,
+            len: splinter.cardinality(),
+        }
+    }
+
+    /// Selects the first batch of values straight from an owned splinter, leaving the range to
+    /// cover only the values after them
+    fn read_head(&mut self, splinter: &impl PartitionRead<High>) {
+        let (lo, hi) = (*self.range.start(), *self.range.end());
+        let n = self.remaining.min(MIN_BATCH);
+        let head: Vec<u32> = match self.rev {
+            false => {
+                let first = lo.checked_sub(1).map_or(0, |below| splinter.rank(below));
+                (first..first + n).filter_map(|i| splinter.select(i)).collect()
+            }
+            true => {
+                let last = splinter.rank(hi);
+                (last - n..last).rev().filter_map(|i| splinter.select(i)).collect()
+            }
+        };
+        self.range = match head.last() {
+            _ if n == self.remaining => empty_range(),
+            Some(&last) if !self.rev => last + 1..=hi,
+            Some(&last) => lo..=last - 1,
+            None => self.range.clone(),
+        };
+        self.head = head.into_iter();
+    }
+
+    /// Whether the splinter being iterated over has changed size since iteration began
+    pub(crate) fn changed(&self, source: &CachedSplinter) -> bool {
+        match (&**source, &self.cursor) {
+            // still reading the very same bytes
+            (CowSplinter::Ref(splinter_ref), Some(cursor)) if splinter_ref.inner().as_ptr() == cursor.data.as_ptr() => false,
+            (splinter, _) => splinter.cardinality() != self.len,
+        }
+    }
+
+    /// Returns the next value, encoding the source to read it if the first batch is used up
+    pub(crate) fn next(&mut self, source: impl FnOnce() -> Bytes) -> Option<u32> {
+        let value = match self.head.next() {
+            Some(value) => Some(value),
+            None => {
+                let (range, rev) = (self.range.clone(), self.rev);
+                self.cursor.get_or_insert_with(|| Cursor::new(source(), range, rev)).next()
+            }
+        };
+        if value.is_some() {
+            self.remaining -= 1;
+        }
+        value
+    }
+
+    pub(crate) fn remaining(&self) -> usize { self.remaining }
+}
+
+/// Iterator class to implement __iter__ on Splinter and FrozenSplinter.
+#[pyclass(name = "SplinterIter", module = "splynters")]
+pub struct SplinterIter {
+    state: IterState,
+    // the Splinter being iterated over, which is checked on every step in case it changes size.
+    // FrozenSplinters can't change, so they aren't tracked
+    source: Option<Py<SplinterWrapper>>,
+}
+
+impl SplinterIter {
+    pub(crate) fn new(splinter: &Bound<'_, SplinterWrapper>, range: RangeInclusive<u32>, rev: bool) -> Self {
+        Self { state: IterState::new(&splinter.borrow().0, range, rev), source: Some(splinter.clone().unbind()) }
+    }
+
+    pub(crate) fn frozen(splinter: &SplinterRef<Bytes>, range: RangeInclusive<u32>, rev: bool) -> Self {
+        Self { state: IterState::frozen(splinter, range, rev), source: None }
+    }
+}
+
+#[pymethods]
+impl SplinterIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> { slf }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<u32>> {
+        let source = self.source.as_ref().map(|s| s.borrow(py));
+        // mirrors the check Python's own set iterator makes
+        if source.as_ref().is_some_and(|s| self.state.changed(&s.0)) {
+            return Err(PyRuntimeError::new_err("Splinter changed size during iteration"));
+        }
+        // only a Splinter's iterator starts out without a cursor
+        Ok(self.state.next(|| source.map(|s| s.encoded()).unwrap_or_default()))
+    }
+
+    /// Returns the number of elements left to iterate over
+    fn __length_hint__(&self) -> usize { self.state.remaining() }
+}
+
+pub(crate) fn count_within(splinter: &impl PartitionRead<High>, range: &RangeInclusive<u32>) -> usize {
+    match (range.is_empty(), range.start().checked_sub(1)) {
+        (true, _) => 0,
+        (false, Some(below)) => splinter.rank(*range.end()) - splinter.rank(below),
+        (false, None) => splinter.rank(*range.end()),
+    }
+}
+
+/// Converts Python-style [start, stop) bounds into the range of u32 values they cover, which may
+/// be empty
+pub(crate) fn value_range(start: i64, stop: i64) -> RangeInclusive<u32> {
+    let start = start.clamp(0, 1 << 32);
+    let stop = stop.clamp(0, 1 << 32);
+    if start >= stop {
+        return empty_range();
+    }
+    start as u32..=(stop - 1) as u32
+}
+
+/// A range holding no values
+#[allow(clippy::reversed_empty_ranges)]
+pub(crate) fn empty_range() -> RangeInclusive<u32> { 1..=0 }
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+    use splinter_rs::PartitionWrite;
+
+    use super::*;
+    use crate::{codec, format::{self, FOOTER_SIZE, TREE}, testing::{cases, kinds, optimized}};
+
+    fn within(splinter: &impl PartitionRead<High>, range: &RangeInclusive<u32>, rev: bool) -> Vec<u32> {
+        let mut values: Vec<u32> = splinter.iter().filter(|v| range.contains(v)).collect();
+        if rev {
+            values.reverse();
+        }
+        values
+    }
+
+    #[test]
+    fn reads_every_kind_of_partition() {
+        for splinter in cases() {
+            let data = codec::encode(&splinter);
+            let mut ranges = vec![0..=u32::MAX, 0..=0, 200..=70_000, u32::MAX..=u32::MAX, empty_range()];
+            if let (Some(first), Some(last)) = (splinter.select(0), splinter.last()) {
+                ranges.extend([first..=last, first.saturating_add(1)..=last.saturating_sub(1), last / 2..=last]);
+            }
+            for range in ranges {
+                for rev in [false, true] {
+                    let values: Vec<u32> = Cursor::new(data.clone(), range.clone(), rev).collect();
+                    assert_eq!(values, within(&splinter, &range, rev), "{range:?} rev={rev}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_partitions() {
+        for splinter in cases() {
+            let bytes = codec::encode(&splinter);
+            let data = &bytes[..bytes.len() - FOOTER_SIZE];
+            let mut malformed: Vec<Vec<u8>> = (0..data.len()).step_by(data.len().div_ceil(64)).map(|i| data[i..].to_vec()).collect();
+            // the headers of the root partition, and of the children at the end of a tree
+            for i in data.len().saturating_sub(48)..data.len() {
+                for bits in [0x01, 0x80, 0xFF] {
+                    let mut data = data.to_vec();
+                    data[i] ^= bits;
+                    malformed.push(data);
+                }
+            }
+            for data in malformed {
+                let Some(root) = Node::from_suffix(&data, 4) else { continue };
+                root.well_formed(4);
+                for rev in [false, true] {
+                    let _ = root.collect(4, 0, 0, u32::MAX, rev, &mut vec![], MAX_BATCH);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_splinter_rs() {
+        // a tree whose segment bitmap lists fewer children than it holds, which splinter-rs reads
+        // the listed ones of while format gives up on it
+        let splinter = optimized((0..40).flat_map(|i| (0..300).map(move |j| (i << 24) | (j * 3))));
+        assert_eq!(kinds(&codec::encode(&splinter))[0], (TREE, 4));
+        let mut data = codec::encode(&splinter).to_vec();
+        data.truncate(data.len() - FOOTER_SIZE);
+        let segments = data.len() - 2 - 32;
+        data[segments + 4] &= !0x80;
+        let bytes = format::finish(data);
+        assert!(Node::root(&bytes).is_none());
+
+        let expected: Vec<u32> = SplinterRef::from_bytes(bytes.clone()).unwrap().iter().collect();
+        assert_eq!(expected.len(), 39 * 300);
+        assert_eq!(Cursor::new(bytes.clone(), 0..=u32::MAX, false).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn puts_off_encoding_owned_splinters() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let splinter = optimized((0..1000).map(|i| i * 3));
+            let wrapper = Bound::new(py, SplinterWrapper::new(CowSplinter::Owned(splinter.clone()))).unwrap();
+            // ranges holding fewer, exactly as many and more values than the first batch
+            for range in [0..=u32::MAX, 30..=220, 30..=219, 3..=190, 2999..=3000] {
+                for rev in [false, true] {
+                    let mut iter = SplinterIter::new(&wrapper, range.clone(), rev);
+                    let mut values = vec![];
+                    while let Some(value) = iter.__next__(py).unwrap() {
+                        values.push(value);
+                        if values.len() == MIN_BATCH / 2 {
+                            assert!(wrapper.borrow().0.encoded_if_cached().is_none());
+                        }
+                    }
+                    assert_eq!(values, within(&splinter, &range, rev), "{range:?} rev={rev}");
+                    assert_eq!(iter.__length_hint__(), 0);
+                    // drops the encoding which finishing the iteration cached
+                    wrapper.borrow_mut().0.to_mut();
+                }
+            }
+            // once encoded, the bytes are read from the start
+            wrapper.borrow().encoded();
+            let mut iter = SplinterIter::new(&wrapper, 0..=u32::MAX, false);
+            assert!(iter.state.cursor.is_some());
+            assert_eq!(iter.__next__(py).unwrap(), Some(0));
+
+            let mut iter = SplinterIter::new(&wrapper, 0..=u32::MAX, false);
+            wrapper.borrow_mut().0.to_mut().remove(0);
+            assert!(iter.__next__(py).is_err());
+        });
+    }
+}
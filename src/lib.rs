@@ -1,29 +1,92 @@
-use std::vec;
+use std::{fs::File, path::{Path, PathBuf}};
 
 use bytes::Bytes;
-use pyo3::{exceptions::{PyTypeError, PyValueError}, prelude::*, types::{PyBytes, PySlice, PyTuple, PyType}, PyTypeInfo};
+use memmap2::Mmap;
+use numpy::{PyArray1, PyArrayDyn};
+use pyo3::{buffer::PyBuffer, exceptions::{PyKeyError, PyOverflowError, PyTypeError, PyValueError}, prelude::*, types::{PyBytes, PyFrozenSet, PyInt, PyList, PySet, PySlice, PyTuple, PyType}, PyTypeInfo};
 use rayon::prelude::*;
-use splinter_rs::{CowSplinter, Cut, Encodable, Optimizable, PartitionRead, PartitionWrite};
+use splinter_rs::{level::High, CowSplinter, Cut, Encodable, Optimizable, PartitionRead, Splinter, SplinterRef};
 
-#[derive(Clone)]
-pub enum SplinterType {
-    Splinter,
-    SplinterRef,
-    CowSplinter,
+/// Evaluates an expression against the splinter-rs value behind a `SplinterArg`, whichever
+/// concrete type it happens to be
+macro_rules! with_splinter {
+    ($arg:expr, |$rhs:ident| $body:expr) => {
+        match $arg {
+            SplinterArg::Splinter(wrapper) => { let $rhs: &CowSplinter<Bytes> = &wrapper.0; $body }
+            SplinterArg::Frozen(wrapper) => { let $rhs = &wrapper.0; $body }
+        }
+    };
+}
+
+/// Like `with_splinter!`, but for a `SetArg`, which may also hold values collected from an
+/// iterable
+macro_rules! with_set_arg {
+    ($arg:expr, |$rhs:ident| $body:expr) => {
+        match $arg {
+            SetArg::Splinter(arg) => with_splinter!(arg, |$rhs| $body),
+            SetArg::Values(splinter) => { let $rhs = &splinter; $body }
+        }
+    };
 }
 
+mod arrays;
+mod arrow;
+mod bitmask;
+mod builder;
+mod codec;
+mod format;
+mod frozen;
+mod iter;
+mod multi;
+mod overlap;
+mod ranges;
+mod roaring;
+mod signed;
+#[cfg(test)]
+mod testing;
+mod wide;
+
+pub use arrow::ArrowArrayWrapper;
+pub use builder::SplinterBuilder;
+pub use frozen::FrozenSplinterWrapper;
+pub use iter::SplinterIter;
+pub use signed::{FrozenSignedSplinterWrapper, SignedSplinterIter, SignedSplinterWrapper};
+pub use wide::{FrozenSplinter64Wrapper, Splinter64Iter, Splinter64Wrapper};
+use codec::CachedSplinter;
+use iter::value_range;
+
 /// A wrapper for higher-order functionality over the Splinter 
 /// crate
 #[pyclass(name="Splinter", module="splynters")]
 #[derive(Clone)] 
-pub struct SplinterWrapper(CowSplinter<Bytes>);
+pub struct SplinterWrapper(
+    CachedSplinter,
+    // whether methods which modify the Splinter optimize it after every call
+    bool,
+);
+
+impl SplinterWrapper {
+    pub(crate) fn new(splinter: CowSplinter<Bytes>) -> Self { Self(splinter.into(), true) }
+
+    /// Returns the encoded form of the Splinter, which is shared if it's already serialized, and
+    /// otherwise encoded once and kept until the Splinter is next modified
+    pub(crate) fn encoded(&self) -> Bytes { self.0.encoded() }
+
+    // through ranges::optimize(), since a single value added to a splinter of a hundred million
+    // takes seconds to optimize with splinter-rs
+    fn optimize_if_auto(&mut self) {
+        if self.1 {
+            self.0.optimize();
+        }
+    }
+}
 
 #[pymethods]
 impl SplinterWrapper {
     #[new]
     pub fn __new__() -> Self {
         let splinter = CowSplinter::from_iter(std::iter::empty::<u32>());
-        Self(splinter)
+        Self::new(splinter)
     }
     pub fn __len__(&self) -> usize { self.0.cardinality() }
     pub fn __sizeof__(&self) -> usize { self.0.encoded_size() }
@@ -31,10 +94,38 @@ impl SplinterWrapper {
         let s = format!("SplinterWrapper(len = {}, compressed_byte_size = {})", self.0.cardinality(), self.0.encoded_size());
         s
     }
-    fn __iter__(&self) -> SplinterIter {
-        SplinterIter {
-            inner: self.0.iter().collect::<Vec<u32>>().into_iter(),
-        }
+    fn __iter__(slf: &Bound<'_, Self>) -> SplinterIter { SplinterIter::new(slf, 0..=u32::MAX, false) }
+    fn __reversed__(slf: &Bound<'_, Self>) -> SplinterIter { SplinterIter::new(slf, 0..=u32::MAX, true) }
+
+    #[pyo3(signature = (value, reverse = false))]
+    /// Iterates over the elements greater than or equal to value, in ascending order.
+    ///
+    /// Iteration starts directly at value, without decoding any of the elements before it.
+    ///
+    /// Args:
+    ///     value (int): The value to start from
+    ///     reverse (bool): If True, iterates instead over the elements less than or equal to
+    ///     value, in descending order
+    ///
+    /// Returns:
+    ///     SplinterIter: A lazy iterator over the elements
+    fn iter_from(slf: &Bound<'_, Self>, value: i64, reverse: bool) -> SplinterIter {
+        let range = if reverse { value_range(0, value.saturating_add(1)) } else { value_range(value, 1 << 32) };
+        SplinterIter::new(slf, range, reverse)
+    }
+
+    #[pyo3(signature = (start, stop, reverse = false))]
+    /// Iterates over the elements in the range [start, stop), in ascending order.
+    ///
+    /// Args:
+    ///     start (int): The lower bound of the range, inclusive
+    ///     stop (int): The upper bound of the range, exclusive
+    ///     reverse (bool): If True, iterates over the same elements in descending order
+    ///
+    /// Returns:
+    ///     SplinterIter: A lazy iterator over the elements
+    fn iter_range(slf: &Bound<'_, Self>, start: i64, stop: i64, reverse: bool) -> SplinterIter {
+        SplinterIter::new(slf, value_range(start, stop), reverse)
     }
 
 
@@ -42,85 +133,135 @@ impl SplinterWrapper {
     ///
     /// Operates according to Python's slice syntax: [start:stop:step]
     /// Supports selection by negative indices and negative steps
-    fn __getitem__(&self, index: &Bound<PyAny>) -> PyResult<UintOrVec> {
-        if let Ok(i_idx) = index.extract::<isize>() {
-            let len = self.0.cardinality();
-            let mut actual_index = i_idx;
-
-            if actual_index < 0 {
-                actual_index += len as isize;
-            }
-
-            match self.0.select(actual_index as usize) {
-                Some(value) => Ok(UintOrVec::U32(value)),
-                None => Err(pyo3::exceptions::PyIndexError::new_err( "splinter index out of range"))
-            }
-        } else if let Ok(u_idx) = index.extract::<usize>() {
-
-            match self.0.select(u_idx) {
-                Some(value) => Ok(UintOrVec::U32(value)),
-                None => Err(pyo3::exceptions::PyIndexError::new_err( "splinter index out of range"))
-            }
-        } else if let Ok(slice) = index.downcast::<PySlice>() {
-
-            let len = self.0.cardinality() as isize;
-            let indices = slice.indices(len)?;
-
-            let mut sliced_values = Vec::with_capacity(indices.slicelength);
-
-            // the step = 0 case is caught by pyo3 in the construction of the PySlice type
-            // we do not need to account for it here
-            if indices.step > 0 {
-                sliced_values.extend(
-                    self.0.iter()
-                        .skip(indices.start as usize)
-                        .step_by(indices.step as usize)
-                        .take(indices.slicelength)
-                );
-            } else {
-
-                let mut current = indices.start;
-                (0..indices.slicelength).for_each(|_| {
-                    if let Some(val) = self.0.select(current as usize) {
-                        sliced_values.push(val);
-                    }
-                    current += indices.step;
-                });
-            }
-
-            // let start = indices.start as usize;
-            // let stop = indices.stop as usize;
-            // let step = indices.step as usize;
-
-            // let sliced_values: Vec<u32> = self.0.iter().skip(start).step_by(step).take((stop - start) / step + 1).collect();
-
-            return Ok(UintOrVec::Vec(sliced_values))
-        } else {
-            Err(PyTypeError::new_err("splinter indices must be integers or slices"))
-        }
-
-    }
+    fn __getitem__(&self, index: &Bound<PyAny>) -> PyResult<UintOrVec> { get_item(&self.0, index) }
 
     #[staticmethod]
     /// Constructs a Splinter from an iterator of unsigned integers.
     ///
+    /// A `range` with a step of 1 or -1 is inserted as a whole, without visiting each value.
+    ///
     /// Args:
-    ///     data list[int]: The iterator from which to construct the Splinter.
+    ///     data list[int] | range: The iterator from which to construct the Splinter.
     ///
     /// Returns: 
     ///     Splinter: A Splinter object constructed from the input items
-    pub fn from_list(data: Vec<u32>) -> Self {
+    pub fn from_list(data: &Bound<PyAny>) -> PyResult<Self> {
+        if let Some((start, stop)) = ranges::contiguous(data)? {
+            return Self::from_range(start, stop);
+        }
         // `pyo3` automatically converts the Python list into a `Vec<u32>`.
         // `Splinter::from_iter` can then consume the vector directly via `into_iter`
-        let mut splinter = CowSplinter::from_iter(data);
+        let mut splinter = CowSplinter::from_iter(data.extract::<Vec<u32>>()?);
         splinter.to_mut().optimize();
 
-        Self(splinter)
+        Ok(Self::new(splinter))
+    }
+
+    #[staticmethod]
+    /// Constructs a Splinter holding every integer in [start, stop), like `range(start, stop)`.
+    ///
+    /// Whole partitions of the range are inserted at once, so even ranges spanning hundreds of
+    /// millions of values are built almost instantly.
+    ///
+    /// Args:
+    ///     start (int): The first value in the Splinter.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Returns: 
+    ///     Splinter: A Splinter containing the range
+    ///
+    /// Raises:
+    ///     OverflowError: If the range reaches outside of 0 to 2**32 - 1
+    pub fn from_range(start: i64, stop: i64) -> PyResult<Self> {
+        let range = ranges::insertable(start, stop, "from_range")?;
+        Ok(Self::new(CowSplinter::from_owned(ranges::splinter_from_range(range))))
     }
     pub fn to_list(&self) -> Vec<u32> { self.0.iter().collect() }
 
+    #[staticmethod]
+    /// Constructs a Splinter from a NumPy array, reading its buffer directly rather than
+    /// converting each element to a Python int.
+    ///
+    /// Args:
+    ///     data (numpy.ndarray): An array of dtype uint32, or of dtype int64 with every
+    ///     value between 0 and 2**32 - 1. Arrays of any shape are flattened.
+    ///
+    /// Returns: 
+    ///     Splinter: A Splinter object constructed from the array's elements
+    pub fn from_numpy(data: &Bound<PyAny>) -> PyResult<Self> {
+        let mut splinter = arrays::splinter_from_numpy(data)?;
+        splinter.optimize();
+
+        Ok(Self::new(CowSplinter::from_owned(splinter)))
+    }
+
+    /// Returns the elements of the Splinter, in ascending order, as a uint32 NumPy array.
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    pub fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<u32>>> { arrays::to_numpy(py, &self.0) }
+
+    #[staticmethod]
+    /// Constructs a Splinter from an Arrow array of type uint32, such as a pyarrow Array or
+    /// ChunkedArray or a polars Series, reading its buffers directly.
+    ///
+    /// The array is read through the Arrow PyCapsule Interface, so no Arrow library is needed
+    /// beyond whichever produced it.
+    ///
+    /// Args:
+    ///     array: Any object with an `__arrow_c_array__` or `__arrow_c_stream__` method
+    ///     exporting uint32 values, which must not be null.
+    ///
+    /// Returns:
+    ///     Splinter: A Splinter object constructed from the array's elements
+    ///
+    /// Raises:
+    ///     TypeError: If the array is not of type uint32
+    ///     ValueError: If the array holds nulls
+    pub fn from_arrow(array: &Bound<PyAny>) -> PyResult<Self> {
+        let mut splinter = arrow::splinter_from_arrow(array)?;
+        splinter.optimize();
+
+        Ok(Self::new(CowSplinter::from_owned(splinter)))
+    }
+
+    /// Returns the elements of the Splinter, in ascending order, as an Arrow array of type
+    /// uint32, which Arrow libraries can import without copying (e.g. with `pyarrow.array()`).
+    pub fn to_arrow(&self) -> ArrowArrayWrapper { arrow::to_arrow(&self.0) }
+
+    #[staticmethod]
+    /// Reads an Arrow binary column of serialized Splinters, as written by `to_bytes()` or
+    /// `splynters.to_arrow_binary()`, into a list of Splinters.
+    ///
+    /// binary, large_binary and binary_view columns are all accepted. As with from_bytes(),
+    /// the Splinters read directly from the column's memory until their first mutation.
+    ///
+    /// Args:
+    ///     column: Any object with an `__arrow_c_array__` or `__arrow_c_stream__` method
+    ///     exporting a binary column.
+    ///
+    /// Returns:
+    ///     list[Splinter | None]: A Splinter for every element, or None for every null
+    ///
+    /// Raises:
+    ///     TypeError: If the column is not of a binary type
+    ///     ValueError: If an element is not a serialized Splinter
+    pub fn from_arrow_binary(column: &Bound<PyAny>) -> PyResult<Vec<Option<Self>>> {
+        arrow::binary_column(column)?
+            .into_iter()
+            .enumerate()
+            .map(|(idx, bytes)| {
+                bytes.map(|bytes| match CowSplinter::from_bytes(bytes) {
+                    Ok(splinter) => Ok(Self::new(splinter)),
+                    Err(e) => Err(PyValueError::new_err(format!("Splinter could not be constructed from element {idx} of the column: {e}"))),
+                })
+                .transpose()
+            })
+            .collect()
+    }
+
     pub fn to_bytes(&self, py: Python) -> Py<PyBytes> {
-        let bytes = self.0.encode_to_bytes();
+        let bytes = self.encoded();
         let py_bytes = PyBytes::new(py, &bytes);
         py_bytes.into()
     }
@@ -128,30 +269,152 @@ impl SplinterWrapper {
     #[classmethod]
     /// Constructs a Splinter from raw byte data.
     ///
-    /// Panics: 
-    ///     This method may cause a panic if the bytes are not formatted correctly.
-    ///     splinter-rs provides a checksum in the serialized data to protect against 
-    ///     corrupted or modified data, and will not cause any undefined behavior, but
-    ///     malicious input can cause a panic.
-    ///     Only use this method on trusted data.
+    /// Accepts any object exporting the buffer protocol, such as bytes, memoryview,
+    /// mmap, or a numpy uint8 array. Only a read-only buffer is shared: the Splinter holds a
+    /// reference to the source object and reads directly from its memory until the
+    /// first mutation, at which point it decodes into an owned Splinter. A writable buffer,
+    /// such as a bytearray or a writable memoryview, is copied, so changing it later leaves the
+    /// Splinter as it was.
+    ///
+    /// The data is checked against the checksum it was written with, so truncated or
+    /// corrupted bytes raise a ValueError rather than being read.
     ///
     /// Args:
-    ///     data array[byte]: The byte data from which to construct the Splinter.
+    ///     data (bytes | memoryview | buffer): The byte data from which to construct the Splinter.
     ///
     /// Returns: 
     ///     Splinter: A Splinter object, or else an error explaining why
     ///     construction failed.
+    ///
+    /// Raises:
+    ///     ValueError: If the data is not a serialized Splinter, or fails its checksum
     pub fn from_bytes(
         _cls: &Bound<'_, PyType>,
-        data: &[u8],
+        data: &Bound<'_, PyAny>,
     ) -> PyResult<Self> {
-        // does this make us no longer zero-copy??
-        let bytes = Bytes::copy_from_slice(data);
+        let bytes = bytes_from_buffer(data)?;
         let splinter = CowSplinter::from_bytes(bytes).map_err(|e| {
             PyValueError::new_err(format!("Splinter could not be constructed from bytes: {e}"))
         })?;
 
-        Ok(Self(splinter))
+        Ok(Self::new(splinter))
+    }
+
+    #[classmethod]
+    /// Opens a serialized Splinter file by memory-mapping it.
+    ///
+    /// The Splinter reads directly from the mapped pages, so queries, iteration and set
+    /// operations never load the whole file into memory. The first mutation decodes the
+    /// mapping into an owned Splinter, after which the file is no longer referenced.
+    ///
+    /// Opening the file verifies its checksum, which reads through the mapping once.
+    /// The file must not be modified or truncated while the Splinter is in use.
+    ///
+    /// Args:
+    ///     path (str | os.PathLike): The path of a file written with to_bytes().
+    ///
+    /// Returns: 
+    ///     Splinter: A Splinter backed by the memory-mapped file.
+    ///
+    /// Raises:
+    ///     OSError: If the file can't be opened
+    ///     ValueError: If the file doesn't hold a serialized Splinter, such as when it's truncated
+    ///         or corrupted
+    pub fn open(
+        _cls: &Bound<'_, PyType>,
+        path: PathBuf,
+    ) -> PyResult<Self> {
+        let splinter = CowSplinter::from_bytes(mmap_bytes(&path)?).map_err(|e| {
+            PyValueError::new_err(format!("Splinter could not be constructed from {}: {e}", path.display()))
+        })?;
+
+        Ok(Self::new(splinter))
+    }
+
+    #[classmethod]
+    /// Constructs a Splinter from a bitmap in the Roaring portable serialization format, as
+    /// written by the Roaring libraries for C, Java, Go and Python (e.g. pyroaring's
+    /// `BitMap.serialize()`).
+    ///
+    /// Array, bitmap and run containers are all supported. Unlike from_bytes(), the data is
+    /// decoded into an owned Splinter, so the source can be discarded afterwards.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): A serialized 32-bit Roaring bitmap.
+    ///
+    /// Returns:
+    ///     Splinter: A Splinter holding the same values
+    ///
+    /// Raises:
+    ///     ValueError: If the data is not a valid Roaring bitmap
+    pub fn from_roaring_bytes(
+        _cls: &Bound<'_, PyType>,
+        data: &Bound<'_, PyAny>,
+    ) -> PyResult<Self> {
+        let bytes = bytes_from_buffer(data)?;
+        let splinter = roaring::decode(&bytes).map_err(|e| {
+            PyValueError::new_err(format!("Splinter could not be constructed from Roaring bytes: {e}"))
+        })?;
+        Ok(Self::new(CowSplinter::from_owned(splinter)))
+    }
+
+    /// Serializes the Splinter in the Roaring portable serialization format, which any Roaring
+    /// library can read (e.g. pyroaring's `BitMap.deserialize()`).
+    ///
+    /// Each container is written as an array, bitmap or runs, whichever is smallest.
+    ///
+    /// Returns:
+    ///     bytes: The serialized Roaring bitmap
+    pub fn to_roaring_bytes(&self, py: Python) -> Py<PyBytes> { PyBytes::new(py, &roaring::encode(&self.encoded())).into() }
+
+    #[classmethod]
+    #[pyo3(signature = (data, bit_order = "little"))]
+    /// Constructs a Splinter from a dense bitmask, holding every i for which bit i of the mask
+    /// is set, such as the output of numpy's `packbits()` or an Arrow validity buffer.
+    ///
+    /// The mask is read 64 bits at a time and encoded directly, rather than inserting each
+    /// value, and the Splinter reads from that encoding until its first mutation.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): The packed bits of the mask.
+    ///     bit_order (str): "little" if the first bit of each byte is its lowest, as in Arrow,
+    ///     or "big" if it's the highest, as in numpy's packbits() by default.
+    ///
+    /// Returns:
+    ///     Splinter: A Splinter holding the positions of the set bits
+    ///
+    /// Raises:
+    ///     ValueError: If bit_order is neither "little" nor "big"
+    ///     OverflowError: If a bit beyond 2**32 - 1 is set
+    pub fn from_bitmask(
+        _cls: &Bound<'_, PyType>,
+        data: &Bound<'_, PyAny>,
+        bit_order: &str,
+    ) -> PyResult<Self> {
+        let msb_first = bitmask::msb_first(bit_order)?;
+        let bytes = bytes_from_buffer(data)?;
+        Ok(Self::new(CowSplinter::Ref(bitmask::decode(&bytes, msb_first)?)))
+    }
+
+    #[pyo3(signature = (length = None, bit_order = "little"))]
+    /// Writes the Splinter as a dense bitmask, in which bit i is set if i is in the Splinter.
+    ///
+    /// Args:
+    ///     length (int | None): The number of bits in the mask, which is padded to a whole
+    ///     number of bytes with unset bits. Defaults to one more than the largest element.
+    ///     bit_order (str): "little" to fill each byte from its lowest bit, as in Arrow, or
+    ///     "big" to fill it from its highest, as numpy's unpackbits() expects by default.
+    ///
+    /// Returns:
+    ///     bytes: The packed bits of the mask
+    ///
+    /// Raises:
+    ///     ValueError: If length is not greater than the largest element, or bit_order is
+    ///     neither "little" nor "big"
+    pub fn to_bitmask(&self, py: Python, length: Option<u64>, bit_order: &str) -> PyResult<Py<PyBytes>> {
+        let msb_first = bitmask::msb_first(bit_order)?;
+        let length = bitmask::mask_len(last(&self.0), length)?;
+        Ok(PyBytes::new(py, &bitmask::encode(&self.encoded(), length, msb_first)).into())
     }
 
     /// Checks if the bitmap contains a single value or multiple values.
@@ -165,25 +428,7 @@ impl SplinterWrapper {
     /// Returns:
     ///     bool | list[bool]: A single boolean if the input was a single integer,
     ///     or a list of booleans if the input was a list.
-    pub fn contains(&self, value: &Bound<PyAny>) -> PyResult<BoolOrVec> {
-        if let Ok(single_val) = value.extract::<u32>() {
-            let result = self.0.contains(single_val);
-            Ok(BoolOrVec::Bool(result))
-        } else if let Ok(vals) = value.extract::<Vec<u32>>() {
-            let results: Vec<bool> = vals.iter().map(|val| {
-                self.0.contains(*val)
-            }).collect();
-
-            Ok(BoolOrVec::Vec(results))
-        } else { 
-            Err(PyTypeError::new_err(
-                format!(
-                    "contains() argument must be an integer or a list of integers, but received an object of type {:#?}", 
-                    value.get_type().name()?
-                )
-            ))
-        }
-    }
+    pub fn contains(&self, value: &Bound<PyAny>) -> PyResult<BoolOrVec> { contains(&self.0, value) }
 
     /// Checks if the bitmap contains multiple values in parallel.
     ///
@@ -201,11 +446,86 @@ impl SplinterWrapper {
     pub fn contains_many_parallel(
         &self, 
         values: Vec<u32>,
-    ) -> Vec<bool> {
-        values
-            .par_iter()
-            .map(|&val| self.0.contains(val))
-            .collect()
+    ) -> Vec<bool> { contains_many_parallel(&self.0, values) }
+
+    /// Checks if the bitmap contains each element of a NumPy array.
+    ///
+    /// Args:
+    ///     values (numpy.ndarray): An array of dtype uint32 or int64. int64 values which
+    ///     cannot be stored in a Splinter are reported as absent.
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A boolean array of the same shape as values.
+    pub fn contains_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        arrays::contains_numpy(py, &self.0, values)
+    }
+
+    /// Checks if the bitmap contains each element of a NumPy array, in parallel.
+    ///
+    /// As with contains_many_parallel(), this is only worthwhile for at least 10,000 values.
+    ///
+    /// Args:
+    ///     values (numpy.ndarray): An array of dtype uint32 or int64.
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A boolean array of the same shape as values.
+    pub fn contains_many_parallel_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        arrays::contains_numpy_parallel(py, &self.0, values)
+    }
+
+    /// Checks if the bitmap contains each of a list or NumPy array of values, without building
+    /// a list of Python bools.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to check for.
+    ///     packed (bool): Pack the results eight to a byte, in the layout of numpy.packbits()
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A boolean array of the same shape as values, or a uint8 array of
+    ///     packed bits if packed
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, packed = false, parallel = false))]
+    pub fn contains_mask(&self, py: Python, values: &Bound<PyAny>, packed: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::contains_mask(py, &self.0, values, packed, parallel)
+    }
+
+    /// Returns the values which are in the bitmap, in their original order.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to filter.
+    ///     indices (bool): Return the indices of the values instead, into the flattened input
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A one-dimensional array of the values present, with the dtype of
+    ///     values or int64 for a list, or of their int64 indices
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_present(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::filter(py, &self.0, values, "filter_present", true, indices, parallel)
+    }
+
+    /// Returns the values which are not in the bitmap, in their original order.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to filter.
+    ///     indices (bool): Return the indices of the values instead, into the flattened input
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A one-dimensional array of the values absent, with the dtype of
+    ///     values or int64 for a list, or of their int64 indices
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_absent(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::filter(py, &self.0, values, "filter_absent", false, indices, parallel)
     }
 
     /// Implements the Python 'in' operator for checking a single value.
@@ -217,8 +537,13 @@ impl SplinterWrapper {
     ///
     /// Returns:
     ///     bool: True if the value is present, False otherwise.
-    fn __contains__(&self, value: u32) -> PyResult<bool> {
-        Ok(self.0.contains(value))
+    fn __contains__(&self, value: &Bound<PyAny>) -> PyResult<bool> {
+        match value.extract() {
+            Ok(value) => Ok(self.0.contains(value)),
+            // like a set, which holds no such int, rather than raising
+            Err(_) if value.is_instance_of::<PyInt>() => Ok(false),
+            Err(err) => Err(err),
+        }
     }
     
     // mimicking python's syntax for sets, instead of lists
@@ -228,104 +553,102 @@ impl SplinterWrapper {
     /// This method is overloaded. It can accept either a single integer or an
     /// iterable of integers.
     ///
+    /// A `range` with a step of 1 or -1 is inserted as a whole, like `add_range()`.
+    ///
     /// Args:
-    ///     values (int | list[int]): The value or values to check for.
+    ///     values (int | list[int] | range): The value or values to insert.
     pub fn add(&mut self, values: &Bound<PyAny>) -> PyResult<()> {
-        if let Ok(val) = values.extract::<u32>() {
-            self.0.insert(val);
-            self.0.to_mut().optimize();
-            Ok(())
-        } else if let Ok(vals) = values.extract::<Vec<u32>>() {
-            vals.iter().for_each(|val| {
-                self.0.insert(*val);
-            });
-            self.0.to_mut().optimize();
-            Ok(())
-        } else {
-            Err(PyTypeError::new_err(
-                format!(
-                    "discard() argument must be an integer or a list of integers, but received an object of type {:#?}", 
-                    values.get_type().name()?
-                )
-            ))
+        match ValuesArg::extract(values, "add")? {
+            ValuesArg::One(value) => self.insert_all([value]),
+            ValuesArg::Range(start, stop) => return self.add_range(start, stop),
+            ValuesArg::Many(values) => self.insert_all(values),
         }
+        Ok(())
     }
 
-    /// Removes a value into the Splinter and returns an error if the value is missing.
+    /// Removes a value from the Splinter and returns an error if the value is missing.
     ///
     /// This method is overloaded. It can accept either a single integer or an
-    /// iterable of integers.
+    /// iterable of integers. Nothing is removed unless every value is present.
+    ///
+    /// A `range` with a step of 1 or -1 is removed as a whole, like `remove_range()`.
     ///
     /// Args:
-    ///     values (int | list[int]): The value or values to check for.
+    ///     values (int | list[int] | range): The value or values to remove.
     pub fn remove(&mut self, value: &Bound<PyAny>) -> PyResult<()> {
-        if let Ok(single_val) = value.extract::<u32>() {
-            if !self.0.remove(single_val) {
-                Err(pyo3::exceptions::PyKeyError::new_err(
-                    format!(
-                        "remove() could not find the key {single_val} in the splinter. For a fault-tolerant alternative to remove(), consider discard()"
-                    )
-                ))
-            } else {
-                // if we get to this point, the operation completed successfully, and we optimize
-                self.0.to_mut().optimize();
-                Ok(())
-            }
-        } else if let Ok(vals) = value.extract::<Vec<u32>>() {
-            for val in &vals {
-                // check to see that all values are actually present: don't mutate anything unless
-                // we know the entire transaction would be successful
-                // for a version of this operation which is fault tolerant, discard is the choice
-                if !self.0.contains(*val) {
-                    return Err(pyo3::exceptions::PyKeyError::new_err(
-                        format!(
-                            "remove() could not find the key {val} in the splinter.\nFor a fault-tolerant alternative to remove(), consider discard()"
-                        )
-                    ));
-                }
-            }
-            // actually remove them
-            vals.iter().for_each(|val| {
-                self.0.remove(*val);
-            });
-            // if we ge to this point, the operation completed successfully
-            self.0.to_mut().optimize();
-            Ok(())
-        } else { 
-            Err(PyTypeError::new_err(
-                format!(
-                    "discard() argument must be an integer or a list of integers, but received an object of type {:#?}", 
-                    value.get_type().name()?
-                )
-            ))
-        }
+        let values = match ValuesArg::extract(value, "remove")? {
+            ValuesArg::One(value) => vec![value],
+            ValuesArg::Range(start, stop) => return self.remove_range(start, stop),
+            ValuesArg::Many(values) => values,
+        };
+        self.remove_all(&values).map_err(missing_key)
     }
 
-    /// Removes a value into the Splinter, or does nothing if the value is missing.
+    /// Removes a value from the Splinter, or does nothing if the value is missing.
     ///
     /// This method is overloaded. It can accept either a single integer or an
     /// iterable of integers.
     ///
+    /// A `range` with a step of 1 or -1 is removed as a whole, like `discard_range()`.
+    ///
     /// Args:
-    ///     values (int | list[int]): The value or values to check for.
+    ///     values (int | list[int] | range): The value or values to remove.
     pub fn discard(&mut self, value: &Bound<PyAny>) -> PyResult<()> {
-        if let Ok(single_val) = value.extract::<u32>() {
-            self.0.remove(single_val);
-            self.0.to_mut().optimize();
-            Ok(())
-        } else if let Ok(vals) = value.extract::<Vec<u32>>() {
-            vals.iter().for_each(|val| {
-                self.0.remove(*val);
-            });
-            self.0.to_mut().optimize();
-            Ok(())
-        } else { 
-            Err(PyTypeError::new_err(
-                format!(
-                    "discard() argument must be an integer or a list of integers, but received an object of type {:#?}", 
-                    value.get_type().name()?
-                )
-            ))
+        match ValuesArg::extract(value, "discard")? {
+            ValuesArg::One(value) => self.discard_all([value]),
+            ValuesArg::Range(start, stop) => self.discard_range(start, stop),
+            ValuesArg::Many(values) => self.discard_all(values),
+        }
+        Ok(())
+    }
+
+    /// Inserts every integer in [start, stop) into the Splinter.
+    ///
+    /// Whole partitions of the range are inserted at once, rather than one value at a time.
+    ///
+    /// Args:
+    ///     start (int): The first value to insert.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Raises:
+    ///     OverflowError: If the range reaches outside of 0 to 2**32 - 1
+    pub fn add_range(&mut self, start: i64, stop: i64) -> PyResult<()> {
+        let range = ranges::insertable(start, stop, "add_range")?;
+        ranges::insert_range(self.0.to_mut(), range);
+        self.optimize_if_auto();
+        Ok(())
+    }
+
+    /// Removes every integer in [start, stop) from the Splinter, and returns an error if any of
+    /// them is missing.
+    ///
+    /// Args:
+    ///     start (int): The first value to remove.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Raises:
+    ///     KeyError: If any value in the range is missing, in which case nothing is removed
+    pub fn remove_range(&mut self, start: i64, stop: i64) -> PyResult<()> {
+        if !ranges::contains_range(&self.0, start, stop) {
+            return Err(missing_range(start, stop));
+        }
+        self.discard_range(start, stop);
+        Ok(())
+    }
+
+    /// Removes every integer in [start, stop) from the Splinter, skipping any that are missing.
+    ///
+    /// Whole partitions of the range are dropped at once, rather than one value at a time.
+    ///
+    /// Args:
+    ///     start (int): The first value to remove.
+    ///     stop (int): The end of the range, itself excluded.
+    pub fn discard_range(&mut self, start: i64, stop: i64) {
+        let range = value_range(start, stop);
+        // leave serialized splinters untouched if there's nothing to remove
+        if iter::count_within(&self.0, &range) > 0 {
+            ranges::remove_range(self.0.to_mut(), range);
+            self.optimize_if_auto();
         }
     }
 
@@ -335,19 +658,19 @@ impl SplinterWrapper {
     /// iterable of Splinters.
     ///
     /// Args:
-    ///     splinters (Splinter | list[Splinter]): The object or objects to merge with
+    ///     splinters (Splinter | FrozenSplinter | list[Splinter | FrozenSplinter]): The object or objects to merge with
     pub fn merge(&mut self, splinters: &Bound<PyAny>) -> PyResult<()> {
-        if let Ok(rhs) = splinters.extract::<SplinterWrapper>() {
+        if let Ok(rhs) = splinters.extract::<SplinterArg>() {
             // todo: ask Carl if this is kosher
-            *self.0.to_mut() |= &rhs.0;
-            self.0.to_mut().optimize();
+            with_splinter!(rhs, |r| *self.0.to_mut() |= r);
+            self.optimize_if_auto();
             Ok(())
-        } else if let Ok(splinter_list) = splinters.extract::<Vec<SplinterWrapper>>() {
+        } else if let Ok(splinter_list) = splinters.extract::<Vec<SplinterArg>>() {
             // is this kosher? likely a more effective way to do this, right??
             for rhs in splinter_list {
-                *self.0.to_mut() |= &rhs.0;
+                with_splinter!(rhs, |r| *self.0.to_mut() |= r);
             };
-            self.0.to_mut().optimize();
+            self.optimize_if_auto();
             Ok(())
         } else {
             Err(PyTypeError::new_err(
@@ -359,6 +682,30 @@ impl SplinterWrapper {
         }
     }
 
+    /// Compacts the Splinter's internal representation, choosing the smallest encoding for
+    /// each of its partitions.
+    ///
+    /// This runs automatically after every modification, such as add() or update(), unless
+    /// auto_optimize has been turned off. A Splinter loaded from bytes and not yet modified is
+    /// left as it is.
+    pub fn optimize(&mut self) {
+        if let CowSplinter::Owned(_) = *self.0 {
+            self.0.optimize();
+        }
+    }
+
+    /// Whether methods which modify the Splinter, such as add() and discard(), optimize it
+    /// after every call.
+    ///
+    /// Optimizing re-encodes the whole Splinter, so when making many small modifications in a
+    /// row it is much faster to turn this off and call optimize() once at the end, or to build the
+    /// Splinter with a SplinterBuilder. Defaults to True.
+    #[getter]
+    fn get_auto_optimize(&self) -> bool { self.1 }
+
+    #[setter]
+    fn set_auto_optimize(&mut self, auto_optimize: bool) { self.1 = auto_optimize }
+
     // for cut, not currently enabling multiple sequential cuts, since it's not clear what the
     // behavior on this is, and don't want to give the user a knife to cut themselves with
     // todo: double check that this isn't terrible
@@ -370,11 +717,13 @@ impl SplinterWrapper {
     /// returned to the caller.
     ///
     /// Args:
-    ///     splinter Splinter: A Splinter object to intersect with
+    ///     splinter (Splinter | FrozenSplinter): A Splinter object to intersect with
     ///
     /// Returns: 
     ///     Splinter
-    pub fn cut(&mut self, rhs: SplinterWrapper) -> Self { Self(CowSplinter::from_owned(self.0.to_mut().cut(&rhs.0)))}
+    pub fn cut(&mut self, rhs: SplinterArg) -> Self {
+        with_splinter!(rhs, |r| Self::new(CowSplinter::from_owned(self.0.to_mut().cut(r))))
+    }
 
     /// Returns the number of elements in the Splinter that are less than or equal to the given
     /// value.
@@ -398,169 +747,762 @@ impl SplinterWrapper {
     ///
     /// Returns: 
     ///     (int | None): the element at the relevant index, or else a None if overflowed
-    pub fn select(&self, idx: &Bound<PyAny>) -> PyResult<Option<u32>> {
-        if let Ok(val) = idx.extract::<usize>() {
-            Ok(self.0.select(val))
-        } else if let Ok(val) = idx.extract::<isize>() {
-            if let Some(index) = self.0.cardinality().checked_sub(val as usize) {
-                Ok(self.0.select(index))
-            } else { Ok(None) }
-        } else {
-            Err(PyTypeError::new_err(
-                format!(
-                    "select() argument must be an integer, but received an object of type {:#?}",
-                    idx.get_type().name()?
-                )
-            ))
-        }
-    }
+    pub fn select(&self, idx: &Bound<PyAny>) -> PyResult<Option<u32>> { select(&self.0, idx) }
     
-    pub fn position(&self, value: u32) -> PyResult<usize> {
-        if let Some(pos) = self.0.position(value) {
-            Ok(pos)
-        } else {
-            Err(PyValueError::new_err(format!("element {value} does not exist in this Splinter")))
-        }
+    pub fn position(&self, value: u32) -> PyResult<usize> { position(&self.0, value) }
 
+    /// Ranks each of a list or NumPy array of values, like rank().
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to rank. Negative values rank 0.
+    ///     parallel (bool): Rank the values in parallel, which as with contains_many_parallel()
+    ///     is only worthwhile for at least 10,000 values
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A uint64 array of the same shape as values
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn rank_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        arrays::rank_many(py, &self.0, values, parallel)
     }
 
-    // basic bitwise set operators
-    fn __and__(&self, rhs: &Self) -> Self { Self(&self.0 & &rhs.0) }
-    fn __or__(&self, rhs: &Self) -> Self { Self(&self.0 | &rhs.0) }
-    fn __xor__(&self, rhs: &Self) -> Self { Self(&self.0 ^ &rhs.0) }
-    fn __sub__(&self, rhs: &Self) -> Self { Self(&self.0 - &rhs.0) }
+    /// Selects the element at each of a list or NumPy array of indices, like select().
+    ///
+    /// Args:
+    ///     indices (list[int] | numpy.ndarray): The indices to select. Negative indices count
+    ///     back from the end.
+    ///     parallel (bool): Select the elements in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: An int64 array of the same shape as indices, holding -1 for indices
+    ///     out of bounds
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (indices, parallel = false))]
+    pub fn select_many<'py>(&self, py: Python<'py>, indices: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        arrays::select_many(py, &self.0, indices, parallel)
+    }
 
-    // reverse bitwise set operators, for completeness
-    fn __rand__(&self, rhs: &Self) -> Self { Self(&self.0 & &rhs.0) }
-    fn __ror__(&self, rhs: &Self) -> Self { Self(&self.0 | &rhs.0) }
-    fn __rxor__(&self, rhs: &Self) -> Self { Self(&self.0 ^ &rhs.0) }
-    fn __rsub__(&self, rhs: &Self) -> Self { Self(&self.0 - &rhs.0) }
+    /// Finds the index of each of a list or NumPy array of values, like position().
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to look for
+    ///     parallel (bool): Look for the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: An int64 array of the same shape as values, holding -1 rather than
+    ///     raising an error for values which are absent
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn position_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        arrays::position_many(py, &self.0, values, parallel)
+    }
+
+    /// Returns the smallest element of the Splinter, or None if it is empty.
+    pub fn min(&self) -> Option<u32> { self.0.select(0) }
+
+    /// Returns the largest element of the Splinter, or None if it is empty.
+    pub fn max(&self) -> Option<u32> { last(&self.0) }
+
+    /// Returns the smallest element of the Splinter greater than the given value.
+    ///
+    /// Args:
+    ///     value (int): the value to search after, which does not need to be in the Splinter
+    ///
+    /// Returns:
+    ///     (int | None): the next element, or None if no element is greater than value
+    pub fn successor(&self, value: i64) -> Option<u32> { successor(&self.0, value) }
+
+    /// Returns the largest element of the Splinter less than the given value.
+    ///
+    /// Args:
+    ///     value (int): the value to search before, which does not need to be in the Splinter
+    ///
+    /// Returns:
+    ///     (int | None): the previous element, or None if no element is less than value
+    pub fn predecessor(&self, value: i64) -> Option<u32> { predecessor(&self.0, value) }
+
+    /// Returns the smallest value greater than or equal to the given value which is not in the
+    /// Splinter.
+    ///
+    /// Runs of consecutive elements are skipped over with a binary search on `select()`, so this
+    /// stays fast even inside a run of millions of elements.
+    ///
+    /// Args:
+    ///     value (int): the value to start searching from
+    ///
+    /// Returns:
+    ///     (int | None): the next absent value, or None if every value from here up to 2**32 - 1
+    ///     is in the Splinter
+    pub fn next_absent(&self, value: i64) -> Option<u32> { next_absent(&self.0, value) }
+
+    /// Returns the elements of the Splinter between lo and hi as a new Splinter.
+    ///
+    /// Unlike slicing with `[]`, which selects by position and returns a list, this selects by
+    /// value, following Python's `range()` convention of including lo and excluding hi. The
+    /// result stays compressed, ready for further set operations.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to include.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     Splinter: The elements within [lo, hi)
+    pub fn range(&self, lo: i64, hi: i64) -> Self {
+        Self::new(CowSplinter::from_owned(ranges::slice(&self.0, value_range(lo, hi))))
+    }
+
+    /// Counts the elements of the Splinter between lo and hi, including lo and excluding hi.
+    ///
+    /// The count is computed from `rank()`, without visiting the elements in between.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to count.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     int: The number of elements within [lo, hi)
+    pub fn range_count(&self, lo: i64, hi: i64) -> usize { iter::count_within(&self.0, &value_range(lo, hi)) }
+
+    // basic bitwise set operators
+    // the right-hand side may also be a set, frozenset, range, list or NumPy array of ints, while
+    // anything else returns NotImplemented so that Python can try the reflected operator
+    // a range reaching past the values a Splinter can hold raises an OverflowError from | and ^,
+    // whose results would hold the values outside, and is clamped for & and -
+    fn __and__(&self, rhs: Operand) -> Self { with_set_arg!(rhs.0, |r| Self::new(&*self.0 & r)) }
+    fn __or__(&self, rhs: Operand) -> PyResult<Self> { with_set_arg!(rhs.whole("|")?, |r| Ok(Self::new(&*self.0 | r))) }
+    fn __xor__(&self, rhs: Operand) -> PyResult<Self> { with_set_arg!(rhs.whole("^")?, |r| Ok(Self::new(&*self.0 ^ r))) }
+    fn __sub__(&self, rhs: Operand) -> Self { with_set_arg!(rhs.0, |r| Self::new(&*self.0 - r)) }
+
+    // reverse bitwise set operators, for completeness
+    fn __rand__(&self, rhs: Operand) -> Self { self.__and__(rhs) }
+    fn __ror__(&self, rhs: Operand) -> PyResult<Self> { self.__or__(rhs) }
+    fn __rxor__(&self, rhs: Operand) -> PyResult<Self> { self.__xor__(rhs) }
+    // unlike the others, subtraction doesn't commute: this computes rhs - self
+    fn __rsub__(&self, rhs: Operand) -> PyResult<Self> {
+        let mut out = rhs.whole("-")?.into_splinter();
+        out -= &*self.0;
+        Ok(Self::new(CowSplinter::from_owned(out)))
+    }
 
     // assign bitwise set operators
     // todo: ask Carl if this is kosher
-    fn __iand__(&mut self, rhs: &Self) { *self.0.to_mut() &= &rhs.0 }
-    fn __ior__(&mut self, rhs: &Self) { *self.0.to_mut() |= &rhs.0 }
-    fn __ixor__(&mut self, rhs: &Self) { *self.0.to_mut() ^= &rhs.0 }
-    fn __isub__(&mut self, rhs: &Self) { *self.0.to_mut() -= &rhs.0 }
+    fn __iand__(&mut self, rhs: Operand) {
+        with_set_arg!(rhs.0, |r| *self.0.to_mut() &= r);
+        self.optimize_if_auto();
+    }
+    fn __ior__(&mut self, rhs: Operand) -> PyResult<()> {
+        with_set_arg!(rhs.whole("|=")?, |r| *self.0.to_mut() |= r);
+        self.optimize_if_auto();
+        Ok(())
+    }
+    fn __ixor__(&mut self, rhs: Operand) -> PyResult<()> {
+        with_set_arg!(rhs.whole("^=")?, |r| *self.0.to_mut() ^= r);
+        self.optimize_if_auto();
+        Ok(())
+    }
+    fn __isub__(&mut self, rhs: Operand) {
+        with_set_arg!(rhs.0, |r| *self.0.to_mut() -= r);
+        self.optimize_if_auto();
+    }
 
     // set comparison operations
-    fn __eq__(&self, rhs: &Self) -> bool { self.0 == rhs.0 }
-    fn __ne__(&self, rhs: &Self) -> bool { self.0 != rhs.0 }
-    fn __le__(&self, rhs: &Self) -> bool { (&self.0 & &rhs.0) == self.0 }
-    fn __lt__(&self, rhs: &Self) -> bool { (self.0.cardinality() < rhs.0.cardinality()) && self.__le__(rhs) }
-    fn __ge__(&self, rhs: &Self) -> bool { (&self.0 & &rhs.0) == rhs.0 }
-    fn __gt__(&self, rhs: &Self) -> bool { self.0.cardinality() > rhs.0.cardinality() &&  self.__ge__(rhs) }
-
-    // for serialization with pickle
-    fn __getstate__(&self, py: Python) -> PyObject {
-        let bytes = self.to_bytes(py);
-        bytes.into()
-    }
-    // for deserializing from pickle
-    fn __setstate__(&mut self, bytes: &[u8]) -> PyResult<()> {
-        self.0 = CowSplinter::from_bytes(Bytes::copy_from_slice(bytes)).map_err(|e| {
+    // only other sets compare equal, as with Python's set, so == agrees with FrozenSplinter's hash
+    // <= and >= walk the serialized forms, as issubset() does
+    fn __eq__(&self, rhs: SetOperand) -> bool { with_set_arg!(rhs.0, |r| *self.0 == *r) }
+    fn __ne__(&self, rhs: SetOperand) -> bool { !self.__eq__(rhs) }
+    // a clamped range still holds the values left out of it, which no Splinter contains
+    fn __le__(&self, rhs: Operand) -> bool { overlap::is_subset(&self.encoded(), &rhs.0.encoded()) }
+    fn __lt__(&self, rhs: Operand) -> bool { (rhs.1 || self.0.cardinality() < rhs.0.cardinality()) && self.__le__(rhs) }
+    fn __ge__(&self, rhs: Operand) -> bool { !rhs.1 && overlap::is_subset(&rhs.0.encoded(), &self.encoded()) }
+    fn __gt__(&self, rhs: Operand) -> bool { !rhs.1 && self.0.cardinality() > rhs.0.cardinality() &&  self.__ge__(rhs) }
+
+    // stops NumPy from applying operators element by element when an array is on the left,
+    // so that they fall through to the reflected operators above
+    #[classattr]
+    fn __array_ufunc__(py: Python) -> PyObject { py.None() }
+
+    // for serialization with pickle, along with whether the Splinter optimizes itself
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        Ok((self.to_bytes(py), self.1).into_pyobject(py)?.into_any().unbind())
+    }
+    // for deserializing from pickle, which also reads the bare bytes pickled by earlier versions
+    fn __setstate__(&mut self, state: &Bound<PyAny>) -> PyResult<()> {
+        let (bytes, auto_optimize) = match state.downcast::<PyTuple>() {
+            Ok(state) => state.extract::<(Bound<PyAny>, bool)>()?,
+            Err(_) => (state.clone(), true),
+        };
+        self.0 = CowSplinter::from_bytes(bytes_from_buffer(&bytes)?).map(CachedSplinter::from).map_err(|e| {
             PyValueError::new_err(format!("Failed to deserialize Splinter from bytes: {e}"))
         })?;
+        self.1 = auto_optimize;
         Ok(())
     }
 
     /// tells pickle how to find the class and serialize it
-    fn __reduce__<'py>(&self, py: Python<'py>,) -> (PyObject, PyObject, PyObject) {
+    fn __reduce__<'py>(&self, py: Python<'py>,) -> PyResult<(PyObject, PyObject, PyObject)> {
         let class = Self::type_object(py).into();
         let args = PyTuple::empty(py).into();
-        let state = self.__getstate__(py);
-        (class, args, state)
+        let state = self.__getstate__(py)?;
+        Ok((class, args, state))
     }
 
     // copy protocol
     fn copy(&self) -> Self { self.clone() }
     // making it easily available from python
     fn __copy__(&self) -> Self { self.clone() }
+    // a Splinter holds no Python objects, so a deep copy is the same as a shallow one
+    fn __deepcopy__(&self, _memo: &Bound<PyAny>) -> Self { self.clone() }
+
+    /// Returns an immutable FrozenSplinter holding the same elements.
+    ///
+    /// If this Splinter was loaded from bytes and has not been modified since, the
+    /// FrozenSplinter shares its buffer without copying. Otherwise the Splinter is
+    /// encoded once.
+    ///
+    /// Returns:
+    ///     FrozenSplinter: an immutable, hashable copy of this Splinter
+    pub fn freeze(&self) -> FrozenSplinterWrapper {
+        FrozenSplinterWrapper::new(SplinterRef::from_bytes(self.encoded()).expect("encoded splinters are always valid"))
+    }
 
 
     // explicit set methods
     // omitting the usual snake_case _ to more closely fit the Python idiom
+    // like Python's set, these accept any iterable of ints as well as Splinters. The queries,
+    // intersection() and difference() also accept ints outside of 0 to 2**32 - 1, which no
+    // Splinter holds, while union() and symmetric_difference() raise an OverflowError for them
 
     /// Returns true if self and rhs have no overlap, and false otherwise.
     ///
-    /// This is an explicit implementation of (self & rhs).is_empty().
+    /// This is equivalent to (self & rhs).is_empty(), but stops at the first common element
+    /// without building the intersection.
+    ///
+    /// Both sides are compared in their serialized form. A Splinter is encoded in O(len) the
+    /// first time it's compared after being modified, and that encoding is reused until it next
+    /// changes; an iterable rhs is collected and encoded on every call.
     ///
     /// Args:
-    ///     rhs Splinter: a Splinter object to compare against   
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against   
     ///
     /// Returns:
     ///     bool: true if there is no overlap, false otherwise
-    fn isdisjoint(&self, rhs: &Self) -> bool { (&self.0 & &rhs.0).is_empty() }
+    fn isdisjoint(&self, rhs: QueryArg) -> bool { overlap::is_disjoint(&self.encoded(), &rhs.0.encoded()) }
 
     /// Returns true if self is a subset of rhs, and false otherwise.
     ///
-    /// This is an explicit implementation of (self & rhs) == self.
+    /// This is equivalent to (self & rhs) == self, but stops at the first element of self
+    /// missing from rhs without building the intersection.
+    ///
+    /// Both sides are compared in their serialized form. A Splinter is encoded in O(len) the
+    /// first time it's compared after being modified, and that encoding is reused until it next
+    /// changes; an iterable rhs is collected and encoded on every call.
     ///
     /// Args:
-    ///     rhs Splinter: a Splinter object to compare against   
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against   
     ///
     /// Returns:
     ///     bool: true if self is a subset of rhs, false otherwise
-    fn issubset(&self, rhs: &Self) -> bool { self.__le__(rhs) }
+    fn issubset(&self, rhs: QueryArg) -> bool { overlap::is_subset(&self.encoded(), &rhs.0.encoded()) }
     
     /// Returns true if self is a superset of rhs, and false otherwise.
     ///
-    /// This is an explicit implementation of (self & rhs) == rhs.
+    /// This is equivalent to (self & rhs) == rhs, but stops at the first element of rhs
+    /// missing from self without building the intersection.
+    ///
+    /// Both sides are compared in their serialized form. A Splinter is encoded in O(len) the
+    /// first time it's compared after being modified, and that encoding is reused until it next
+    /// changes; an iterable rhs is collected and encoded on every call.
     ///
     /// Args:
-    ///     rhs Splinter: a Splinter object to compare against   
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against   
     ///
     /// Returns:
     ///     bool: true if self is a subset of rhs, false otherwise
-    fn issuperset(&self, rhs: &Self) -> bool { self.__ge__(rhs) }
+    fn issuperset(&self, rhs: QueryArg) -> bool { rhs.1 == 0 && overlap::is_subset(&rhs.0.encoded(), &self.encoded()) }
+
+    // sizes of set operations, counted without building the result
+
+    /// Returns the number of elements in both self and rhs, without building the intersection.
+    ///
+    /// As with issubset(), this walks the serialized forms, encoding a modified Splinter once
+    /// and an iterable rhs on every call.
+    ///
+    /// Args:
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against
+    ///
+    /// Returns:
+    ///     int: len(self & rhs)
+    fn intersection_len(&self, rhs: QueryArg) -> usize { overlap::intersection_len(&self.encoded(), &rhs.0.encoded()) }
+
+    /// Returns the number of elements in either self or rhs, without building the union.
+    ///
+    /// Args:
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against
+    ///
+    /// Returns:
+    ///     int: len(self | rhs)
+    fn union_len(&self, rhs: QueryArg) -> usize {
+        self.0.cardinality() + rhs.cardinality() - self.intersection_len(rhs)
+    }
+
+    /// Returns the number of elements in self but not in rhs, without building the difference.
+    ///
+    /// Args:
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against
+    ///
+    /// Returns:
+    ///     int: len(self - rhs)
+    fn difference_len(&self, rhs: QueryArg) -> usize { self.0.cardinality() - self.intersection_len(rhs) }
+
+    /// Returns the Jaccard similarity of self and rhs, the size of their intersection divided by
+    /// the size of their union.
+    ///
+    /// This costs the same as intersection_len().
+    ///
+    /// Args:
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against
+    ///
+    /// Returns:
+    ///     float: a similarity between 0.0 and 1.0, which is 1.0 if both are empty
+    fn jaccard(&self, rhs: QueryArg) -> f64 {
+        let (len, rhs_len) = (self.0.cardinality(), rhs.cardinality());
+        overlap::jaccard(self.intersection_len(rhs), len, rhs_len)
+    }
 
     // todo: consolidate this with merge???
 
     /// Returns the union of one or more Splinters
     ///
     /// Args:
-    ///     rhs list[Splinter]: an iterable of one or more Splinters to combine
+    ///     rhs list[Splinter | FrozenSplinter | Iterable[int]]: one or more Splinters to combine
     ///
     /// Returns:
     ///     Splinter: a combined splinter made up of the union of all provided values
     #[pyo3(signature = (*rhs))]
     fn union(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> {
-        let mut result = self.0.clone();
+        let mut result = (*self.0).clone();
         for other in rhs.iter() {
-            let other_splinter = other.extract::<PyRef<Self>>()?;
-            *result.to_mut() |= &other_splinter.0;
+            with_set_arg!(other.extract::<SetArg>()?, |r| *result.to_mut() |= r);
         }
-        Ok(Self(result))
+        Ok(Self::new(result))
     }
 
     /// Returns the intersection of one or more Splinters
     ///
     /// Args:
-    ///     rhs: list[Splinter]: an iterable of one or more Splinters to combine
+    ///     rhs: list[Splinter | FrozenSplinter | Iterable[int]]: one or more Splinters to combine
     ///
     /// Return: 
     ///     Splinter: a combined splinter made up of the intersection of all provided values
     #[pyo3(signature = (*rhs))]
     fn intersection(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> { 
-        let mut result = self.0.clone();
+        let mut result = (*self.0).clone();
         for other in rhs.iter() {
-            let other_splinter = other.extract::<PyRef<Self>>()?;
-            *result.to_mut() &= &other_splinter.0;
+            with_set_arg!(other.extract::<QueryArg>()?.0, |r| *result.to_mut() &= r);
         }
-        Ok(Self(result))
+        Ok(Self::new(result))
     }
+
+    /// Returns the elements of self which are in none of the others
+    ///
+    /// Args:
+    ///     rhs: list[Splinter | FrozenSplinter | Iterable[int]]: one or more Splinters to subtract
+    ///
+    /// Return: 
+    ///     Splinter: the difference between self and all provided values
+    #[pyo3(signature = (*rhs))]
+    fn difference(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> {
+        let mut result = (*self.0).clone();
+        for other in rhs.iter() {
+            with_set_arg!(other.extract::<QueryArg>()?.0, |r| *result.to_mut() -= r);
+        }
+        Ok(Self::new(result))
+    }
+
+    /// Returns the elements which are in exactly one of self and rhs
+    ///
+    /// Args:
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against
+    ///
+    /// Return: 
+    ///     Splinter: the symmetric difference of self and rhs
+    fn symmetric_difference(&self, rhs: SetArg) -> Self { with_set_arg!(rhs, |r| Self::new(&*self.0 ^ r)) }
+
+    /// Adds the elements of one or more Splinters to self
+    ///
+    /// Args:
+    ///     rhs: list[Splinter | FrozenSplinter | Iterable[int]]: one or more Splinters to add
+    #[pyo3(signature = (*rhs))]
+    fn update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> {
+        let others: Vec<SetArg> = Self::operands(slf, rhs)?;
+        let mut this = slf.borrow_mut();
+        for other in others {
+            match other {
+                // a few values are merged into the runs of a splinter, which |= would walk
+                SetArg::Values(values) if values.cardinality() <= ranges::MAX_WALK => ranges::insert_values(&mut this.0, values.iter()),
+                other => with_set_arg!(other, |r| *this.0.to_mut() |= r),
+            }
+        }
+        this.optimize_if_auto();
+        Ok(())
+    }
+
+    /// Keeps only the elements of self which are also in every one of the others
+    ///
+    /// Args:
+    ///     rhs: list[Splinter | FrozenSplinter | Iterable[int]]: one or more Splinters to intersect with
+    #[pyo3(signature = (*rhs))]
+    fn intersection_update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> {
+        let others: Vec<QueryArg> = Self::operands(slf, rhs)?;
+        slf.borrow_mut().modify(|splinter| {
+            for other in others {
+                with_set_arg!(other.0, |r| *splinter &= r);
+            }
+        });
+        Ok(())
+    }
+
+    /// Removes the elements of one or more Splinters from self
+    ///
+    /// Args:
+    ///     rhs: list[Splinter | FrozenSplinter | Iterable[int]]: one or more Splinters to subtract
+    #[pyo3(signature = (*rhs))]
+    fn difference_update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> {
+        let others: Vec<QueryArg> = Self::operands(slf, rhs)?;
+        let mut this = slf.borrow_mut();
+        for other in others {
+            match other.0 {
+                SetArg::Values(values) if values.cardinality() <= ranges::MAX_WALK => ranges::remove_values(&mut this.0, values.iter()),
+                other => with_set_arg!(other, |r| *this.0.to_mut() -= r),
+            }
+        }
+        this.optimize_if_auto();
+        Ok(())
+    }
+
+    /// Keeps only the elements which are in exactly one of self and rhs
+    ///
+    /// Args:
+    ///     rhs (Splinter | FrozenSplinter | Iterable[int]): a Splinter object to compare against
+    fn symmetric_difference_update(slf: &Bound<'_, Self>, rhs: &Bound<PyAny>) -> PyResult<()> {
+        let rhs = PyTuple::new(slf.py(), [rhs])?;
+        let mut others: Vec<SetArg> = Self::operands(slf, &rhs)?;
+        slf.borrow_mut().modify(|splinter| with_set_arg!(others.remove(0), |r| *splinter ^= r));
+        Ok(())
+    }
+
+    /// Removes and returns the largest element of the Splinter
+    ///
+    /// Returns:
+    ///     int: the removed element
+    ///
+    /// Raises:
+    ///     KeyError: if the Splinter is empty
+    fn pop(&mut self) -> PyResult<u32> {
+        let Some(value) = last(&self.0) else {
+            return Err(PyKeyError::new_err("pop from an empty Splinter"));
+        };
+        self.discard_all([value]);
+        Ok(value)
+    }
+
+    /// Removes every element from the Splinter
+    fn clear(&mut self) { self.0 = CowSplinter::default().into() }
 }
 
-/// Iterator class to implement __iter__ on SplinterWrapper
-#[pyclass(name = "SplinterIter")]
-struct SplinterIter {
-    inner: vec::IntoIter<u32>
+impl SplinterWrapper {
+    /// Extracts the arguments of an in-place set method, as a SetArg or a QueryArg. An argument
+    /// which is the Splinter being updated is copied, since it can't be borrowed again while
+    /// it's being modified
+    fn operands<'py, A>(slf: &Bound<'py, Self>, args: &Bound<'py, PyTuple>) -> PyResult<Vec<A>>
+    where
+        A: FromPyObject<'py> + From<SetArg<'py>>,
+    {
+        args.iter()
+            .map(|arg| match arg.is(slf) {
+                true => Ok(SetArg::Values(Splinter::from(slf.borrow().0.clone())).into()),
+                false => arg.extract(),
+            })
+            .collect()
+    }
 }
 
-#[pymethods]
-impl SplinterIter {
-    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> { slf }
-    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<u32> { slf.inner.next() }
+// shared implementations of the modifying methods, which SignedSplinter also calls on the
+// Splinter holding its shifted values, so that both optimize at the same points
+impl SplinterWrapper {
+    /// Applies a modification to the splinter, then optimizes it if auto_optimize is on
+    pub(crate) fn modify(&mut self, op: impl FnOnce(&mut Splinter)) {
+        op(self.0.to_mut());
+        self.optimize_if_auto();
+    }
+
+    pub(crate) fn insert_all(&mut self, values: impl IntoIterator<Item = u32>) {
+        ranges::insert_values(&mut self.0, values);
+        self.optimize_if_auto();
+    }
+
+    pub(crate) fn discard_all(&mut self, values: impl IntoIterator<Item = u32>) {
+        ranges::remove_values(&mut self.0, values);
+        self.optimize_if_auto();
+    }
+
+    /// Removes every one of the values, or returns the first which is missing without
+    /// removing anything
+    pub(crate) fn remove_all(&mut self, values: &[u32]) -> Result<(), u32> {
+        if let Some(&missing) = values.iter().find(|&&value| !self.0.contains(value)) {
+            return Err(missing);
+        }
+        self.discard_all(values.iter().copied());
+        Ok(())
+    }
+}
+
+/// The error remove() raises for a missing value
+pub(crate) fn missing_key(value: impl std::fmt::Display) -> PyErr {
+    PyKeyError::new_err(format!(
+        "remove() could not find the key {value} in the splinter. For a fault-tolerant alternative to remove(), consider discard()"
+    ))
+}
+
+/// The error remove_range() raises when part of the range is missing
+pub(crate) fn missing_range(start: i64, stop: i64) -> PyErr {
+    PyKeyError::new_err(format!(
+        "remove_range() could not find every key in [{start}, {stop}) in the splinter. For a fault-tolerant alternative to remove_range(), consider discard_range()"
+    ))
+}
+
+/// The argument of add(), remove() and discard(): a single value, a `range` with a step of 1
+/// or -1, which is handled as a whole, or a list of values
+pub(crate) enum ValuesArg<T> {
+    One(T),
+    Range(i64, i64),
+    Many(Vec<T>),
+}
+
+impl<T: for<'py> FromPyObject<'py>> ValuesArg<T> {
+    pub(crate) fn extract(values: &Bound<PyAny>, method: &str) -> PyResult<Self> {
+        if let Ok(value) = values.extract() {
+            Ok(Self::One(value))
+        } else if let Some((start, stop)) = ranges::contiguous(values)? {
+            Ok(Self::Range(start, stop))
+        } else if let Ok(values) = values.extract() {
+            Ok(Self::Many(values))
+        } else {
+            Err(PyTypeError::new_err(format!(
+                "{method}() argument must be an integer or a list of integers, but received an object of type {:#?}",
+                values.get_type().name()?
+            )))
+        }
+    }
+}
+
+// shared implementations of the read-only methods, so that they behave identically on
+// Splinter and FrozenSplinter regardless of the splinter-rs type backing them
+
+pub(crate) fn get_item(splinter: &impl PartitionRead<High>, index: &Bound<PyAny>) -> PyResult<UintOrVec> {
+    if let Ok(i_idx) = index.extract::<isize>() {
+        let len = splinter.cardinality();
+        let mut actual_index = i_idx;
+
+        if actual_index < 0 {
+            actual_index += len as isize;
+        }
+
+        match splinter.select(actual_index as usize) {
+            Some(value) => Ok(UintOrVec::U32(value)),
+            None => Err(pyo3::exceptions::PyIndexError::new_err( "splinter index out of range"))
+        }
+    } else if let Ok(u_idx) = index.extract::<usize>() {
+
+        match splinter.select(u_idx) {
+            Some(value) => Ok(UintOrVec::U32(value)),
+            None => Err(pyo3::exceptions::PyIndexError::new_err( "splinter index out of range"))
+        }
+    } else if let Ok(slice) = index.downcast::<PySlice>() {
+
+        let len = splinter.cardinality() as isize;
+        let indices = slice.indices(len)?;
+
+        let mut sliced_values = Vec::with_capacity(indices.slicelength);
+
+        // the step = 0 case is caught by pyo3 in the construction of the PySlice type
+        // we do not need to account for it here
+        if indices.step > 0 {
+            sliced_values.extend(
+                splinter.iter()
+                    .skip(indices.start as usize)
+                    .step_by(indices.step as usize)
+                    .take(indices.slicelength)
+            );
+        } else if indices.slicelength > 0 {
+            // the selected positions are the same as those of the forward slice ending at start,
+            // so walk that once and flip it, rather than selecting each position separately
+            let step = indices.step.unsigned_abs();
+            let first = indices.start as usize - (indices.slicelength - 1) * step;
+            sliced_values.extend(
+                splinter.iter()
+                    .skip(first)
+                    .step_by(step)
+                    .take(indices.slicelength)
+            );
+            sliced_values.reverse();
+        }
+
+        // let start = indices.start as usize;
+        // let stop = indices.stop as usize;
+        // let step = indices.step as usize;
+
+        // let sliced_values: Vec<u32> = splinter.iter().skip(start).step_by(step).take((stop - start) / step + 1).collect();
+
+        Ok(UintOrVec::Vec(sliced_values))
+    } else {
+        Err(PyTypeError::new_err("splinter indices must be integers or slices"))
+    }
+
+}
+
+pub(crate) fn contains(splinter: &impl PartitionRead<High>, value: &Bound<PyAny>) -> PyResult<BoolOrVec> {
+    if let Ok(single_val) = value.extract::<u32>() {
+        let result = splinter.contains(single_val);
+        Ok(BoolOrVec::Bool(result))
+    } else if let Ok(vals) = value.extract::<Vec<u32>>() {
+        let results: Vec<bool> = vals.iter().map(|val| {
+            splinter.contains(*val)
+        }).collect();
+
+        Ok(BoolOrVec::Vec(results))
+    } else { 
+        Err(PyTypeError::new_err(
+            format!(
+                "contains() argument must be an integer or a list of integers, but received an object of type {:#?}", 
+                value.get_type().name()?
+            )
+        ))
+    }
+}
+
+pub(crate) fn contains_many_parallel(splinter: &(impl PartitionRead<High> + Sync), values: Vec<u32>) -> Vec<bool> {
+    values
+        .par_iter()
+        .map(|&val| splinter.contains(val))
+        .collect()
+}
+
+pub(crate) fn select(splinter: &impl PartitionRead<High>, idx: &Bound<PyAny>) -> PyResult<Option<u32>> {
+    if let Ok(val) = idx.extract::<usize>() {
+        Ok(splinter.select(val))
+    } else if let Ok(val) = idx.extract::<i64>() {
+        Ok(select_index(splinter, splinter.cardinality(), val))
+    } else {
+        Err(PyTypeError::new_err(
+            format!(
+                "select() argument must be an integer, but received an object of type {:#?}",
+                idx.get_type().name()?
+            )
+        ))
+    }
+}
+
+/// Selects the element at an index, given the number of elements, with negative indices
+/// counting back from the end
+pub(crate) fn select_index(splinter: &impl PartitionRead<High>, len: usize, idx: i64) -> Option<u32> {
+    let idx = if idx < 0 { len.checked_sub(idx.unsigned_abs() as usize)? } else { idx as usize };
+    splinter.select(idx)
+}
+
+pub(crate) fn position(splinter: &impl PartitionRead<High>, value: u32) -> PyResult<usize> {
+    if let Some(pos) = splinter.position(value) {
+        Ok(pos)
+    } else {
+        Err(PyValueError::new_err(format!("element {value} does not exist in this Splinter")))
+    }
+
+}
+
+pub(crate) fn last(splinter: &impl PartitionRead<High>) -> Option<u32> {
+    splinter.cardinality().checked_sub(1).and_then(|idx| splinter.select(idx))
+}
+
+pub(crate) fn successor(splinter: &impl PartitionRead<High>, value: i64) -> Option<u32> {
+    match u32::try_from(value) {
+        // rank counts the elements <= value, so it is also the index of the first one above it
+        Ok(value) => splinter.select(splinter.rank(value)),
+        Err(_) if value < 0 => splinter.select(0),
+        Err(_) => None,
+    }
+}
+
+pub(crate) fn predecessor(splinter: &impl PartitionRead<High>, value: i64) -> Option<u32> {
+    match u32::try_from(value) {
+        Ok(0) => None,
+        Ok(value) => splinter.rank(value - 1).checked_sub(1).and_then(|idx| splinter.select(idx)),
+        Err(_) if value < 0 => None,
+        Err(_) => last(splinter),
+    }
+}
+
+pub(crate) fn next_absent(splinter: &impl PartitionRead<High>, value: i64) -> Option<u32> {
+    let value = u32::try_from(value.max(0)).ok()?;
+    let Some(pos) = splinter.position(value) else { return Some(value) };
+
+    // elements are sorted and distinct, so value + k is present exactly when it sits k places
+    // after value: binary search for the end of the run starting at value
+    let (mut lo, mut hi) = (0, (splinter.cardinality() - 1 - pos).min((u32::MAX - value) as usize));
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if splinter.select(pos + mid) == Some(value + mid as u32) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    // a run can reach u32::MAX, leaving nothing absent after it
+    value.checked_add(lo as u32)?.checked_add(1)
+}
+
+/// Maps a serialized splinter file into memory, keeping the mapping alive for as long as the
+/// returned `Bytes` are referenced
+pub(crate) fn mmap_bytes(path: &Path) -> PyResult<Bytes> {
+    let file = File::open(path)?;
+    // SAFETY: the mapping is read-only, and callers are warned not to modify the
+    // file while the Splinter is alive
+    let mmap = unsafe { Mmap::map(&file) }?;
+    Ok(Bytes::from_owner(mmap))
+}
+
+/// Keeps a Python buffer export alive for as long as any `Bytes` view into it exists
+struct PyBufferOwner(PyBuffer<u8>);
+
+impl AsRef<[u8]> for PyBufferOwner {
+    fn as_ref(&self) -> &[u8] {
+        if self.0.len_bytes() == 0 {
+            return &[];
+        }
+        // SAFETY: the buffer is checked to be C-contiguous before the owner is built, and the
+        // exporting object keeps its memory valid until the PyBuffer is released on drop
+        unsafe { std::slice::from_raw_parts(self.0.buf_ptr() as *const u8, self.0.len_bytes()) }
+    }
+}
+
+/// Wraps an object exporting the buffer protocol in `Bytes`, sharing its memory if it's
+/// read-only. A writable buffer is copied, since Python code could change it after its checksum
+/// has been checked
+fn bytes_from_buffer(data: &Bound<PyAny>) -> PyResult<Bytes> {
+    let buffer = PyBuffer::<u8>::get(data)?;
+    if !buffer.is_c_contiguous() {
+        return Err(PyValueError::new_err("Splinter can only be constructed from a contiguous buffer"));
+    }
+    let owner = PyBufferOwner(buffer);
+    Ok(match owner.0.readonly() {
+        true => Bytes::from_owner(owner),
+        false => Bytes::copy_from_slice(owner.as_ref()),
+    })
 }
 
 // as of new patch notes, it's just more straightforward to define an enum for varying outputs
@@ -576,9 +1518,618 @@ pub enum UintOrVec {
     Vec(Vec<u32>),
 }
 
+/// A Splinter or FrozenSplinter passed as an operand from Python
+#[derive(FromPyObject)]
+pub enum SplinterArg<'py> {
+    Splinter(PyRef<'py, SplinterWrapper>),
+    Frozen(PyRef<'py, FrozenSplinterWrapper>),
+}
+
+impl SplinterArg<'_> {
+    pub fn cardinality(&self) -> usize { with_splinter!(self, |r| r.cardinality()) }
+}
+
+/// An argument to a named set method, which as with Python's set can be any iterable of ints
+/// as well as a Splinter or FrozenSplinter
+pub enum SetArg<'py> {
+    Splinter(SplinterArg<'py>),
+    Values(Splinter),
+}
+
+impl<'py> FromPyObject<'py> for SetArg<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        match obj.extract::<SplinterArg>() {
+            Ok(arg) => Ok(Self::Splinter(arg)),
+            Err(_) => splinter_from_values(obj).map(Self::Values),
+        }
+    }
+}
+
+impl SetArg<'_> {
+    pub fn cardinality(&self) -> usize { with_set_arg!(self, |r| r.cardinality()) }
+
+    /// Returns the encoded form of the argument, which is shared if it's already serialized or
+    /// cached, and is built from scratch only for a modified Splinter or an iterable of values
+    pub(crate) fn encoded(&self) -> Bytes {
+        match self {
+            SetArg::Splinter(SplinterArg::Splinter(wrapper)) => wrapper.encoded(),
+            SetArg::Splinter(SplinterArg::Frozen(wrapper)) => wrapper.0.inner().clone(),
+            SetArg::Values(splinter) => codec::encode(splinter),
+        }
+    }
+
+    /// Converts the argument into an owned Splinter, copying it only if it's borrowed
+    pub fn into_splinter(self) -> Splinter {
+        match self {
+            SetArg::Splinter(SplinterArg::Splinter(wrapper)) => Splinter::from(wrapper.0.clone()),
+            SetArg::Splinter(SplinterArg::Frozen(wrapper)) => wrapper.0.decode_to_splinter(),
+            SetArg::Values(splinter) => splinter,
+        }
+    }
+}
+
+/// An argument to a named set method whose result never holds the values of the argument which
+/// are outside of it, such as intersection() or issubset(). As with Python's set, which accepts
+/// any ints there, the ints a Splinter can't hold are left out of the Splinter and counted
+pub struct QueryArg<'py>(SetArg<'py>, usize);
+
+impl<'py> FromPyObject<'py> for QueryArg<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        match obj.extract::<SplinterArg>() {
+            Ok(arg) => Ok(Self(SetArg::Splinter(arg), 0)),
+            Err(_) => splinter_from_values_within(obj).map(|(splinter, outside)| Self(SetArg::Values(splinter), outside)),
+        }
+    }
+}
+
+impl<'py> From<SetArg<'py>> for QueryArg<'py> {
+    fn from(arg: SetArg<'py>) -> Self { Self(arg, 0) }
+}
+
+impl QueryArg<'_> {
+    /// The number of distinct values in the argument, including those left out of it
+    pub fn cardinality(&self) -> usize { self.0.cardinality() + self.1 }
+}
+
+/// The right-hand side of an operator: a Splinter or FrozenSplinter, or a set, frozenset, range,
+/// list or NumPy array of ints. Any other type fails to extract, which makes pyo3 return
+/// NotImplemented.
+///
+/// As with a QueryArg, ints outside of 0 to 2**32 - 1 are left out, which is all &, - and the
+/// comparisons need, and the flag records that values were left out
+pub struct Operand<'py>(SetArg<'py>, bool);
+
+impl<'py> Operand<'py> {
+    /// Returns the operand of an operator whose result holds all of it, raising an OverflowError
+    /// if values were left out of it
+    fn whole(self, op: &str) -> PyResult<SetArg<'py>> {
+        if self.1 {
+            return Err(PyOverflowError::new_err(format!(
+                "Splinters can only hold values between 0 and 2**32 - 1, but the operand of {op} reaches outside of them"
+            )));
+        }
+        Ok(self.0)
+    }
+}
+
+impl<'py> FromPyObject<'py> for Operand<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(arg) = obj.extract::<SplinterArg>() {
+            return Ok(Self(SetArg::Splinter(arg), false));
+        }
+        let convertible = obj.is_instance_of::<PySet>()
+            || obj.is_instance_of::<PyFrozenSet>()
+            || obj.is_instance_of::<PyList>()
+            || arrays::is_array(obj)
+            || ranges::contiguous(obj)?.is_some();
+        if !convertible {
+            return Err(PyTypeError::new_err(format!("cannot use an object of type {} as a set operand", obj.get_type().name()?)));
+        }
+        obj.extract().map(|QueryArg(arg, outside)| Self(arg, outside > 0))
+    }
+}
+
+/// The right-hand side of == and !=: a Splinter or FrozenSplinter, or a set or frozenset of ints.
+/// Like Python's set, which never equals a list or a range, anything else fails to extract,
+/// which makes pyo3 return NotImplemented
+pub struct SetOperand<'py>(SetArg<'py>);
+
+impl<'py> FromPyObject<'py> for SetOperand<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(arg) = obj.extract::<SplinterArg>() {
+            return Ok(Self(SetArg::Splinter(arg)));
+        }
+        if !obj.is_instance_of::<PySet>() && !obj.is_instance_of::<PyFrozenSet>() {
+            return Err(PyTypeError::new_err(format!("cannot compare a Splinter with an object of type {}", obj.get_type().name()?)));
+        }
+        splinter_from_values(obj).map(|splinter| Self(SetArg::Values(splinter)))
+    }
+}
+
+/// Collects a range, NumPy array or any other iterable of ints into a Splinter
+pub(crate) fn splinter_from_values(values: &Bound<PyAny>) -> PyResult<Splinter> {
+    if let Some((start, stop)) = ranges::contiguous(values)? {
+        if start < stop && (start < 0 || stop > 1 << 32) {
+            return Err(PyOverflowError::new_err(format!(
+                "Splinters can only hold values between 0 and 2**32 - 1, but received the range [{start}, {stop})"
+            )));
+        }
+        return Ok(ranges::splinter_from_range(value_range(start, stop)));
+    }
+    if let Some(arr) = arrays::ValueArray::try_extract(values) {
+        return arr.to_splinter().map_err(|val| {
+            PyOverflowError::new_err(format!("Splinters can only hold values between 0 and 2**32 - 1, but received {val}"))
+        });
+    }
+    let mut vals = values.try_iter()?.map(|value| value?.extract()).collect::<PyResult<Vec<u32>>>()?;
+    vals.sort_unstable();
+    Ok(Splinter::from_iter(vals))
+}
+
+/// Like `splinter_from_values()`, but leaves out the ints a Splinter can't hold rather than
+/// raising, and returns how many distinct ints were left out
+pub(crate) fn splinter_from_values_within(values: &Bound<PyAny>) -> PyResult<(Splinter, usize)> {
+    if let Some((start, stop)) = ranges::contiguous_wide(values)? {
+        let outside = (stop.min(0) - start).max(0) + (stop - start.max(1 << 32)).max(0);
+        let (start, stop) = ranges::contiguous(values)?.expect("a contiguous range");
+        let splinter = ranges::splinter_from_range(value_range(start, stop));
+        return Ok((splinter, usize::try_from(outside).unwrap_or(usize::MAX)));
+    }
+    if let Some(arr) = arrays::ValueArray::try_extract(values) {
+        return Ok(arr.to_splinter_within());
+    }
+    let (mut vals, mut outside) = (Vec::new(), Vec::new());
+    for value in values.try_iter()? {
+        let value = value?;
+        match value.extract::<u32>() {
+            Ok(val) => vals.push(val),
+            Err(err) if err.is_instance_of::<PyOverflowError>(values.py()) => outside.push(value),
+            Err(err) => return Err(err),
+        }
+    }
+    vals.sort_unstable();
+    // the ints left out are counted once each, as a set would hold them
+    let outside = if outside.is_empty() { 0 } else { PySet::new(values.py(), outside)?.len() };
+    Ok((Splinter::from_iter(vals), outside))
+}
+
 #[pymodule]
 fn splynters(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<SplinterWrapper>()?;
+    m.add_class::<FrozenSplinterWrapper>()?;
     m.add_class::<SplinterIter>()?;
+    m.add_class::<SplinterBuilder>()?;
+    m.add_class::<Splinter64Wrapper>()?;
+    m.add_class::<FrozenSplinter64Wrapper>()?;
+    m.add_class::<Splinter64Iter>()?;
+    m.add_class::<SignedSplinterWrapper>()?;
+    m.add_class::<FrozenSignedSplinterWrapper>()?;
+    m.add_class::<SignedSplinterIter>()?;
+    m.add_class::<ArrowArrayWrapper>()?;
+    m.add_function(wrap_pyfunction!(multi::union_all, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::intersect_all, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::pairwise, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::top_k, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::at_least, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::majority, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::exactly_once, m)?)?;
+    m.add_function(wrap_pyfunction!(multi::count_occurrences, m)?)?;
+    m.add_function(wrap_pyfunction!(arrow::to_arrow_binary, m)?)?;
+
+    // let code checking isinstance(x, MutableSet) or isinstance(x, Set) accept splinters
+    let abc = m.py().import("collections.abc")?;
+    abc.getattr("MutableSet")?.call_method1("register", (m.getattr("Splinter")?,))?;
+    abc.getattr("MutableSet")?.call_method1("register", (m.getattr("Splinter64")?,))?;
+    abc.getattr("MutableSet")?.call_method1("register", (m.getattr("SignedSplinter")?,))?;
+    abc.getattr("Set")?.call_method1("register", (m.getattr("FrozenSplinter")?,))?;
+    abc.getattr("Set")?.call_method1("register", (m.getattr("FrozenSplinter64")?,))?;
+    abc.getattr("Set")?.call_method1("register", (m.getattr("FrozenSignedSplinter")?,))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use pyo3::{ffi::c_str, prelude::*, types::PyBytes};
+
+    use super::bytes_from_buffer;
+    use crate::testing::run_python;
+
+    #[test]
+    fn compares_equal_only_to_sets() {
+        run_python(c_str!(
+            r#"
+frozen = FrozenSplinter.from_list([1, 2, 3])
+for other in [{1, 2, 3}, frozenset({1, 2, 3}), Splinter.from_list([3, 2, 1]), FrozenSplinter.from_list([1, 2, 3])]:
+    assert frozen == other and other == frozen and not frozen != other, other
+    assert Splinter.from_list([1, 2, 3]) == other, other
+for other in [range(1, 4), [1, 2, 3], (1, 2, 3), {1, 2}, {-1}, {"a"}, None, 3]:
+    assert frozen != other and other != frozen and not frozen == other, other
+    assert Splinter.from_list([1, 2, 3]) != other, other
+
+# whatever compares equal hashes equal
+assert hash(frozen) == hash(frozenset({1, 2, 3})) == hash(FrozenSplinter.from_list([3, 1, 2]))
+assert len({frozen, frozenset({1, 2, 3}), FrozenSplinter.from_list([1, 2, 3])}) == 1
+assert len({frozen, (1, 2, 3)}) == 2
+"#
+        ));
+    }
+
+    #[test]
+    fn finds_frozensets_and_frozen_splinters_by_each_other_in_dicts() {
+        run_python(c_str!(
+            r#"
+for values in [[], [0], [1, 2, 3], [2**32 - 1], list(range(0, 100_000, 7)), list(range(5000)) + [2**31]]:
+    frozen = FrozenSplinter.from_list(values)
+    assert hash(frozen) == hash(frozenset(values))
+    by_set = {frozenset(values): "set"}
+    assert by_set[frozen] == "set" and frozen in by_set
+    by_splinter = {frozen: "splinter"}
+    assert by_splinter[frozenset(values)] == "splinter"
+    # the same key, so storing one replaces the value stored under the other
+    by_set[frozen] = "splinter"
+    assert len(by_set) == 1 and by_set[frozenset(values)] == "splinter"
+"#
+        ));
+    }
+
+    #[test]
+    fn treats_values_outside_of_u32_as_absent() {
+        run_python(c_str!(
+            r#"
+for cls in [Splinter, FrozenSplinter]:
+    s = cls.from_list([0, 1, 5, 2**32 - 1])
+    assert -1 not in s and 2**32 not in s and 2**40 not in s and 5 in s and True in s
+    try:
+        "5" in s
+        raise AssertionError("'in' accepted a str")
+    except TypeError:
+        pass
+
+    # &, - and the comparisons clamp a range to the values a Splinter can hold
+    assert (s & range(-5, 3)).to_list() == [0, 1] and (range(-5, 3) & s).to_list() == [0, 1]
+    assert (s & range(2**32 - 10, 2**40)).to_list() == [2**32 - 1] and len(s & range(-10, -1)) == 0
+    assert (s - range(-5, 3)).to_list() == [5, 2**32 - 1] and (s - range(2**32 - 10, 2**40)).to_list() == [0, 1, 5]
+    assert s <= range(-1, 2**40) and s < range(-1, 2**40) and cls.from_list(range(10)) < range(-5, 10)
+    assert not s <= range(-5, 3) and not s >= range(-1, 2) and not s > range(-1, 2)
+    assert s >= range(0, 2) and s > range(0, 2)
+    for bad in [range(-1, 2), range(2**32 - 1, 2**32 + 1)]:
+        for op in [lambda: s | bad, lambda: bad | s, lambda: s ^ bad, lambda: bad ^ s, lambda: bad - s]:
+            try:
+                op()
+                raise AssertionError("an operator accepted", bad)
+            except OverflowError:
+                pass
+    assert (s | range(2, 4)).to_list() == [0, 1, 2, 3, 5, 2**32 - 1] and (range(-3, -5) | s) == s
+
+s = Splinter.from_list([0, 1, 5])
+s &= range(-5, 3)
+s -= range(-5, 1)
+assert s.to_list() == [1]
+for bad in [range(-1, 2), range(2**32, 2**32 + 1)]:
+    for op in ["__ior__", "__ixor__"]:
+        try:
+            getattr(s, op)(bad)
+            raise AssertionError(op, "accepted", bad)
+        except OverflowError:
+            pass
+assert s.to_list() == [1]
+"#
+        ));
+    }
+
+    #[test]
+    fn answers_queries_about_values_outside_of_u32() {
+        run_python(c_str!(
+            r#"
+import itertools
+
+values = [0, 1, 5, 2**32 - 1]
+others = [[-1, 1, 2**40], {-3, -2, 5, 2**32 - 1, 2**32}, range(-5, 3), range(2**32 - 3, 2**32 + 4), [-1, -1, 2**70], [-1], []]
+for cls, other in itertools.product([Splinter, FrozenSplinter], others):
+    s, expected = cls.from_list(values), set(values)
+    assert s.isdisjoint(other) == expected.isdisjoint(other), other
+    assert s.issubset(other) == expected.issubset(other), other
+    assert s.issuperset(other) == expected.issuperset(other), other
+    assert s.intersection_len(other) == len(expected.intersection(other)), other
+    assert s.difference_len(other) == len(expected.difference(other)), other
+    assert s.union_len(other) == len(expected.union(other)), other
+    assert s.jaccard(other) == (len(expected & set(other)) / len(expected | set(other))), other
+    assert s.intersection(other).to_list() == sorted(expected.intersection(other)), other
+    assert s.difference(other, [1]).to_list() == sorted(expected.difference(other, [1])), other
+    assert (s & other).to_list() == sorted(expected & set(other)), other
+    assert (s - other).to_list() == sorted(expected - set(other)), other
+    assert (s <= other) == (expected <= set(other)) and (s >= other) == (expected >= set(other)), other
+    # whose results would hold the values outside
+    for method in [s.union, s.symmetric_difference]:
+        try:
+            assert method(other).to_list() == sorted(getattr(expected, method.__name__)(other)), other
+        except OverflowError:
+            assert not all(0 <= v < 2**32 for v in other), (method, other)
+
+s = Splinter.from_list(values)
+s.intersection_update([-1, 0, 5, 2**40], range(-2, 6))
+s.difference_update([2**32, 5])
+assert s.to_list() == [0]
+try:
+    s.update([1, -1])
+    raise AssertionError("update() accepted -1")
+except OverflowError:
+    assert s.to_list() == [0]
+for bad in [["1"], [1.5], [None]]:
+    try:
+        s.isdisjoint(bad)
+        raise AssertionError("isdisjoint() accepted", bad)
+    except TypeError:
+        pass
+"#
+        ));
+    }
+
+    #[test]
+    fn optimizes_after_range_operations() {
+        run_python(c_str!(
+            r#"
+def optimized(values):
+    s = Splinter.from_list(list(values))
+    s.optimize()
+    return s
+
+s = Splinter.from_list([1, 3, 10**9])
+s.add(range(1000, 1003))
+s.add_range(2000, 2002)
+assert s.to_list() == [1, 3, 1000, 1001, 1002, 2000, 2001, 10**9]
+assert s.__sizeof__() <= optimized(s.to_list()).__sizeof__()
+s.discard_range(0, 1001)
+s.discard(range(2000, 2001))
+assert s.to_list() == [1001, 1002, 2001, 10**9]
+assert s.__sizeof__() <= optimized(s.to_list()).__sizeof__()
+
+s = Splinter.from_list([])
+s.auto_optimize = False
+s.add_range(7, 9)
+assert s.to_list() == [7, 8]
+
+big = Splinter.from_list([5])
+big.add_range(0, 2**32 - 3)
+assert len(big) == 2**32 - 3
+"#
+        ));
+    }
+
+    #[test]
+    fn compares_again_after_each_modification() {
+        run_python(c_str!(
+            r#"
+s = Splinter.from_list([1, 2, 3])
+other = FrozenSplinter.from_list([1, 2, 3, 4])
+assert s <= other and s.issubset(other) and s.intersection_len(other) == 3
+assert s.to_bytes() == s.to_bytes()
+
+s.add(5)
+assert not s <= other and s.intersection_len(other) == 3 and s.jaccard(other) == 3 / 5
+s.discard(5)
+s |= {4}
+assert s.issuperset(other) and FrozenSplinter.from_bytes(s.to_bytes()) == other
+s -= [1]
+assert s.isdisjoint([1, 9]) and not s.issuperset(other)
+s.add_range(100, 200)
+assert s.intersection_len(range(150, 250)) == 50
+s.__setstate__(other.to_bytes())
+assert s == other and s.issuperset(other)
+s.clear()
+assert s.isdisjoint(other) and len(FrozenSplinter.from_bytes(s.to_bytes())) == 0
+"#
+        ));
+    }
+
+    #[test]
+    fn optimizes_after_in_place_operators() {
+        run_python(c_str!(
+            r#"
+def apply(s, op, rhs):
+    if op == "&": s &= rhs
+    elif op == "|": s |= rhs
+    elif op == "^": s ^= rhs
+    else: s -= rhs
+    return s
+
+starts = [list(range(0, 3000, 3)), list(range(1000)) + [10**6], list(range(0, 70000, 2))]
+operands = [range(0, 5000), list(range(1, 3000, 3)), Splinter.from_list(range(500, 2500)), FrozenSplinter.from_list([0, 3, 10**6])]
+for values in starts:
+    for op in "&|^-":
+        for rhs in operands:
+            s = apply(Splinter.from_list(values), op, rhs)
+            manual = Splinter.from_list(values)
+            manual.auto_optimize = False
+            manual = apply(manual, op, rhs)
+            assert s == manual
+            unoptimized = manual.__sizeof__()
+            manual.optimize()
+            assert s.__sizeof__() == manual.__sizeof__() <= unoptimized, (op, rhs)
+            assert len(s.to_bytes()) == len(manual.to_bytes())
+
+# turning a tree into a single run shrinks it
+s = Splinter.from_list(range(0, 5000, 2))
+before = s.__sizeof__()
+s |= range(0, 5000)
+assert s.__sizeof__() < before and len(s.to_bytes()) == s.__sizeof__()
+"#
+        ));
+    }
+
+    #[test]
+    fn optimizes_to_the_same_encoding_every_time() {
+        run_python(c_str!(
+            r#"
+big = Splinter.from_range(0, 10**8)
+big.add(10**9)
+big.discard(5)
+big.update([10**9 + 7])
+cases = [big, Splinter.from_list([3, 64, 65, 1000, 70_000, 2**24, 2**32 - 2]), Splinter.from_list(range(0, 5000, 3)), Splinter.from_list(list(range(300)) + [2**31])]
+for s in cases:
+    s.optimize()
+    size, data = s.__sizeof__(), s.to_bytes()
+    s.optimize()
+    assert s.__sizeof__() == size and s.to_bytes() == data, len(s)
+
+assert len(big) == 10**8 + 1 and 5 not in big and 10**9 + 7 in big
+built = SplinterBuilder()
+built.add(range(0, 10**8))
+built.add(10**9)
+built = built.build()
+size = built.__sizeof__()
+built.optimize()
+assert built.__sizeof__() == size and len(built) == 10**8 + 1 and 10**9 in built
+
+# and so is a hundred million values with one more added, however it's added
+for add in [lambda s: s.add(10**9), lambda s: s.update([10**9]), lambda s: s.difference_update([5])]:
+    s = Splinter.from_range(0, 10**8)
+    for _ in range(3):
+        add(s)
+        size = s.__sizeof__()
+        s.optimize()
+        assert s.__sizeof__() == size
+"#
+        ));
+    }
+
+    #[test]
+    fn keeps_auto_optimize_across_pickling() {
+        run_python(c_str!(
+            r#"
+import copy, pickle
+s = Splinter.from_list([1, 2, 3])
+s.auto_optimize = False
+for t in [pickle.loads(pickle.dumps(s)), copy.copy(s), copy.deepcopy(s)]:
+    assert t == s and t.auto_optimize is False
+s.auto_optimize = True
+assert pickle.loads(pickle.dumps(s)).auto_optimize is True
+# the bare bytes pickled before the state held the flag
+t = Splinter()
+t.auto_optimize = False
+t.__setstate__(s.to_bytes())
+assert t == s and t.auto_optimize is True
+"#
+        ));
+    }
+
+    #[test]
+    fn finds_absent_values_at_the_ends_of_runs() {
+        run_python(c_str!(
+            r#"
+full = Splinter.from_range(0, 2**32)
+assert full.next_absent(0) is None and full.next_absent(2**32 - 1) is None
+s = Splinter.from_list([0, 1, 2, 5, 2**32 - 2, 2**32 - 1])
+assert [s.next_absent(v) for v in [-1, 0, 3, 5, 2**32 - 3, 2**32 - 2]] == [3, 3, 3, 6, 2**32 - 3, None]
+assert s.next_absent(2**32) is None
+"#
+        ));
+    }
+
+    #[test]
+    fn reads_buffers_in_place() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let data = PyBytes::new(py, b"splinter");
+            let bytes = bytes_from_buffer(&data).unwrap();
+            assert_eq!(bytes.as_ptr(), data.as_bytes().as_ptr());
+            // the buffer export keeps the object alive once nothing else refers to it
+            drop(data);
+            assert_eq!(&bytes[..], b"splinter");
+            assert!(bytes_from_buffer(&PyBytes::new(py, b"")).unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn shares_only_read_only_buffers() {
+        run_python(c_str!(
+            r#"
+data = Splinter.from_list([1, 5, 10**6]).to_bytes()
+
+# a read-only buffer is held until the Splinter no longer reads from it
+view = memoryview(data)
+s = Splinter.from_bytes(view)
+try:
+    view.release()
+    raise AssertionError("released a buffer in use")
+except BufferError:
+    pass
+s.add(7)
+view.release()
+assert s.to_list() == [1, 5, 7, 10**6]
+
+# a writable one is copied, so changing it afterwards leaves every kind of splinter intact
+for cls, values in [(Splinter, [1, 5, 10**6]), (FrozenSplinter, [1, 5, 10**6]), (SignedSplinter, [-3, 0, 8]), (Splinter64, [1, 2**40])]:
+    buffer = bytearray(cls.from_list(values).to_bytes())
+    copy = cls.from_bytes(buffer)
+    buffer[:] = b"\xff" * len(buffer)
+    buffer.append(0)
+    assert list(copy) == values and len(copy) == len(values), cls
+
+assert Splinter.from_bytes(memoryview(b"xx" + data)[2:]).to_list() == [1, 5, 10**6]
+try:
+    Splinter.from_bytes(memoryview(data + data)[::2])
+    raise AssertionError("read a non-contiguous buffer")
+except ValueError:
+    pass
+"#
+        ));
+    }
+
+    #[test]
+    fn opens_files_written_by_to_bytes() {
+        run_python(c_str!(
+            r#"
+import os, tempfile
+
+values = list(range(0, 3000, 3)) + [10**6, 2**32 - 1]
+data = Splinter.from_list(values).to_bytes()
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "splinter.bin")
+    with open(path, "wb") as f:
+        f.write(data)
+    for cls in [Splinter, FrozenSplinter]:
+        opened = cls.open(path)
+        assert opened == Splinter.from_list(values) and opened.to_bytes() == data, cls
+    del opened
+
+    flipped = bytearray(data)
+    flipped[len(data) // 2] ^= 0xFF
+    for bad in [b"", data[:5], data[:-1], bytes(flipped)]:
+        bad_path = os.path.join(tmp, f"bad-{len(bad)}.bin")
+        with open(bad_path, "wb") as f:
+            f.write(bad)
+        for cls in [Splinter, FrozenSplinter]:
+            try:
+                cls.open(bad_path)
+                raise AssertionError(f"{cls.__name__} opened {bad!r}")
+            except ValueError:
+                pass
+
+    try:
+        Splinter.open(os.path.join(tmp, "missing.bin"))
+        raise AssertionError("opened a missing file")
+    except FileNotFoundError:
+        pass
+"#
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_and_corrupted_bytes() {
+        run_python(c_str!(
+            r#"
+data = Splinter.from_list(list(range(0, 3000, 3)) + [10**6]).to_bytes()
+flipped = bytearray(data)
+flipped[len(data) // 2] ^= 0xFF
+for bad in [b"", data[:5], data[:-1], data[1:], bytes(flipped), b"\0" * len(data)]:
+    for cls in [Splinter, FrozenSplinter]:
+        try:
+            cls.from_bytes(bad)
+            raise AssertionError(f"{cls.__name__} read {bad!r}")
+        except ValueError:
+            pass
+"#
+        ));
+    }
+}
@@ -0,0 +1,735 @@
+use bytes::Bytes;
+use numpy::{ndarray::Array2, IntoPyArray, PyArray1};
+use pyo3::{exceptions::{PyTypeError, PyValueError}, prelude::*};
+use rayon::prelude::*;
+use splinter_rs::{CowSplinter, PartitionRead, Splinter, SplinterRef};
+
+use crate::{arrays, codec::CachedSplinter, format::{Node, Parsed}, overlap, ranges, SetArg, SplinterArg, SplinterWrapper};
+
+// top_k() scores candidates in rounds of this many, and checks between rounds whether the rest
+// could still make it into the top k
+const ROUND: usize = 1024;
+
+// occurrences are counted one chunk of this many values at a time, with each value's count kept
+// in an array indexed by its low bits
+const CHUNK_BITS: u32 = 16;
+const CHUNK: usize = 1 << CHUNK_BITS;
+
+// module-level functions combining many splinters at once, which release the GIL and spread the
+// work across threads with rayon
+
+/// A borrowed Splinter or FrozenSplinter. Unlike a `SplinterArg`, it can be shared across
+/// threads while the GIL is released
+#[derive(Clone, Copy)]
+pub(crate) enum SplinterView<'a> {
+    Splinter(&'a CachedSplinter),
+    Frozen(&'a SplinterRef<Bytes>),
+}
+
+/// Evaluates an expression against the splinter-rs value behind a `SplinterView`
+macro_rules! with_view {
+    ($view:expr, |$rhs:ident| $body:expr) => {
+        match $view {
+            SplinterView::Splinter(cached) => { let $rhs: &CowSplinter<Bytes> = cached; $body }
+            SplinterView::Frozen(frozen) => { let $rhs: &SplinterRef<Bytes> = frozen; $body }
+        }
+    };
+}
+
+impl SplinterView<'_> {
+    pub(crate) fn cardinality(&self) -> usize { with_view!(self, |r| r.cardinality()) }
+
+    /// Returns the encoded form of the splinter, which is shared if it's already serialized, and
+    /// otherwise cached on the Splinter until it's next modified
+    pub(crate) fn encoded(&self) -> Bytes {
+        match self {
+            SplinterView::Splinter(splinter) => splinter.encoded(),
+            SplinterView::Frozen(splinter) => splinter.inner().clone(),
+        }
+    }
+
+    pub(crate) fn to_splinter(self) -> Splinter {
+        match self {
+            SplinterView::Splinter(splinter) => Splinter::from((*splinter).clone()),
+            SplinterView::Frozen(splinter) => splinter.decode_to_splinter(),
+        }
+    }
+}
+
+/// A measure of how much two splinters overlap
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Metric {
+    Jaccard,
+    Overlap,
+    Intersection,
+}
+
+impl Metric {
+    pub(crate) fn parse(metric: &str) -> PyResult<Self> {
+        match metric {
+            "jaccard" => Ok(Metric::Jaccard),
+            "overlap" => Ok(Metric::Overlap),
+            "intersection" => Ok(Metric::Intersection),
+            _ => Err(PyValueError::new_err(format!(
+                "metric must be 'jaccard', 'overlap' or 'intersection', but received {metric:?}"
+            ))),
+        }
+    }
+
+    /// Scores a pair of splinters, given their sizes and the size of their intersection
+    pub(crate) fn score(self, intersection: usize, a: usize, b: usize) -> f64 {
+        match self {
+            Metric::Jaccard => overlap::jaccard(intersection, a, b),
+            // the empty set is a subset of every set, which is what a score of 1 stands for
+            Metric::Overlap => match a.min(b) {
+                0 => 1.0,
+                smaller => intersection as f64 / smaller as f64,
+            },
+            Metric::Intersection => intersection as f64,
+        }
+    }
+
+    /// The highest score a pair of splinters of these sizes could get
+    fn bound(self, a: usize, b: usize) -> f64 {
+        match self {
+            Metric::Jaccard => self.score(a.min(b), a, b),
+            Metric::Overlap => 1.0,
+            Metric::Intersection => a.min(b) as f64,
+        }
+    }
+}
+
+/// Extracts every element of an iterable as a Splinter or FrozenSplinter, naming the calling
+/// function in the error raised for anything else
+pub(crate) fn extract_all<'py>(splinters: &Bound<'py, PyAny>, function: &str) -> PyResult<Vec<SplinterArg<'py>>> {
+    splinters
+        .try_iter()?
+        .map(|item| {
+            let item = item?;
+            item.extract().map_err(|_| match item.get_type().name() {
+                Ok(name) => PyTypeError::new_err(format!(
+                    "{function}() argument must be an iterable of Splinters, but it contained an object of type {name:#?}"
+                )),
+                Err(e) => e,
+            })
+        })
+        .collect()
+}
+
+/// Borrows the splinters behind a list of arguments, ready to be handed to other threads
+pub(crate) fn views<'a>(args: &'a [SplinterArg]) -> Vec<SplinterView<'a>> {
+    args.iter()
+        .map(|arg| match arg {
+            SplinterArg::Splinter(wrapper) => SplinterView::Splinter(&wrapper.0),
+            SplinterArg::Frozen(wrapper) => SplinterView::Frozen(&wrapper.0),
+        })
+        .collect()
+}
+
+#[pyfunction]
+/// Returns the union of any number of Splinters.
+///
+/// The Splinters are merged in parallel, as a tree of pairwise unions, with the GIL released,
+/// so other Python threads keep running in the meantime.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter]): The Splinters to combine
+///
+/// Returns:
+///     Splinter: A Splinter holding every element of every input, which is empty if there
+///     are no inputs
+pub fn union_all(py: Python, splinters: &Bound<PyAny>) -> PyResult<SplinterWrapper> {
+    let args = extract_all(splinters, "union_all")?;
+    let views = views(&args);
+    let union = py.allow_threads(|| {
+        let mut union = views
+            .par_iter()
+            .fold(|| Splinter::EMPTY, |mut acc, view| {
+                with_view!(view, |r| acc |= r);
+                acc
+            })
+            // merging by value folds the smaller splinter into the larger one
+            .reduce(|| Splinter::EMPTY, |mut a, b| {
+                a |= b;
+                a
+            });
+        ranges::optimize(&mut union);
+        union
+    });
+    Ok(SplinterWrapper::new(CowSplinter::from_owned(union)))
+}
+
+#[pyfunction]
+/// Returns the intersection of any number of Splinters.
+///
+/// The Splinters are intersected in parallel with the GIL released, each thread starting from a
+/// copy of the smallest, so that the intermediate results stay small. Work stops as soon as any
+/// intermediate result turns out empty.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter]): The Splinters to intersect
+///
+/// Returns:
+///     Splinter: A Splinter holding the elements common to every input, which is empty if
+///     there are no inputs
+pub fn intersect_all(py: Python, splinters: &Bound<PyAny>) -> PyResult<SplinterWrapper> {
+    let args = extract_all(splinters, "intersect_all")?;
+    let mut views = views(&args);
+    let intersection = py.allow_threads(|| {
+        views.sort_by_cached_key(|view| view.cardinality());
+        let Some((&smallest, rest)) = views.split_first() else {
+            return Splinter::EMPTY;
+        };
+        // only the smallest splinter is decoded, and every thread intersects its share of the
+        // others into a copy of it. An empty intersection returns None from try_fold and
+        // try_reduce to stop the others
+        let seed = smallest.to_splinter();
+        let intersection = rest
+            .par_iter()
+            .try_fold(|| seed.clone(), |mut acc, view| {
+                with_view!(view, |r| acc &= r);
+                (!acc.is_empty()).then_some(acc)
+            })
+            .try_reduce(|| seed.clone(), |a, b| {
+                let acc = if a.cardinality() <= b.cardinality() { a & &b } else { b & &a };
+                (!acc.is_empty()).then_some(acc)
+            });
+        let mut intersection = intersection.unwrap_or(Splinter::EMPTY);
+        ranges::optimize(&mut intersection);
+        intersection
+    });
+    Ok(SplinterWrapper::new(CowSplinter::from_owned(intersection)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (splinters, metric = "jaccard", condensed = false))]
+/// Scores the overlap between every pair of Splinters.
+///
+/// Each pair is counted without building its intersection, in parallel and with the GIL
+/// released. As with top_k(), a Splinter's encoding is reused until it's next modified.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter]): The Splinters to compare
+///     metric (str): How to score each pair:
+///         "jaccard": len(a & b) / len(a | b), or 1.0 if both are empty
+///         "overlap": len(a & b) / min(len(a), len(b)), or 1.0 if either is empty
+///         "intersection": len(a & b)
+///     condensed (bool): Return only the pairs above the diagonal, in the order of
+///     scipy.spatial.distance.pdist, instead of the full matrix
+///
+/// Returns:
+///     numpy.ndarray: An n x n symmetric matrix, or a vector of the n * (n - 1) / 2 pairs if
+///     condensed, of float64 scores or of uint64 counts for "intersection"
+///
+/// Raises:
+///     ImportError: If NumPy isn't installed
+pub fn pairwise(py: Python, splinters: &Bound<PyAny>, metric: &str, condensed: bool) -> PyResult<PyObject> {
+    let metric = Metric::parse(metric)?;
+    arrays::require_numpy(py, "pairwise")?;
+    let args = extract_all(splinters, "pairwise")?;
+    let views = views(&args);
+    let n = views.len();
+    let (lens, counts) = py.allow_threads(|| {
+        let encoded: Vec<Bytes> = views.par_iter().map(SplinterView::encoded).collect();
+        let lens: Vec<usize> = views.par_iter().map(SplinterView::cardinality).collect();
+        let counts: Vec<usize> = (0..n)
+            .into_par_iter()
+            .flat_map_iter(|i| {
+                let encoded = &encoded;
+                (i + 1..n).map(move |j| overlap::intersection_len(&encoded[i], &encoded[j]))
+            })
+            .collect();
+        (lens, counts)
+    });
+
+    if metric == Metric::Intersection {
+        let counts: Vec<u64> = counts.into_iter().map(|count| count as u64).collect();
+        return into_array(py, n, counts, |i| lens[i] as u64, condensed);
+    }
+    let pairs = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)));
+    let scores = pairs.zip(counts).map(|((i, j), count)| metric.score(count, lens[i], lens[j])).collect();
+    into_array(py, n, scores, |i| metric.score(lens[i], lens[i], lens[i]), condensed)
+}
+
+/// Returns the scores of the pairs above the diagonal as they are, or spreads them over a
+/// symmetric matrix with the given diagonal
+fn into_array<T: numpy::Element + Copy>(py: Python, n: usize, pairs: Vec<T>, diagonal: impl Fn(usize) -> T, condensed: bool) -> PyResult<PyObject> {
+    if condensed {
+        return Ok(pairs.into_pyarray(py).into_any().unbind());
+    }
+    // pair (i, j) of the condensed scores, for i < j, is preceded by n - 1 + n - 2 + ... + n - i
+    // pairs from earlier rows
+    let index = |i: usize, j: usize| i * n - i * (i + 1) / 2 + j - i - 1;
+    let matrix = Array2::from_shape_fn((n, n), |(i, j)| match i.cmp(&j) {
+        std::cmp::Ordering::Less => pairs[index(i, j)],
+        std::cmp::Ordering::Equal => diagonal(i),
+        std::cmp::Ordering::Greater => pairs[index(j, i)],
+    });
+    Ok(matrix.into_pyarray(py).into_any().unbind())
+}
+
+#[pyfunction]
+#[pyo3(signature = (query, candidates, k, metric = "jaccard"))]
+/// Finds the k Splinters most similar to a query.
+///
+/// Candidates are scored in parallel with the GIL released, in order of the best score their
+/// size allows, so that candidates too small or too large to make it into the top k are never
+/// compared against the query at all. Each Splinter is compared in its serialized form, which
+/// is encoded the first time it's needed after a modification and then reused, so repeated
+/// queries against the same candidates don't pay for encoding them again.
+///
+/// Args:
+///     query (Splinter | FrozenSplinter | Iterable[int]): The Splinter to compare against
+///     candidates (Iterable[Splinter | FrozenSplinter]): The Splinters to rank
+///     k (int): How many of the best candidates to return
+///     metric (str): "jaccard", "overlap" or "intersection", scored as in pairwise()
+///
+/// Returns:
+///     tuple[numpy.ndarray, numpy.ndarray]: The positions of the best candidates within
+///     candidates, and their scores, from the best down. Ties go to the earlier candidate. The
+///     scores are float64, or uint64 counts for "intersection"
+///
+/// Raises:
+///     ImportError: If NumPy isn't installed
+pub fn top_k<'py>(py: Python<'py>, query: SetArg, candidates: &Bound<PyAny>, k: usize, metric: &str) -> PyResult<(Bound<'py, PyArray1<isize>>, PyObject)> {
+    let metric = Metric::parse(metric)?;
+    arrays::require_numpy(py, "top_k")?;
+    let args = extract_all(candidates, "top_k")?;
+    let views = views(&args);
+    let (query_len, query) = (query.cardinality(), query.encoded());
+    let best = py.allow_threads(|| {
+        if k == 0 {
+            return Vec::new();
+        }
+        let lens: Vec<usize> = views.par_iter().map(SplinterView::cardinality).collect();
+        let bound = |i: usize| metric.bound(query_len, lens[i]);
+        let mut order: Vec<usize> = (0..views.len()).collect();
+        order.par_sort_by(|&a, &b| bound(b).total_cmp(&bound(a)).then(a.cmp(&b)));
+
+        let mut best: Vec<(f64, usize)> = Vec::new();
+        for round in order.chunks(ROUND) {
+            // the score to match, once there are k candidates to beat
+            let threshold = if best.len() == k { best[k - 1].0 } else { f64::NEG_INFINITY };
+            if bound(round[0]) < threshold {
+                break;
+            }
+            best.par_extend(round.par_iter().filter(|&&i| bound(i) >= threshold).map(|&i| {
+                let count = overlap::intersection_len(&query, &views[i].encoded());
+                (metric.score(count, query_len, lens[i]), i)
+            }));
+            best.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            best.truncate(k);
+        }
+        best
+    });
+
+    let indices: Vec<isize> = best.iter().map(|&(_, i)| i as isize).collect();
+    let scores = if metric == Metric::Intersection {
+        best.iter().map(|&(score, _)| score as u64).collect::<Vec<_>>().into_pyarray(py).into_any()
+    } else {
+        best.iter().map(|&(score, _)| score).collect::<Vec<_>>().into_pyarray(py).into_any()
+    };
+    Ok((indices.into_pyarray(py), scores.unbind()))
+}
+
+/// Yields the leading bits of every chunk of a splinter which holds any values
+fn chunk_keys<'a>(root: &'a Node<'a>) -> impl Iterator<Item = u32> + 'a {
+    let mut next = Some(0u32);
+    let mut first = Vec::with_capacity(1);
+    std::iter::from_fn(move || {
+        first.clear();
+        root.collect(4, 0, next?, u32::MAX, false, &mut first, 1)?;
+        let key = *first.first()? >> CHUNK_BITS;
+        next = (key + 1).checked_shl(CHUNK_BITS).filter(|&next| next != 0);
+        Some(key)
+    })
+}
+
+/// Counts how many of the splinters hold each of their values, chunk by chunk in parallel.
+///
+/// Chunks held by fewer than min splinters are skipped. For each of the others, emit receives
+/// the values of the chunk held by any splinter, in ascending order, with their counts.
+fn count_chunks<T, F>(splinters: &[Bytes], min: usize, emit: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&mut dyn Iterator<Item = (u32, u32)>) -> T + Sync,
+{
+    let parsed: Vec<Parsed> = splinters.par_iter().map(|bytes| Parsed::new(bytes)).collect();
+    let roots: Vec<Node> = parsed.iter().map(Parsed::root).collect();
+    let mut holders: Vec<(u32, usize)> = roots
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, root)| chunk_keys(root).map(move |key| (key, i)))
+        .collect();
+    holders.par_sort_unstable();
+    let chunks: Vec<&[(u32, usize)]> = holders.chunk_by(|a, b| a.0 == b.0).filter(|chunk| chunk.len() >= min).collect();
+
+    chunks
+        .into_par_iter()
+        // each thread keeps its own counts, and resets only the ones it touched
+        .map_init(
+            || (vec![0u32; CHUNK], Vec::new(), Vec::new()),
+            |(counts, touched, values), chunk| {
+                let lo = chunk[0].0 << CHUNK_BITS;
+                for &(_, i) in chunk {
+                    values.clear();
+                    // every partition of a Parsed splinter parses, so this reads the whole chunk
+                    let _ = roots[i].collect(4, 0, lo, lo | (CHUNK as u32 - 1), false, values, usize::MAX);
+                    for &value in values.iter() {
+                        let low = value as usize % CHUNK;
+                        if counts[low] == 0 {
+                            touched.push(low);
+                        }
+                        counts[low] += 1;
+                    }
+                }
+                touched.sort_unstable();
+                let out = emit(&mut touched.iter().map(|&low| (lo | low as u32, counts[low])));
+                touched.drain(..).for_each(|low| counts[low] = 0);
+                out
+            },
+        )
+        .collect()
+}
+
+/// Builds a Splinter from the values held by at least min and at most max of the splinters
+fn occurring(py: Python, splinters: &Bound<PyAny>, function: &str, min: impl FnOnce(usize) -> usize, max: usize) -> PyResult<SplinterWrapper> {
+    let args = extract_all(splinters, function)?;
+    let views = views(&args);
+    let min = min(views.len()).max(1);
+    let splinter = py.allow_threads(|| {
+        let encoded: Vec<Bytes> = views.par_iter().map(SplinterView::encoded).collect();
+        let chunks = count_chunks(&encoded, min, |values| {
+            values.filter(|&(_, count)| (min..=max).contains(&(count as usize))).map(|(value, _)| value).collect::<Vec<_>>()
+        });
+        let mut splinter = Splinter::from_iter(chunks.into_iter().flatten());
+        ranges::optimize(&mut splinter);
+        splinter
+    });
+    Ok(SplinterWrapper::new(CowSplinter::from_owned(splinter)))
+}
+
+#[pyfunction]
+/// Returns the values held by at least t of the Splinters.
+///
+/// Occurrences are counted chunk by chunk in parallel with the GIL released, skipping the parts
+/// of the value range which fewer than t Splinters reach into.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter]): The Splinters to count across
+///     t (int): The fewest Splinters a value must be in, which must be at least 1
+///
+/// Returns:
+///     Splinter: The values held by t or more of the Splinters
+pub fn at_least(py: Python, splinters: &Bound<PyAny>, t: usize) -> PyResult<SplinterWrapper> {
+    if t == 0 {
+        return Err(PyValueError::new_err("at_least() requires t to be at least 1, as every value is in at least 0 Splinters"));
+    }
+    occurring(py, splinters, "at_least", |_| t, usize::MAX)
+}
+
+#[pyfunction]
+/// Returns the values held by more than half of the Splinters.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter]): The Splinters to count across
+///
+/// Returns:
+///     Splinter: The values held by a strict majority of the Splinters
+pub fn majority(py: Python, splinters: &Bound<PyAny>) -> PyResult<SplinterWrapper> {
+    occurring(py, splinters, "majority", |n| n / 2 + 1, usize::MAX)
+}
+
+#[pyfunction]
+/// Returns the values held by exactly one of the Splinters.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter]): The Splinters to count across
+///
+/// Returns:
+///     Splinter: The values which appear in one Splinter and no other
+pub fn exactly_once(py: Python, splinters: &Bound<PyAny>) -> PyResult<SplinterWrapper> {
+    occurring(py, splinters, "exactly_once", |_| 1, 1)
+}
+
+/// The values held by any of the splinters, and how many of them hold each
+type Occurrences<'py> = (Bound<'py, PyArray1<u32>>, Bound<'py, PyArray1<u32>>);
+
+#[pyfunction]
+/// Counts how many of the Splinters hold each value.
+///
+/// Occurrences are counted chunk by chunk in parallel with the GIL released.
+///
+/// Args:
+///     splinters (Iterable[Splinter | FrozenSplinter]): The Splinters to count across
+///
+/// Returns:
+///     tuple[numpy.ndarray, numpy.ndarray]: Every value held by any of the Splinters, in
+///     ascending order, and the number of Splinters holding each, both as uint32
+///
+/// Raises:
+///     ImportError: If NumPy isn't installed
+pub fn count_occurrences<'py>(py: Python<'py>, splinters: &Bound<PyAny>) -> PyResult<Occurrences<'py>> {
+    let args = extract_all(splinters, "count_occurrences")?;
+    arrays::require_numpy(py, "count_occurrences")?;
+    let views = views(&args);
+    let (values, counts): (Vec<u32>, Vec<u32>) = py.allow_threads(|| {
+        let encoded: Vec<Bytes> = views.par_iter().map(SplinterView::encoded).collect();
+        let chunks = count_chunks(&encoded, 1, |values| values.collect::<Vec<_>>());
+        chunks.into_iter().flatten().unzip()
+    });
+    Ok((values.into_pyarray(py), counts.into_pyarray(py)))
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::ffi::c_str;
+    use splinter_rs::PartitionWrite;
+
+    use super::*;
+    use crate::{codec, testing::{optimized, run_python}};
+
+    #[test]
+    fn reuses_the_encoding_of_owned_splinters() {
+        let mut cached = CachedSplinter::from(CowSplinter::Owned(optimized(0..50)));
+        let query = codec::encode(&optimized(0..100));
+        let first = SplinterView::Splinter(&cached).encoded();
+        assert_eq!(SplinterView::Splinter(&cached).encoded().as_ptr(), first.as_ptr());
+        assert_eq!(overlap::intersection_len(&query, &first), 50);
+
+        cached.to_mut().insert(75);
+        let view = SplinterView::Splinter(&cached);
+        assert_eq!((view.cardinality(), overlap::intersection_len(&query, &view.encoded())), (51, 51));
+        assert_eq!(view.to_splinter(), optimized((0..50).chain([75])));
+    }
+
+    #[test]
+    fn counts_every_value_of_every_chunk() {
+        let edges = [0, u16::MAX as u32, 1 << 16, (1 << 16) + 1, u32::MAX - 1, u32::MAX];
+        let splinters = [
+            optimized(edges),
+            optimized((0..70_000).step_by(3).chain([u32::MAX])),
+            optimized((60_000..140_000).chain([1 << 16])),
+            optimized([]),
+            optimized((u32::MAX - 5000..=u32::MAX).step_by(7)),
+        ];
+        let mut expected = std::collections::BTreeMap::new();
+        for value in splinters.iter().flat_map(|splinter| splinter.iter()) {
+            *expected.entry(value).or_insert(0u32) += 1;
+        }
+        let keys: Vec<std::collections::BTreeSet<u32>> =
+            splinters.iter().map(|splinter| splinter.iter().map(|v| v >> CHUNK_BITS).collect()).collect();
+        let encoded: Vec<Bytes> = splinters.iter().map(codec::encode).collect();
+        for min in [1, 2, 3] {
+            let counts: Vec<(u32, u32)> =
+                count_chunks(&encoded, min, |values| values.collect::<Vec<_>>()).into_iter().flatten().collect();
+            // chunks reached by fewer than min splinters are skipped, along with every value in them
+            let kept: Vec<(u32, u32)> = expected
+                .iter()
+                .filter(|&(&value, _)| keys.iter().filter(|keys| keys.contains(&(value >> CHUNK_BITS))).count() >= min)
+                .map(|(&value, &count)| (value, count))
+                .collect();
+            assert_eq!(counts, kept, "min = {min}");
+        }
+        assert!(count_chunks(&[], 1, |values| values.count()).is_empty());
+    }
+
+    #[test]
+    fn matches_a_counter_of_the_values() {
+        run_python(c_str!(
+            r#"
+import collections, random, splynters
+
+rng = random.Random(18)
+edges = [0, 2**16 - 1, 2**16, 2**16 + 1, 2**32 - 1]
+sets = [set(rng.sample(range(200_000), rng.randrange(3000))) | set(rng.sample(edges, rng.randrange(len(edges) + 1))) for _ in range(8)]
+sets += [set(), set(edges), set(range(2**16 - 100, 2**16 + 100))]
+splinters = [Splinter.from_list(sorted(s)) if i % 2 else FrozenSplinter.from_list(sorted(s)) for i, s in enumerate(sets)]
+counter = collections.Counter(v for s in sets for v in s)
+n = len(sets)
+
+def held(lo, hi=n):
+    return sorted(v for v, c in counter.items() if lo <= c <= hi)
+
+for t in range(1, n + 2):
+    assert splynters.at_least(splinters, t).to_list() == held(t), t
+assert splynters.at_least(splinters, n + 100).to_list() == []
+assert splynters.majority(splinters).to_list() == held(n // 2 + 1)
+assert splynters.majority(splinters[:4]).to_list() == sorted(v for v, c in collections.Counter(v for s in sets[:4] for v in s).items() if c >= 3)
+assert splynters.exactly_once(splinters).to_list() == held(1, 1)
+try:
+    splynters.at_least(splinters, 0)
+    raise AssertionError("at_least() accepted t = 0")
+except ValueError:
+    pass
+for function in [splynters.majority, splynters.exactly_once, lambda s: splynters.at_least(s, 1)]:
+    assert function([]).to_list() == [] and function(iter([])).to_list() == []
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn counts_occurrences_like_a_counter() {
+        run_python(c_str!(
+            r#"
+import collections, random, splynters
+
+rng = random.Random(18)
+edges = [0, 2**16 - 1, 2**16, 2**16 + 1, 2**32 - 1]
+sets = [set(rng.sample(range(200_000), rng.randrange(3000))) | set(rng.sample(edges, rng.randrange(len(edges) + 1))) for _ in range(8)]
+sets += [set(), set(edges), set(range(2**16 - 100, 2**16 + 100))]
+splinters = [Splinter.from_list(sorted(s)) if i % 2 else FrozenSplinter.from_list(sorted(s)) for i, s in enumerate(sets)]
+counter = collections.Counter(v for s in sets for v in s)
+
+values, counts = splynters.count_occurrences(splinters)
+assert list(zip(values.tolist(), counts.tolist())) == sorted(counter.items())
+values, counts = splynters.count_occurrences([])
+assert values.tolist() == [] and counts.tolist() == []
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn scores_every_pair() {
+        run_python(c_str!(
+            r#"
+import itertools, random, splynters
+
+rng = random.Random(16)
+sets = [set(rng.sample(range(5000), rng.randrange(1, 300))) for _ in range(6)] + [set(), set(range(100))]
+splinters = [Splinter.from_list(sorted(s)) if i % 2 else FrozenSplinter.from_list(sorted(s)) for i, s in enumerate(sets)]
+scores = {
+    "jaccard": lambda a, b: len(a & b) / len(a | b) if a | b else 1.0,
+    "overlap": lambda a, b: len(a & b) / min(len(a), len(b)) if a and b else 1.0,
+    "intersection": lambda a, b: len(a & b),
+}
+for metric, score in scores.items():
+    matrix = splynters.pairwise(splinters, metric=metric).tolist()
+    assert len(matrix) == len(sets) and all(len(row) == len(sets) for row in matrix)
+    for (i, a), (j, b) in itertools.product(enumerate(sets), repeat=2):
+        assert abs(matrix[i][j] - score(a, b)) < 1e-12, (metric, i, j)
+    condensed = splynters.pairwise(splinters, metric=metric, condensed=True).tolist()
+    assert condensed == [matrix[i][j] for i, j in itertools.combinations(range(len(sets)), 2)], metric
+assert splynters.pairwise([]).shape == (0, 0) and splynters.pairwise(splinters[:1], condensed=True).tolist() == []
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn ranks_candidates_like_a_brute_force_search() {
+        run_python(c_str!(
+            r#"
+import random, splynters
+
+rng = random.Random(17)
+query = set(rng.sample(range(2000), 300))
+sets = [set(rng.sample(range(2000), rng.randrange(1000))) for _ in range(2000)]
+# ties: copies of earlier candidates, and empty candidates which every metric scores alike
+sets += [sets[i] for i in rng.sample(range(len(sets)), 200)] + [set()] * 50 + [set(query)] * 3
+rng.shuffle(sets)
+candidates = [Splinter.from_list(sorted(s)) if i % 2 else FrozenSplinter.from_list(sorted(s)) for i, s in enumerate(sets)]
+scores = {
+    "jaccard": lambda a, b: len(a & b) / len(a | b) if a | b else 1.0,
+    "overlap": lambda a, b: len(a & b) / min(len(a), len(b)) if a and b else 1.0,
+    "intersection": lambda a, b: len(a & b),
+}
+for metric, score in scores.items():
+    expected = sorted(((score(query, s), i) for i, s in enumerate(sets)), key=lambda pair: (-pair[0], pair[1]))
+    for k in [0, 1, 5, 1500, len(sets) + 10]:
+        indices, found = splynters.top_k(sorted(query), candidates, k, metric=metric)
+        assert indices.tolist() == [i for _, i in expected[:k]], (metric, k)
+        assert all(abs(a - b) < 1e-12 for a, b in zip(found.tolist(), (s for s, _ in expected[:k]))), (metric, k)
+indices, found = splynters.top_k(Splinter(), [], 3)
+assert indices.tolist() == [] and found.tolist() == []
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn returns_numpy_arrays() {
+        run_python(c_str!(
+            r#"
+import numpy as np
+import splynters
+
+splinters = [Splinter.from_list([1, 2, 3]), FrozenSplinter.from_list([2, 3, 4, 5]), Splinter()]
+matrix = splynters.pairwise(splinters)
+assert matrix.dtype == np.float64 and matrix.shape == (3, 3) and matrix[0, 1] == 2 / 5
+counts = splynters.pairwise(splinters, metric="intersection", condensed=True)
+assert counts.dtype == np.uint64 and counts.tolist() == [2, 0, 0]
+indices, scores = splynters.top_k([2, 3], splinters, 2)
+assert indices.dtype == np.intp and scores.dtype == np.float64 and indices.tolist() == [0, 1]
+assert splynters.top_k([2, 3], splinters, 1, metric="intersection")[1].dtype == np.uint64
+values, counts = splynters.count_occurrences(splinters)
+assert values.dtype == np.uint32 and counts.dtype == np.uint32
+assert values.tolist() == [1, 2, 3, 4, 5] and counts.tolist() == [1, 2, 2, 1, 1]
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(numpy, ignore = "NumPy is installed")]
+    fn raises_import_errors_without_numpy() {
+        run_python(c_str!(
+            r#"
+import splynters
+
+splinters = [Splinter.from_list([1, 2, 3]), FrozenSplinter.from_list([2, 3])]
+for name, call in [
+    ("pairwise", lambda: splynters.pairwise(splinters)),
+    ("pairwise", lambda: splynters.pairwise([], condensed=True)),
+    ("top_k", lambda: splynters.top_k([2], splinters, 1)),
+    ("count_occurrences", lambda: splynters.count_occurrences(splinters)),
+]:
+    try:
+        call()
+        raise AssertionError(f"{name}() returned without NumPy")
+    except ImportError as e:
+        assert f"{name}() requires NumPy" in str(e)
+"#
+        ));
+    }
+
+    #[test]
+    fn combines_splinters_again_after_each_modification() {
+        run_python(c_str!(
+            r#"
+import splynters
+
+a = Splinter.from_list(list(range(50)))
+b = Splinter.from_list(list(range(40, 100)))
+c = FrozenSplinter.from_list(list(range(0, 100, 2)))
+assert splynters.intersect_all([a, b, c]).to_list() == [40, 42, 44, 46, 48]
+
+a.discard_range(0, 45)
+b.add(200)
+assert splynters.intersect_all([a, b, c]).to_list() == [46, 48]
+assert splynters.union_all([a, b]).to_list() == list(range(40, 100)) + [200]
+"#
+        ));
+    }
+
+    #[test]
+    fn intersects_many_splinters_from_the_smallest() {
+        run_python(c_str!(
+            r#"
+import random, splynters
+
+rng = random.Random(14)
+sets = [set(range(0, 20_000, 3)) | set(rng.sample(range(20_000), 500)) for _ in range(63)]
+sets.append(set(range(0, 600, 3)) | {10_001})
+splinters = [Splinter.from_list(sorted(s)) if i % 2 else FrozenSplinter.from_list(sorted(s)) for i, s in enumerate(sets)]
+rng.shuffle(splinters)
+assert splynters.intersect_all(splinters).to_list() == sorted(set.intersection(*sets))
+assert splynters.intersect_all(splinters[:1]).to_list() == splinters[0].to_list()
+assert splynters.intersect_all([]).to_list() == []
+assert splynters.intersect_all(splinters + [Splinter.from_list([1])]).to_list() == []
+assert splynters.intersect_all(splinters + [Splinter()]).to_list() == []
+"#
+        ));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{format::{read_be, Node, Parsed}, iter::partition_point};
+
+// Counting the overlap between two encoded splinters without building it.
+//
+// The partition trees of both splinters are walked side by side, descending only into the
+// segments both of them hold. Blocks of 256 values are compared as four 64-bit words, while
+// lists and runs are checked against the other partition without decoding it. Counts take a
+// limit at which they may stop early, which lets isdisjoint() and issubset() return at the first
+// value that decides the answer.
+
+/// Counts the values two encoded splinters have in common
+pub(crate) fn intersection_len(a: &[u8], b: &[u8]) -> usize {
+    intersect(&Parsed::new(a).root(), &Parsed::new(b).root(), 4, usize::MAX)
+}
+
+/// Returns true if two encoded splinters have no values in common
+pub(crate) fn is_disjoint(a: &[u8], b: &[u8]) -> bool { intersect(&Parsed::new(a).root(), &Parsed::new(b).root(), 4, 1) == 0 }
+
+/// Returns true if every value of the encoded splinter a is also in b
+pub(crate) fn is_subset(a: &[u8], b: &[u8]) -> bool { difference(&Parsed::new(a).root(), &Parsed::new(b).root(), 4, 1) == 0 }
+
+/// The Jaccard similarity of two sets, given their sizes and the size of their intersection.
+/// Two empty sets are identical, so their similarity is 1
+pub(crate) fn jaccard(intersection: usize, a: usize, b: usize) -> f64 {
+    match a + b - intersection {
+        0 => 1.0,
+        union => intersection as f64 / union as f64,
+    }
+}
+
+/// Counts the values two partitions have in common, or stops at some count of at least limit
+fn intersect(a: &Node, b: &Node, width: usize, limit: usize) -> usize {
+    match (a, b) {
+        (Node::Empty, _) | (_, Node::Empty) => 0,
+        (Node::Full, other) | (other, Node::Full) => other.cardinality(width),
+        _ if width == 1 => {
+            let (a, b) = (a.block(), b.block());
+            a.iter().zip(b).map(|(a, b)| (a & b).count_ones() as usize).sum()
+        }
+        (Node::Tree { .. }, Node::Tree { .. }) => {
+            let mut count = 0;
+            for (segment, child) in a.children(width) {
+                if let Some(other) = b.child(width, segment) {
+                    count += intersect(&child, &other, width - 1, limit - count);
+                    if count >= limit { break }
+                }
+            }
+            count
+        }
+        // lists of similar lengths are merged, while a much shorter list is looked up in the
+        // longer one below
+        (Node::Vec(a), Node::Vec(b)) if a.len().max(b.len()) / a.len().min(b.len()) < 16 => {
+            let value = |values: &[u8], i: usize| read_be(&values[i * width..(i + 1) * width]);
+            let (len_a, len_b) = (a.len() / width, b.len() / width);
+            let (mut i, mut j, mut count) = (0, 0, 0);
+            // steps past the smaller value, or both when they match, without branching on which
+            while i < len_a && j < len_b && count < limit {
+                let (x, y) = (value(a, i), value(b, j));
+                i += (x <= y) as usize;
+                j += (y <= x) as usize;
+                count += (x == y) as usize;
+            }
+            count
+        }
+        (Node::Vec(values), other) | (other, Node::Vec(values)) => {
+            values.chunks(width).filter(|v| other.contains(width, read_be(v))).take(limit).count()
+        }
+        (Node::Run(runs), other) | (other, Node::Run(runs)) => {
+            let mut count = 0;
+            for run in runs.chunks(2 * width) {
+                count += other.count_range(width, read_be(&run[..width]), read_be(&run[width..]));
+                if count >= limit { break }
+            }
+            count
+        }
+        (Node::Bitmap(bitmap), other) | (other, Node::Bitmap(bitmap)) => {
+            bits(bitmap).filter(|&v| other.contains(width, v)).take(limit).count()
+        }
+    }
+}
+
+/// Counts the values of a which are missing from b, or stops at some count of at least limit
+fn difference(a: &Node, b: &Node, width: usize, limit: usize) -> usize {
+    match (a, b) {
+        (Node::Empty, _) | (_, Node::Full) => 0,
+        (_, Node::Empty) => a.cardinality(width),
+        (Node::Tree { .. }, Node::Tree { .. }) => {
+            let mut count = 0;
+            for (segment, child) in a.children(width) {
+                count += match b.child(width, segment) {
+                    Some(other) => difference(&child, &other, width - 1, limit - count),
+                    None => child.cardinality(width - 1),
+                };
+                if count >= limit { break }
+            }
+            count
+        }
+        (Node::Vec(values), _) if width > 1 => {
+            values.chunks(width).filter(|v| !b.contains(width, read_be(v))).take(limit).count()
+        }
+        _ => a.cardinality(width) - intersect(a, b, width, usize::MAX),
+    }
+}
+
+/// Appends a run to a list of ascending runs, merging it into the last one if they touch
+pub(crate) fn push_run(runs: &mut Vec<(u32, u32)>, lo: u32, hi: u32) {
+    match runs.last_mut() {
+        Some(last) if last.1 as u64 + 1 == lo as u64 => last.1 = hi,
+        _ => runs.push((lo, hi)),
+    }
+}
+
+/// Yields the positions of the set bits of a bitmap partition
+fn bits(bitmap: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    (0..bitmap.len() as u32 * 8).filter(|&i| bitmap[i as usize / 8] & (1 << (i % 8)) != 0)
+}
+
+impl<'a> Node<'a> {
+    /// Counts the values in this partition
+    pub(crate) fn cardinality(&self, width: usize) -> usize {
+        match self {
+            Node::Empty => 0,
+            Node::Full => 1 << (8 * width),
+            Node::Bitmap(bitmap) => bitmap.iter().map(|b| b.count_ones() as usize).sum(),
+            Node::Vec(values) => values.len() / width,
+            Node::Run(runs) => runs
+                .chunks(2 * width)
+                .map(|run| (read_be(&run[width..]) - read_be(&run[..width])) as usize + 1)
+                .sum(),
+            Node::Tree { .. } => self.children(width).map(|(_, child)| child.cardinality(width - 1)).sum(),
+        }
+    }
+
+    /// Lists the inclusive runs of values in this partition, relative to its start
+    pub(crate) fn runs(&self, width: usize) -> Vec<(u32, u32)> {
+        let mut runs = Vec::new();
+        match self {
+            Node::Empty => {}
+            Node::Full => runs.push((0, (1u64 << (8 * width)).wrapping_sub(1) as u32)),
+            Node::Bitmap(bitmap) => bits(bitmap).for_each(|value| push_run(&mut runs, value, value)),
+            Node::Vec(values) => values.chunks(width).map(read_be).for_each(|value| push_run(&mut runs, value, value)),
+            Node::Run(bounds) => {
+                runs.extend(bounds.chunks(2 * width).map(|run| (read_be(&run[..width]), read_be(&run[width..]))))
+            }
+            Node::Tree { .. } => {
+                let shift = 8 * (width - 1) as u32;
+                for (segment, child) in self.children(width) {
+                    let base = (segment as u32) << shift;
+                    child.runs(width - 1).into_iter().for_each(|(lo, hi)| push_run(&mut runs, base | lo, base | hi));
+                }
+            }
+        }
+        runs
+    }
+
+    /// Returns true if the partition holds value, which is relative to its start
+    fn contains(&self, width: usize, value: u32) -> bool {
+        match self {
+            Node::Empty => false,
+            Node::Full => true,
+            Node::Bitmap(bitmap) => bitmap[value as usize / 8] & (1 << (value % 8)) != 0,
+            Node::Vec(values) => {
+                let len = values.len() / width;
+                let i = partition_point(len, |i| read_be(&values[i * width..(i + 1) * width]) < value);
+                i < len && read_be(&values[i * width..(i + 1) * width]) == value
+            }
+            Node::Run(runs) => {
+                let bound = |i: usize, j: usize| read_be(&runs[(2 * i + j) * width..(2 * i + j + 1) * width]);
+                let len = runs.len() / (2 * width);
+                let i = partition_point(len, |i| bound(i, 1) < value);
+                i < len && bound(i, 0) <= value
+            }
+            Node::Tree { .. } => {
+                let shift = 8 * (width - 1) as u32;
+                self.child(width, (value >> shift) as u8)
+                    .is_some_and(|child| child.contains(width - 1, value & ((1 << shift) - 1)))
+            }
+        }
+    }
+
+    /// Counts the values of this partition within [lo, hi], which are relative to its start
+    fn count_range(&self, width: usize, lo: u32, hi: u32) -> usize {
+        match self {
+            Node::Empty => 0,
+            Node::Full => (hi - lo) as usize + 1,
+            Node::Bitmap(bitmap) => {
+                let (first, last) = (lo as usize / 8, hi as usize / 8);
+                (first..=last)
+                    .map(|i| {
+                        let mut bits = bitmap[i];
+                        if i == first { bits &= 0xFF << (lo % 8) }
+                        if i == last { bits &= 0xFF >> (7 - hi % 8) }
+                        bits.count_ones() as usize
+                    })
+                    .sum()
+            }
+            Node::Vec(values) => {
+                let value = |i: usize| read_be(&values[i * width..(i + 1) * width]);
+                let len = values.len() / width;
+                partition_point(len, |i| value(i) <= hi) - partition_point(len, |i| value(i) < lo)
+            }
+            Node::Run(runs) => {
+                let bound = |i: usize, j: usize| read_be(&runs[(2 * i + j) * width..(2 * i + j + 1) * width]);
+                let len = runs.len() / (2 * width);
+                (partition_point(len, |i| bound(i, 1) < lo)..partition_point(len, |i| bound(i, 0) <= hi))
+                    .map(|i| (bound(i, 1).min(hi) - bound(i, 0).max(lo)) as usize + 1)
+                    .sum()
+            }
+            Node::Tree { .. } => {
+                let shift = 8 * (width - 1) as u32;
+                let child_max = (1u32 << shift) - 1;
+                self.children(width)
+                    .map(|(segment, child)| ((segment as u32) << shift, child))
+                    .skip_while(|&(seg_lo, _)| seg_lo | child_max < lo)
+                    .take_while(|&(seg_lo, _)| seg_lo <= hi)
+                    .map(|(seg_lo, child)| {
+                        child.count_range(width - 1, lo.max(seg_lo) - seg_lo, hi.min(seg_lo | child_max) - seg_lo)
+                    })
+                    .sum()
+            }
+        }
+    }
+
+    /// Expands a block, the 256-value partitions at the bottom of the tree, into a bitmap
+    fn block(&self) -> [u64; 4] {
+        let mut block = [0u64; 4];
+        let mut set = |v: u8| block[v as usize / 64] |= 1 << (v % 64);
+        match self {
+            Node::Empty => {}
+            Node::Full => return [u64::MAX; 4],
+            Node::Bitmap(bitmap) => {
+                for (word, bytes) in block.iter_mut().zip(bitmap.chunks(8)) {
+                    *word = u64::from_le_bytes(bytes.try_into().expect("block bitmaps are 32 bytes"));
+                }
+            }
+            Node::Vec(values) => values.iter().for_each(|&v| set(v)),
+            Node::Run(runs) => runs.chunks(2).for_each(|run| (run[0]..=run[1]).for_each(&mut set)),
+            // trees never parse at the bottom of the partition tree
+            Node::Tree { .. } => {}
+        }
+        block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use splinter_rs::PartitionRead;
+
+    use super::*;
+    use crate::{codec, testing::cases};
+
+    #[test]
+    fn compares_every_pair_of_partition_kinds() {
+        let cases = cases();
+        let sets: Vec<BTreeSet<u32>> = cases.iter().map(|s| s.iter().collect()).collect();
+        let encoded: Vec<_> = cases.iter().map(codec::encode).collect();
+        for (a, (set_a, bytes_a)) in cases.iter().zip(sets.iter().zip(&encoded)) {
+            assert_eq!(Parsed::new(bytes_a).root().cardinality(4), a.cardinality());
+            for (set_b, bytes_b) in sets.iter().zip(&encoded) {
+                let common = set_a.intersection(set_b).count();
+                assert_eq!(intersection_len(bytes_a, bytes_b), common);
+                assert_eq!(is_disjoint(bytes_a, bytes_b), common == 0);
+                assert_eq!(is_subset(bytes_a, bytes_b), set_a.is_subset(set_b));
+            }
+        }
+    }
+
+    #[test]
+    fn lists_runs() {
+        for splinter in cases() {
+            let runs = Parsed::new(&codec::encode(&splinter)).root().runs(4);
+            let mut expected = vec![];
+            for value in splinter.iter() {
+                push_run(&mut expected, value, value);
+            }
+            assert_eq!(runs, expected);
+        }
+    }
+
+    #[test]
+    fn measures_jaccard_similarity() {
+        assert_eq!(jaccard(0, 0, 0), 1.0);
+        assert_eq!(jaccard(0, 3, 0), 0.0);
+        assert_eq!(jaccard(2, 4, 2), 0.5);
+    }
+}
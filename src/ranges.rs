@@ -0,0 +1,597 @@
+use std::ops::{BitAndAssign, RangeInclusive};
+
+use bytes::Bytes;
+use pyo3::{exceptions::PyOverflowError, prelude::*};
+use splinter_rs::{level::High, CowSplinter, PartitionRead, PartitionWrite, Splinter, SplinterRef};
+
+use crate::{codec::{self, CachedSplinter}, format::{self, read_be, smallest_form, Form, Node, Parsed}, iter::{count_within, value_range}, overlap};
+
+// Contiguous ranges of values, handled a whole partition at a time.
+//
+// splinter-rs can only insert one value at a time, which is far too slow for ranges spanning
+// millions of values. Instead, ranges are written directly in the encoding of format, where every
+// partition they cover completely is a single Full node, and then merged in with set operations,
+// which splinter-rs carries out partition by partition between two trees.
+//
+// Between partitions of different kinds splinter-rs walks every value instead, and it stores a
+// large run partition as a tree, value by value, after any operation on it. Splinters whose root
+// holds runs are therefore rebuilt from their runs rather than operated on.
+
+// slices of up to this many values are collected directly, which beats walking the partitions
+pub(crate) const MAX_WALK: usize = 4096;
+
+/// Stores every partition of a splinter in whichever form is smallest, as optimize() does.
+///
+/// splinter-rs walks the values of every tree one by one to count its runs, and turns any large
+/// run into a tree value by value, which takes seconds for a splinter of a hundred million
+/// values, and optimizing what it optimized can give a different encoding again. Here it's done
+/// in time proportional to the size of the encoding, and optimizing twice changes nothing.
+///
+/// Returns the encoding of the optimized splinter, which comes for free.
+pub(crate) fn optimize(splinter: &mut Splinter) -> Bytes {
+    let bytes = codec::encode(splinter);
+    let mut buf = Vec::with_capacity(bytes.len());
+    if put_optimized(&mut buf, &Parsed::new(&bytes).root(), 4).is_empty() {
+        *splinter = Splinter::EMPTY;
+        return codec::encode(splinter);
+    }
+    let optimized = format::decode(buf);
+    *splinter = optimized.decode_to_splinter();
+    optimized.into_inner()
+}
+
+/// Appends a partition with values `width` bytes wide in its smallest form, with each of its
+/// children in theirs, and returns the runs of values it holds. Nothing is appended for an empty
+/// partition.
+fn put_optimized(buf: &mut Vec<u8>, node: &Node, width: usize) -> Vec<(u32, u32)> {
+    let start = buf.len();
+    let (runs, tree_size) = match node {
+        Node::Tree { .. } => {
+            let shift = 8 * (width as u32 - 1);
+            let (mut runs, mut children): (Vec<(u32, u32)>, _) = (Vec::new(), Vec::new());
+            for (segment, child) in node.children(width) {
+                let child_runs = put_optimized(buf, &child, width - 1);
+                if child_runs.is_empty() {
+                    continue;
+                }
+                children.push((segment, buf.len()));
+                // a run reaching the end of one child carries on into the next if it starts with one
+                let base = (segment as u32) << shift;
+                child_runs.into_iter().for_each(|(lo, hi)| overlap::push_run(&mut runs, base | lo, base | hi));
+            }
+            if children.is_empty() {
+                return runs;
+            }
+            format::put_tree(buf, width, &children);
+            (runs, buf.len() - start)
+        }
+        _ => (node.runs(width), usize::MAX),
+    };
+    if runs.is_empty() {
+        return runs;
+    }
+
+    let count = runs.iter().map(|&(lo, hi)| (hi - lo) as u64 + 1).sum();
+    let form = smallest_form(width, count, runs.len(), tree_size);
+    if form == Form::Tree {
+        return runs;
+    }
+    buf.truncate(start);
+    format::put_form(buf, width, form, &runs);
+    runs
+}
+
+/// Builds a Splinter holding the given runs, which must be sorted and non-overlapping
+pub(crate) fn splinter_from_runs(runs: &[RangeInclusive<u32>]) -> Splinter {
+    if runs.is_empty() {
+        return Splinter::EMPTY;
+    }
+    let runs: Vec<_> = runs.iter().map(|run| (*run.start(), *run.end())).collect();
+    let mut buf = Vec::new();
+    format::put_runs(&mut buf, 4, &runs);
+    format::decode(buf).decode_to_splinter()
+}
+
+/// Builds a Splinter holding every value in range
+pub(crate) fn splinter_from_range(range: RangeInclusive<u32>) -> Splinter {
+    if range.is_empty() {
+        return Splinter::EMPTY;
+    }
+    splinter_from_runs(&[range])
+}
+
+/// Inserts every value in range into the splinter
+pub(crate) fn insert_range(splinter: &mut Splinter, range: RangeInclusive<u32>) {
+    if range.is_empty() {
+        return;
+    }
+    match splinter.root() {
+        Root::Runs(mut runs) => {
+            let idx = runs.partition_point(|run| run.start() < range.start());
+            runs.insert(idx, range);
+            *splinter = splinter_from_runs(&coalesce(runs));
+        }
+        // splinter-rs merges into whichever side is larger, so this never walks the values of
+        // the range unless the splinter already holds more values than it
+        _ => *splinter |= splinter_from_range(range),
+    }
+}
+
+/// Removes every value in range from the splinter
+pub(crate) fn remove_range(splinter: &mut Splinter, range: RangeInclusive<u32>) {
+    // splinter-rs has its own remove_range, but in 0.7 it drops every child of a tree other than
+    // the ones holding either end of the range, and turns a large run into a tree value by value,
+    // so only lists and bitmaps of values are left to it
+    match splinter.root() {
+        Root::Tree => {
+            if let Some(range) = within_span(splinter, range) {
+                *splinter -= splinter_from_range(range);
+            }
+        }
+        Root::Runs(runs) => {
+            let (lo, hi) = (*range.start(), *range.end());
+            let kept: Vec<_> = runs
+                .into_iter()
+                .flat_map(|run| {
+                    let (start, end) = run.into_inner();
+                    let below = (start < lo).then(|| start..=end.min(lo - 1));
+                    let above = (end > hi).then(|| start.max(hi + 1)..=end);
+                    below.into_iter().chain(above)
+                })
+                .collect();
+            *splinter = splinter_from_runs(&kept);
+        }
+        Root::Values => splinter.remove_range(range),
+    }
+}
+
+/// Inserts values into the splinter one at a time, unless its root is known to hold runs, which
+/// splinter-rs would turn into a tree value by value on the first insertion. The values are then
+/// merged into the runs instead.
+pub(crate) fn insert_values(splinter: &mut CachedSplinter, values: impl IntoIterator<Item = u32>) {
+    match splinter.encoded_if_cached().map(|bytes| bytes.root()) {
+        Some(Root::Runs(runs)) => {
+            let mut runs: Vec<_> = runs.into_iter().chain(values.into_iter().map(|value| value..=value)).collect();
+            runs.sort_unstable_by_key(|run| *run.start());
+            *splinter.to_mut() = splinter_from_runs(&coalesce(runs));
+        }
+        _ => {
+            let splinter = splinter.to_mut();
+            values.into_iter().for_each(|value| { splinter.insert(value); });
+        }
+    }
+}
+
+/// Removes values from the splinter one at a time, or from its runs, as with insert_values().
+///
+/// splinter-rs also lists every value of a full partition to remove one from it, so from a
+/// large splinter the values are subtracted instead, partition by partition.
+pub(crate) fn remove_values(splinter: &mut CachedSplinter, values: impl IntoIterator<Item = u32>) {
+    match splinter.encoded_if_cached().map(|bytes| bytes.root()) {
+        Some(Root::Runs(runs)) => {
+            let mut values: Vec<u32> = values.into_iter().collect();
+            values.sort_unstable();
+            let mut values = values.into_iter().peekable();
+            let mut kept = Vec::with_capacity(runs.len());
+            for run in runs {
+                let (mut lo, hi) = run.into_inner();
+                let mut rest = true;
+                while values.next_if(|&value| value < lo).is_some() {}
+                // split the run around each value within it
+                while let Some(value) = values.next_if(|&value| value <= hi) {
+                    if value > lo {
+                        kept.push(lo..=value - 1);
+                    }
+                    if value == hi {
+                        rest = false;
+                        break;
+                    }
+                    lo = value + 1;
+                }
+                if rest {
+                    kept.push(lo..=hi);
+                }
+            }
+            *splinter.to_mut() = splinter_from_runs(&kept);
+        }
+        _ if splinter.cardinality() > MAX_WALK => {
+            let mut values: Vec<_> = values.into_iter().map(|value| value..=value).collect();
+            values.sort_unstable_by_key(|run| *run.start());
+            *splinter.to_mut() -= splinter_from_runs(&coalesce(values));
+        }
+        _ => {
+            let splinter = splinter.to_mut();
+            values.into_iter().for_each(|value| { splinter.remove(value); });
+        }
+    }
+}
+
+/// Returns the values of the splinter within range as a new Splinter.
+///
+/// Partitions of the splinter which lie entirely inside or outside of the range are kept or
+/// skipped whole, rather than value by value.
+pub(crate) fn slice<S>(splinter: &S, range: RangeInclusive<u32>) -> Splinter
+where
+    S: PartitionRead<High> + RootKind,
+    Splinter: for<'a> BitAndAssign<&'a S>,
+{
+    let count = count_within(splinter, &range);
+    if count == 0 {
+        return Splinter::EMPTY;
+    }
+    if count as u64 == *range.end() as u64 - *range.start() as u64 + 1 {
+        return splinter_from_range(range);
+    }
+    if count <= MAX_WALK {
+        return splinter.range(range).collect();
+    }
+
+    match splinter.root() {
+        Root::Tree => {
+            let mut sliced = splinter_from_range(within_span(splinter, range).expect("the splinter holds values in range"));
+            sliced &= splinter;
+            sliced
+        }
+        Root::Runs(runs) => {
+            let (lo, hi) = (*range.start(), *range.end());
+            let within: Vec<_> = runs
+                .into_iter()
+                .filter(|run| *run.end() >= lo && *run.start() <= hi)
+                .map(|run| (*run.start()).max(lo)..=(*run.end()).min(hi))
+                .collect();
+            splinter_from_runs(&within)
+        }
+        Root::Values => splinter.range(range).collect(),
+    }
+}
+
+/// Narrows range down to the values between the first and last values of the splinter, since
+/// the partial partitions at either end of a range can still be walked value by value
+fn within_span(splinter: &impl PartitionRead<High>, range: RangeInclusive<u32>) -> Option<RangeInclusive<u32>> {
+    let (first, last) = (splinter.select(0)?, splinter.last()?);
+    let range = (*range.start()).max(first)..=(*range.end()).min(last);
+    (!range.is_empty()).then_some(range)
+}
+
+/// Merges overlapping and adjacent runs, which must be sorted by their start
+pub(crate) fn coalesce(runs: Vec<RangeInclusive<u32>>) -> Vec<RangeInclusive<u32>> {
+    let mut merged: Vec<RangeInclusive<u32>> = Vec::with_capacity(runs.len());
+    for run in runs {
+        match merged.last_mut() {
+            Some(last) if *run.start() as u64 <= *last.end() as u64 + 1 => {
+                *last = *last.start()..=(*last.end()).max(*run.end());
+            }
+            _ => merged.push(run),
+        }
+    }
+    merged
+}
+
+/// How the root partition of a splinter stores its values
+#[derive(Debug, PartialEq)]
+pub(crate) enum Root {
+    Tree,
+    /// A run partition, or a full one, with its runs
+    Runs(Vec<RangeInclusive<u32>>),
+    /// A list or bitmap of values, which is small enough to walk
+    Values,
+}
+
+/// Reads the kind of partition at the root of a splinter, which splinter-rs doesn't expose
+pub(crate) trait RootKind {
+    fn root(&self) -> Root;
+}
+
+impl RootKind for [u8] {
+    fn root(&self) -> Root {
+        // a root which doesn't parse is read by splinter-rs, value by value
+        match Node::root(self) {
+            Some(Node::Tree { .. }) => Root::Tree,
+            Some(Node::Full) => Root::Runs(vec![0..=u32::MAX]),
+            Some(Node::Run(runs)) => Root::Runs(
+                runs.chunks_exact(8).map(|run| read_be(&run[..4])..=read_be(&run[4..])).collect(),
+            ),
+            _ => Root::Values,
+        }
+    }
+}
+
+// an owned splinter has to be encoded to find out, in O(len) of its encoding, which is no more
+// than any of the operations which ask for its root go on to spend
+impl RootKind for Splinter {
+    fn root(&self) -> Root { codec::encode(self).root() }
+}
+
+impl RootKind for CachedSplinter {
+    fn root(&self) -> Root { self.encoded().root() }
+}
+
+impl RootKind for SplinterRef<Bytes> {
+    fn root(&self) -> Root { self.inner().root() }
+}
+
+impl RootKind for CowSplinter<Bytes> {
+    fn root(&self) -> Root {
+        match self {
+            CowSplinter::Ref(splinter_ref) => splinter_ref.root(),
+            CowSplinter::Owned(splinter) => splinter.root(),
+        }
+    }
+}
+
+/// Encodes a range as a single run, which is as compact as a range gets
+pub(crate) fn encode_range(range: RangeInclusive<u32>) -> SplinterRef<Bytes> {
+    if range.is_empty() {
+        return codec::encode_to_ref(&Splinter::EMPTY);
+    }
+    let (lo, hi) = range.into_inner();
+    let mut buf = Vec::with_capacity(13);
+    if lo == 0 && hi == u32::MAX {
+        format::put_full(&mut buf);
+    } else {
+        format::put_run(&mut buf, 4, [(lo, hi)]);
+    }
+    format::decode(buf)
+}
+
+/// Reads a Python range with a step of 1 or -1 as [start, stop) bounds, so that it can be handled
+/// as one contiguous run of values. Returns None for anything else.
+pub(crate) fn contiguous(values: &Bound<PyAny>) -> PyResult<Option<(i64, i64)>> {
+    // bounds beyond an i64 reach past any u32 anyway, so they are clamped rather than rejected
+    let clamp = |bound: i128| bound.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    Ok(contiguous_wide(values)?.map(|(start, stop)| (clamp(start), clamp(stop))))
+}
+
+/// Like `contiguous()`, but with bounds wide enough for ranges of 64-bit values
+pub(crate) fn contiguous_wide(values: &Bound<PyAny>) -> PyResult<Option<(i128, i128)>> {
+    // range can't be subclassed, so an exact type check is enough
+    let range = values.py().import("builtins")?.getattr("range")?;
+    if !values.get_type().is(&range) {
+        return Ok(None);
+    }
+    let (start, stop) = (values.getattr("start")?.extract::<i128>()?, values.getattr("stop")?.extract::<i128>()?);
+    Ok(match values.getattr("step")?.extract::<i128>()? {
+        1 => Some((start, stop)),
+        -1 => Some((stop.saturating_add(1), start.saturating_add(1))),
+        _ => None,
+    })
+}
+
+/// Converts [start, stop) bounds into the values to insert, raising an OverflowError if the bounds
+/// reach outside of the values a Splinter can hold
+pub(crate) fn insertable(start: i64, stop: i64, method: &str) -> PyResult<RangeInclusive<u32>> {
+    if start < stop && (start < 0 || stop > 1 << 32) {
+        return Err(PyOverflowError::new_err(format!(
+            "{method}() can only insert values between 0 and 2**32 - 1, but received the range [{start}, {stop})"
+        )));
+    }
+    Ok(value_range(start, stop))
+}
+
+/// Whether every value in [start, stop) is present in the splinter
+pub(crate) fn contains_range(splinter: &impl PartitionRead<High>, start: i64, stop: i64) -> bool {
+    if start >= stop {
+        return true;
+    }
+    start >= 0 && stop <= 1 << 32 && count_within(splinter, &value_range(start, stop)) == (stop - start) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use splinter_rs::Optimizable;
+
+    use super::*;
+    use crate::{format::{FULL, VEC}, testing::{cases, kinds, optimized}};
+
+    fn values(splinter: &impl PartitionRead<High>) -> Vec<u32> { splinter.iter().collect() }
+
+    /// Whether a splinter holds exactly the values of runs, without listing them
+    fn holds_runs(splinter: &impl PartitionRead<High>, runs: &[RangeInclusive<u32>]) -> bool {
+        let len = |run: &RangeInclusive<u32>| (*run.end() - *run.start()) as usize + 1;
+        splinter.cardinality() == runs.iter().map(len).sum::<usize>() && runs.iter().all(|run| count_within(splinter, run) == len(run))
+    }
+
+    #[test]
+    fn encodes_runs_of_every_shape() {
+        let shapes: Vec<Vec<RangeInclusive<u32>>> = vec![
+            vec![0..=0],
+            vec![0..=u32::MAX],
+            vec![5..=200],
+            vec![0..=255, 300..=301],
+            vec![10..=70_000, 1 << 24..=(1 << 24) + 3],
+            vec![u32::MAX - 300..=u32::MAX],
+            // trees with 31, 32, 33 and 256 children, at each width
+            (0..31).map(|i| i << 8..=i << 8 | 3).collect(),
+            (0..32).map(|i| i << 8..=i << 8 | 3).collect(),
+            (0..33).map(|i| i << 8..=i << 8 | 3).collect(),
+            (0..256).map(|i| i << 8..=i << 8 | 3).collect(),
+            (0..32).map(|i| i << 16..=i << 16 | 300).collect(),
+            (0..32).map(|i| i << 24..=i << 24 | 70_000).collect(),
+            (0..256).map(|i| i << 24..=i << 24).collect(),
+        ];
+        for runs in shapes {
+            let splinter = splinter_from_runs(&runs);
+            assert!(holds_runs(&splinter, &runs));
+            // which splinter-rs also reads back from its own encoding, once codec has fixed it
+            assert!(holds_runs(&codec::encode_to_ref(&splinter), &runs));
+        }
+        assert_eq!(splinter_from_runs(&[]), Splinter::EMPTY);
+        assert_eq!(kinds(&codec::encode(&splinter_from_range(0..=u32::MAX))), [(FULL, 4)]);
+        assert!(kinds(&codec::encode(&splinter_from_range(0..=(1 << 16) - 1))).contains(&(FULL, 2)));
+    }
+
+    #[test]
+    fn encodes_single_ranges() {
+        for range in [0..=0, 7..=1_000_000, 0..=u32::MAX, u32::MAX..=u32::MAX] {
+            assert_eq!(encode_range(range.clone()).cardinality() as u64, *range.end() as u64 - *range.start() as u64 + 1);
+            assert_eq!(encode_range(range.clone()).iter().take(3).collect::<Vec<_>>(), range.clone().take(3).collect::<Vec<_>>());
+            assert_eq!(encode_range(range.clone()).last(), Some(*range.end()));
+        }
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 5..=4;
+        assert_eq!(encode_range(empty).cardinality(), 0);
+    }
+
+    #[test]
+    fn reads_the_kind_of_the_root() {
+        assert!(matches!(optimized((0..5000).map(|i| i * 3)).root(), Root::Tree));
+        assert!(matches!(optimized([1, 5, 9]).root(), Root::Values));
+        assert!(matches!(optimized(0..1 << 16).root(), Root::Runs(runs) if runs == [0..=(1 << 16) - 1]));
+        assert!(matches!(splinter_from_range(0..=u32::MAX).root(), Root::Runs(runs) if runs == [0..=u32::MAX]));
+        let bytes = codec::encode(&optimized((10..20).chain(30..40)));
+        assert!(matches!(bytes[..].root(), Root::Runs(runs) if runs == [10..=19, 30..=39]));
+        // however the splinter is held
+        for splinter in cases() {
+            let cached = CachedSplinter::from(CowSplinter::Owned(splinter.clone()));
+            let frozen = codec::encode_to_ref(&splinter);
+            assert_eq!(splinter.root(), cached.root());
+            assert_eq!(splinter.root(), frozen.root());
+        }
+    }
+
+    #[test]
+    fn inserts_and_removes_ranges() {
+        let starts = [
+            optimized([]),
+            optimized([1, 5, 9, 100_000]),
+            optimized((0..5000).map(|i| i * 3)),
+            optimized((100..200).chain(5000..9000)),
+            splinter_from_range(0..=u32::MAX),
+        ];
+        let ranges = [0..=0, 3..=7, 150..=6000, 4999..=4999, 90_000..=200_000, u32::MAX - 5..=u32::MAX];
+        for start in &starts {
+            for range in &ranges {
+                let mut inserted = start.clone();
+                insert_range(&mut inserted, range.clone());
+                let added = range.clone().count() - count_within(start, range);
+                assert_eq!(inserted.cardinality(), start.cardinality() + added);
+                assert_eq!(count_within(&inserted, range), range.clone().count());
+                if inserted.cardinality() < 1 << 20 {
+                    let mut want = values(start);
+                    want.extend(range.clone());
+                    want.sort_unstable();
+                    want.dedup();
+                    assert_eq!(values(&inserted), want);
+                }
+
+                let mut removed = start.clone();
+                remove_range(&mut removed, range.clone());
+                assert_eq!(removed.cardinality(), start.cardinality() - count_within(start, range));
+                assert_eq!(count_within(&removed, range), 0);
+                assert_eq!(removed.select(0), start.iter().find(|value| !range.contains(value)));
+            }
+        }
+    }
+
+    #[test]
+    fn inserts_and_removes_values_among_runs() {
+        let runs = [0..=5, 100..=200, 1 << 20..=(1 << 20) + 50, u32::MAX - 3..=u32::MAX];
+        let changes = [0, 5, 6, 7, 99, 100, 150, 150, 200, 201, 1 << 20, 1 << 21, u32::MAX - 4, u32::MAX];
+        let start = splinter_from_runs(&runs);
+        for cached in [true, false] {
+            let mut inserted = CachedSplinter::from(CowSplinter::Owned(start.clone()));
+            if cached {
+                // which keeps the encoding, whose root is then read
+                inserted.optimize();
+                assert!(matches!(inserted.encoded_if_cached().unwrap().root(), Root::Runs(_)));
+            }
+            let mut removed = inserted.clone();
+            insert_values(&mut inserted, changes);
+            remove_values(&mut removed, changes);
+            let mut want = values(&start);
+            want.extend(changes);
+            want.sort_unstable();
+            want.dedup();
+            assert_eq!(values(&*inserted), want);
+            assert_eq!(values(&*removed), values(&start).into_iter().filter(|value| !changes.contains(value)).collect::<Vec<_>>());
+        }
+
+        // nor from a large tree, whose full partitions splinter-rs would list
+        let mut tree = CachedSplinter::from(CowSplinter::Owned(splinter_from_range(0..=99_999_999)));
+        remove_values(&mut tree, [5, 5, 1 << 24, 99_999_999, 100_000_000]);
+        assert!(holds_runs(&*tree, &[0..=4, 6..=(1 << 24) - 1, (1 << 24) + 1..=99_999_998]));
+
+        // a hundred million values don't have to be walked to add or remove one
+        let mut huge = CachedSplinter::from(CowSplinter::Owned(splinter_from_range(0..=99_999_999)));
+        huge.optimize();
+        insert_values(&mut huge, [1_000_000_000]);
+        huge.optimize();
+        remove_values(&mut huge, [5]);
+        assert!(holds_runs(&*huge, &[0..=4, 6..=99_999_999, 1_000_000_000..=1_000_000_000]));
+    }
+
+    #[test]
+    fn leaves_trees_and_runs_out_of_splinter_rs_remove_range() {
+        // splinter-rs 0.7 drops the children of a tree which don't hold either end of the range;
+        // once it doesn't, remove_range() can hand every root over to it
+        let start = optimized((0..5000).map(|i| i * 3).chain([1 << 20, 3 << 30]));
+        let mut upstream = start.clone();
+        PartitionWrite::remove_range(&mut upstream, 3..=7);
+        assert_ne!(upstream.cardinality(), start.cardinality() - 2);
+
+        let mut removed = start.clone();
+        remove_range(&mut removed, 3..=7);
+        assert_eq!(values(&removed), start.iter().filter(|value| !(3..=7).contains(value)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn slices_every_kind_of_root() {
+        let splinters = [
+            optimized([]),
+            optimized([1, 5, 9, 100_000]),
+            optimized((0..20_000).map(|i| i * 3)),
+            optimized((0..20_000).map(|i| i * 3).chain(1 << 24..(1 << 24) + 50_000)),
+            optimized((100..200).chain(5000..19_000).chain(1 << 20..(1 << 20) + 9000)),
+        ];
+        let ranges = [(0, 1 << 32), (0, 1), (3, 8), (150, 30_000), (4999, 5000), (6000, 60_000), (1 << 24, (1 << 24) + 20_000), (-5, 2)];
+        for splinter in &splinters {
+            let bytes = codec::encode(splinter);
+            let serialized = SplinterRef::from_bytes(bytes).unwrap();
+            for &(start, stop) in &ranges {
+                let range = value_range(start, stop);
+                let want: Vec<u32> = splinter.iter().filter(|value| range.contains(value)).collect();
+                assert_eq!(values(&slice(splinter, range.clone())), want);
+                assert_eq!(values(&slice(&serialized, range.clone())), want);
+                assert_eq!(count_within(splinter, &range), want.len());
+                assert_eq!(contains_range(splinter, start, stop), want.len() as i64 == stop.min(1 << 32) - start.max(0));
+            }
+        }
+        // slices covering every value in range are built from the range alone
+        let full = splinter_from_range(0..=u32::MAX);
+        assert_eq!(slice(&full, 10..=1 << 30), splinter_from_range(10..=1 << 30));
+        assert!(holds_runs(&slice(&optimized(0..1 << 16), 10..=5000), &[10..=5000]));
+    }
+
+    #[test]
+    fn optimizes_without_walking_values() {
+        for splinter in cases().into_iter().chain([splinter_from_range(7..=8), splinter_from_runs(&[0..=5, 300..=1 << 20])]) {
+            let mut fast = splinter.clone();
+            let encoded = optimize(&mut fast);
+            assert!(fast == splinter);
+            assert_eq!(encoded, codec::encode(&fast));
+            let mut slow = splinter.clone();
+            slow.optimize();
+            assert!(codec::encode(&fast).len() <= codec::encode(&slow).len());
+            // and optimizing again changes nothing
+            let bytes = codec::encode(&fast);
+            optimize(&mut fast);
+            assert_eq!(codec::encode(&fast), bytes);
+        }
+        // which doesn't take long however many values a splinter holds
+        let mut huge = splinter_from_runs(&[0..=5, 300..=u32::MAX - 1]);
+        optimize(&mut huge);
+        assert!(holds_runs(&huge, &[0..=5, 300..=u32::MAX - 1]));
+
+        // a single small run is stored as one, rather than as a tree down to a block
+        let mut small = splinter_from_range(7..=8);
+        optimize(&mut small);
+        assert_eq!(kinds(&codec::encode(&small)), [(VEC, 4)]);
+    }
+
+    #[test]
+    fn coalesces_runs() {
+        assert_eq!(coalesce(vec![0..=3, 4..=6, 6..=6, 9..=12, 10..=11]), [0..=6, 9..=12]);
+        assert_eq!(coalesce(vec![0..=u32::MAX, 5..=10]), [0..=u32::MAX]);
+        assert_eq!(coalesce(vec![]), Vec::<RangeInclusive<u32>>::new());
+    }
+}
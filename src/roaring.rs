@@ -0,0 +1,399 @@
+use std::ops::RangeInclusive;
+
+use splinter_rs::{Optimizable, PartitionRead, Splinter};
+
+use crate::{format::{read_be, Node, Parsed}, ranges};
+
+// Reading and writing the Roaring portable serialization format, which the Roaring libraries for
+// C, Java, Go and Python all share (https://github.com/RoaringBitmap/RoaringFormatSpec).
+//
+// A Roaring bitmap splits its values into containers of 65536 by their high 16 bits, each
+// stored as a sorted array of up to 4096 values, a 65536-bit bitmap, or a list of runs. The
+// serialized form is a cookie, a bitmap marking the run containers if there are any, the key and
+// cardinality of every container, usually the offset of every container, and then the
+// containers, with every integer little-endian.
+//
+// Splinters are written by walking the partition tree into runs, which are then split up
+// between containers and written in whichever of the three forms is smallest, as Roaring's own
+// runOptimize() would choose.
+
+// the cookie of a bitmap without run containers, followed by the number of containers
+const SERIAL_COOKIE_NO_RUNCONTAINER: u32 = 12346;
+// the cookie of a bitmap with run containers, which holds the number of containers minus one in
+// its high 16 bits
+const SERIAL_COOKIE: u32 = 12347;
+// bitmaps with run containers only store container offsets from this many containers on
+const NO_OFFSET_THRESHOLD: usize = 4;
+// containers of more values than this are bitmaps, unless they are runs
+const MAX_ARRAY: usize = 4096;
+const BITMAP_BYTES: usize = 8192;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Array,
+    Bitmap,
+    Run,
+}
+
+/// The runs of values in one container, relative to its start
+struct Container {
+    key: u16,
+    runs: Vec<(u16, u16)>,
+}
+
+impl Container {
+    fn cardinality(&self) -> usize { self.runs.iter().map(|&(lo, hi)| (hi - lo) as usize + 1).sum() }
+
+    /// Picks the smallest of the three forms, preferring an array or bitmap over runs of the
+    /// same size
+    fn kind(&self) -> Kind {
+        let cardinality = self.cardinality();
+        let (kind, size) = match cardinality <= MAX_ARRAY {
+            true => (Kind::Array, 2 * cardinality),
+            false => (Kind::Bitmap, BITMAP_BYTES),
+        };
+        if 2 + 4 * self.runs.len() < size { Kind::Run } else { kind }
+    }
+
+    fn size(&self, kind: Kind) -> usize {
+        match kind {
+            Kind::Array => 2 * self.cardinality(),
+            Kind::Bitmap => BITMAP_BYTES,
+            Kind::Run => 2 + 4 * self.runs.len(),
+        }
+    }
+
+    fn write(&self, kind: Kind, out: &mut Vec<u8>) {
+        match kind {
+            Kind::Array => {
+                for &(lo, hi) in &self.runs {
+                    (lo..=hi).for_each(|value| out.extend(value.to_le_bytes()));
+                }
+            }
+            Kind::Bitmap => {
+                let mut words = [0u64; BITMAP_BYTES / 8];
+                for &(lo, hi) in &self.runs {
+                    let (lo, hi) = (lo as usize, hi as usize);
+                    for (i, word) in words.iter_mut().enumerate().take(hi / 64 + 1).skip(lo / 64) {
+                        let first = if i == lo / 64 { lo % 64 } else { 0 };
+                        let last = if i == hi / 64 { hi % 64 } else { 63 };
+                        *word |= (u64::MAX >> (63 - last + first)) << first;
+                    }
+                }
+                words.iter().for_each(|word| out.extend(word.to_le_bytes()));
+            }
+            Kind::Run => {
+                out.extend((self.runs.len() as u16).to_le_bytes());
+                for &(lo, hi) in &self.runs {
+                    out.extend(lo.to_le_bytes());
+                    out.extend((hi - lo).to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// Writes an encoded splinter in the Roaring portable format
+pub(crate) fn encode(splinter: &[u8]) -> Vec<u8> {
+    let mut runs = Vec::new();
+    collect_runs(&Parsed::new(splinter).root(), 4, 0, &mut runs);
+    let containers = split_runs(&runs);
+    let kinds: Vec<Kind> = containers.iter().map(Container::kind).collect();
+    let has_runs = kinds.contains(&Kind::Run);
+
+    let mut out = Vec::new();
+    if has_runs {
+        out.extend((SERIAL_COOKIE | (containers.len() as u32 - 1) << 16).to_le_bytes());
+        let mut run_bitmap = vec![0u8; containers.len().div_ceil(8)];
+        for (i, _) in kinds.iter().enumerate().filter(|(_, &kind)| kind == Kind::Run) {
+            run_bitmap[i / 8] |= 1 << (i % 8);
+        }
+        out.extend(run_bitmap);
+    } else {
+        out.extend(SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes());
+        out.extend((containers.len() as u32).to_le_bytes());
+    }
+    for container in &containers {
+        out.extend(container.key.to_le_bytes());
+        out.extend(((container.cardinality() - 1) as u16).to_le_bytes());
+    }
+    if !has_runs || containers.len() >= NO_OFFSET_THRESHOLD {
+        // offsets count from the start of the serialized bitmap
+        let mut offset = out.len() + 4 * containers.len();
+        for (container, &kind) in containers.iter().zip(&kinds) {
+            out.extend((offset as u32).to_le_bytes());
+            offset += container.size(kind);
+        }
+    }
+    for (container, &kind) in containers.iter().zip(&kinds) {
+        container.write(kind, &mut out);
+    }
+    out
+}
+
+/// Appends the inclusive runs of values in a partition to out, merging adjacent runs
+fn collect_runs(node: &Node, width: usize, base: u32, out: &mut Vec<(u32, u32)>) {
+    let mut push = |start: u32, end: u32| match out.last_mut() {
+        Some(last) if last.1.checked_add(1) == Some(start) => last.1 = end,
+        _ => out.push((start, end)),
+    };
+    match node {
+        Node::Empty => {}
+        Node::Full => push(base, base | ((1u64 << (8 * width)) - 1) as u32),
+        Node::Bitmap(bitmap) => {
+            for (i, &byte) in bitmap.iter().enumerate() {
+                let mut bits = byte;
+                while bits != 0 {
+                    let value = base | (8 * i as u32 + bits.trailing_zeros());
+                    push(value, value);
+                    bits &= bits - 1;
+                }
+            }
+        }
+        Node::Vec(values) => values.chunks(width).for_each(|v| push(base | read_be(v), base | read_be(v))),
+        Node::Run(runs) => runs.chunks(2 * width).for_each(|run| push(base | read_be(&run[..width]), base | read_be(&run[width..]))),
+        Node::Tree { .. } => {
+            let shift = 8 * (width - 1) as u32;
+            for (segment, child) in node.children(width) {
+                collect_runs(&child, width - 1, base | (segment as u32) << shift, out);
+            }
+        }
+    }
+}
+
+/// Splits runs of values at the boundaries of Roaring containers
+fn split_runs(runs: &[(u32, u32)]) -> Vec<Container> {
+    let mut containers: Vec<Container> = Vec::new();
+    for &(start, end) in runs {
+        for key in start >> 16..=end >> 16 {
+            let lo = if key == start >> 16 { start as u16 } else { 0 };
+            let hi = if key == end >> 16 { end as u16 } else { u16::MAX };
+            match containers.last_mut() {
+                Some(container) if container.key as u32 == key => container.runs.push((lo, hi)),
+                _ => containers.push(Container { key: key as u16, runs: vec![(lo, hi)] }),
+            }
+        }
+    }
+    containers
+}
+
+/// Reads little-endian integers from the front of a buffer
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or_else(|| "the data is truncated".to_string())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> { Ok(u16::from_le_bytes(self.bytes(2)?.try_into().expect("read 2 bytes"))) }
+
+    fn u32(&mut self) -> Result<u32, String> { Ok(u32::from_le_bytes(self.bytes(4)?.try_into().expect("read 4 bytes"))) }
+}
+
+/// Reads a bitmap in the Roaring portable format into a Splinter
+pub(crate) fn decode(data: &[u8]) -> Result<Splinter, String> {
+    let mut reader = Reader { data, pos: 0 };
+    let cookie = reader.u32()?;
+    let (len, run_bitmap) = if cookie & 0xFFFF == SERIAL_COOKIE {
+        let len = (cookie >> 16) as usize + 1;
+        (len, Some(reader.bytes(len.div_ceil(8))?))
+    } else if cookie == SERIAL_COOKIE_NO_RUNCONTAINER {
+        let len = reader.u32()? as usize;
+        if len > 1 << 16 {
+            return Err(format!("the bitmap claims {len} containers, but a 32-bit Roaring bitmap holds at most 65536"));
+        }
+        (len, None)
+    } else {
+        return Err("the data does not start with a Roaring cookie".into());
+    };
+    let header = reader.bytes(4 * len)?;
+    if run_bitmap.is_none() || len >= NO_OFFSET_THRESHOLD {
+        // the containers are read in order, so their offsets aren't needed
+        reader.bytes(4 * len)?;
+    }
+
+    // array and bitmap containers are collected as values, and run containers as runs, which
+    // are inserted a partition at a time
+    let mut values = Vec::new();
+    let mut runs: Vec<RangeInclusive<u32>> = Vec::new();
+    let mut last_key = None;
+    for (i, entry) in header.chunks(4).enumerate() {
+        let key = u16::from_le_bytes([entry[0], entry[1]]);
+        let cardinality = u16::from_le_bytes([entry[2], entry[3]]) as usize + 1;
+        if last_key.is_some_and(|last| last >= key) {
+            return Err(format!("container {key} is out of order"));
+        }
+        last_key = Some(key);
+        let base = (key as u32) << 16;
+
+        let found = if run_bitmap.is_some_and(|bitmap| bitmap[i / 8] & (1 << (i % 8)) != 0) {
+            let count = reader.u16()? as usize;
+            let mut next = 0;
+            for run in reader.bytes(4 * count)?.chunks(4) {
+                let start = u16::from_le_bytes([run[0], run[1]]) as u32;
+                let end = start + u16::from_le_bytes([run[2], run[3]]) as u32;
+                if start < next || end > u16::MAX as u32 {
+                    return Err(format!("container {key} holds overlapping or out of range runs"));
+                }
+                next = end + 1;
+                runs.push(base | start..=base | end);
+            }
+            runs.iter().rev().take(count).map(|run| (run.end() - run.start()) as usize + 1).sum()
+        } else if cardinality > MAX_ARRAY {
+            let before = values.len();
+            for (i, word) in reader.bytes(BITMAP_BYTES)?.chunks(8).enumerate() {
+                let mut word = u64::from_le_bytes(word.try_into().expect("bitmap words are 8 bytes"));
+                while word != 0 {
+                    values.push(base | (64 * i as u32 + word.trailing_zeros()));
+                    word &= word - 1;
+                }
+            }
+            values.len() - before
+        } else {
+            let array = reader.bytes(2 * cardinality)?;
+            let array = array.chunks(2).map(|v| u16::from_le_bytes([v[0], v[1]]));
+            if array.clone().zip(array.clone().skip(1)).any(|(a, b)| a >= b) {
+                return Err(format!("the values of container {key} are not sorted"));
+            }
+            values.extend(array.map(|v| base | v as u32));
+            cardinality
+        };
+        if found != cardinality {
+            return Err(format!("container {key} holds {found} values, but its header records {cardinality}"));
+        }
+    }
+    if reader.pos != data.len() {
+        return Err(format!("{} bytes follow the last container", data.len() - reader.pos));
+    }
+
+    // the runs are already compact, and optimizing them would walk every value they hold
+    let mut splinter = Splinter::from_iter(values);
+    splinter.optimize();
+    if !runs.is_empty() {
+        let runs = ranges::splinter_from_runs(&ranges::coalesce(runs));
+        if splinter.is_empty() { splinter = runs } else { splinter |= &runs }
+    }
+    Ok(splinter)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use roaring::RoaringBitmap;
+
+    use super::*;
+    use crate::{codec, testing::{cases, optimized}};
+
+    /// Writes values in the Roaring format with the roaring crate, which never uses run
+    /// containers
+    fn reference(values: &[u32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RoaringBitmap::from_sorted_iter(values.iter().copied()).unwrap().serialize_into(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Checks that values survive the trip through both our Roaring format and the roaring
+    /// crate's, in either direction
+    fn check_round_trip(values: &[u32]) {
+        let encoded = encode(&codec::encode(&optimized(values.iter().copied())));
+        assert_eq!(decode(&encoded).unwrap().iter().collect::<Vec<_>>(), values);
+        let read = RoaringBitmap::deserialize_from(&encoded[..]).unwrap();
+        assert_eq!(read.iter().collect::<Vec<_>>(), values);
+        assert_eq!(decode(&reference(values)).unwrap().iter().collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn round_trips_the_real_roaring_datasets() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("real_roaring_data");
+        let mut datasets: Vec<_> = fs::read_dir(root).unwrap().map(|entry| entry.unwrap().path()).collect();
+        datasets.sort();
+        for dataset in datasets {
+            let mut files: Vec<_> = fs::read_dir(&dataset).unwrap().map(|entry| entry.unwrap().path()).collect();
+            files.sort();
+            // a few bitmaps from each keeps the test quick, while covering every dataset
+            for file in files.iter().take(3) {
+                let text = fs::read_to_string(file).unwrap();
+                let mut values: Vec<u32> = text.trim().split(',').map(|v| v.trim().parse().unwrap()).collect();
+                values.sort_unstable();
+                values.dedup();
+                check_round_trip(&values);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_every_kind_of_partition() {
+        for splinter in cases() {
+            check_round_trip(&splinter.iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn writes_run_containers() {
+        let encoded = encode(&codec::encode(&optimized((0..100).chain(1000..2000))));
+        let mut expected = Vec::new();
+        // a single container, holding 1100 values in two runs, without offsets
+        expected.extend(SERIAL_COOKIE.to_le_bytes());
+        expected.push(0b1);
+        expected.extend([0u16, 1099].map(u16::to_le_bytes).concat());
+        expected.extend([2u16, 0, 99, 1000, 999].map(u16::to_le_bytes).concat());
+        assert_eq!(encoded, expected);
+        assert_eq!(decode(&encoded).unwrap().iter().collect::<Vec<_>>(), (0..100).chain(1000..2000).collect::<Vec<_>>());
+
+        // from four containers on, offsets are written too, and runs may span containers
+        let values: Vec<u32> = (0..5 << 16).filter(|&v| v % (1 << 16) != 7).collect();
+        let encoded = encode(&codec::encode(&optimized(values.iter().copied())));
+        assert_eq!(u32::from_le_bytes(encoded[..4].try_into().unwrap()), SERIAL_COOKIE | 4 << 16);
+        check_round_trip(&values);
+        check_round_trip(&[u32::MAX - 1, u32::MAX]);
+    }
+
+    #[test]
+    fn switches_to_bitmaps_above_4096_values() {
+        // with no runs to take advantage of, 4096 values are an array and 4097 a bitmap
+        let array: Vec<u32> = (0..4096).map(|v| v * 2).collect();
+        let bitmap: Vec<u32> = (0..4097).map(|v| v * 2).collect();
+        let (array_bytes, bitmap_bytes) = (encode(&codec::encode(&optimized(array.clone()))), encode(&codec::encode(&optimized(bitmap.clone()))));
+        // the containers start after the cookie, the count, one key and cardinality and one offset
+        assert_eq!(array_bytes[16..20], [0, 0, 2, 0]);
+        assert_eq!(bitmap_bytes[16..24], 0x5555_5555_5555_5555u64.to_le_bytes());
+        assert_eq!(array_bytes.len(), 16 + 2 * 4096);
+        assert_eq!(bitmap_bytes.len(), 16 + BITMAP_BYTES);
+        assert_eq!(array_bytes, reference(&array));
+        assert_eq!(bitmap_bytes, reference(&bitmap));
+        check_round_trip(&array);
+        check_round_trip(&bitmap);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for values in [vec![1, 2, 3], (0..5000).map(|v| v * 3).collect(), (0..100).chain(1000..2000).collect()] {
+            let encoded = encode(&codec::encode(&optimized(values)));
+            for len in 0..encoded.len() {
+                assert!(decode(&encoded[..len]).is_err(), "read {len} of {} bytes", encoded.len());
+            }
+            let mut trailing = encoded.clone();
+            trailing.push(0);
+            assert_eq!(decode(&trailing).unwrap_err(), "1 bytes follow the last container");
+        }
+        assert!(decode(&[0; 8]).unwrap_err().contains("cookie"));
+
+        // containers out of order, and runs which overlap
+        let mut bytes = SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes().to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend([1u16, 0, 0, 0].map(u16::to_le_bytes).concat());
+        bytes.extend([20u32, 22].map(u32::to_le_bytes).concat());
+        bytes.extend([5u16, 5].map(u16::to_le_bytes).concat());
+        assert!(decode(&bytes).unwrap_err().contains("out of order"));
+        let mut bytes = SERIAL_COOKIE.to_le_bytes().to_vec();
+        bytes.push(0b1);
+        bytes.extend([0u16, 10, 2, 0, 5, 3, 5].map(u16::to_le_bytes).concat());
+        assert!(decode(&bytes).unwrap_err().contains("overlapping"));
+    }
+}
@@ -0,0 +1,1574 @@
+use std::{ops::RangeInclusive, path::PathBuf, sync::OnceLock};
+
+use bytes::Bytes;
+use numpy::{ndarray::ArrayD, IntoPyArray, PyArray1, PyArrayDyn};
+use pyo3::{exceptions::{PyIndexError, PyKeyError, PyOverflowError, PyRuntimeError, PyTypeError, PyValueError}, prelude::*, types::{PyBytes, PyFrozenSet, PyInt, PyList, PySet, PyTuple, PyType}, PyTypeInfo};
+use splinter_rs::{CowSplinter, Cut, Optimizable, PartitionRead, Splinter, SplinterRef};
+
+use crate::{arrays::{self, Values}, bytes_from_buffer, codec, contains_many_parallel, frozen::{frozenset_hash, int_hash}, get_item, iter::{count_within, value_range, IterState}, missing_key, missing_range, mmap_bytes, overlap, ranges, select_index, BoolOrVec, SplinterWrapper, UintOrVec, ValuesArg};
+
+// Splinters of signed 32-bit values.
+//
+// Each i32 is stored as the u32 2**31 above it, so that i32::MIN is stored as 0 and i32::MAX as
+// u32::MAX. The shift preserves order, which keeps ranges contiguous and rank(), select() and
+// iteration meaningful, and it is simply a flip of the sign bit.
+//
+// A SignedSplinter keeps its stored values in a SplinterWrapper, whose methods do the work of
+// modifying them, so that it behaves exactly as a Splinter would.
+//
+// A serialized SignedSplinter is the splinter-rs encoding of its stored values, behind a four
+// byte header recording that they are shifted, so that the bytes of a Splinter and a
+// SignedSplinter can't be mistaken for one another.
+
+// the first four bytes of every serialized SignedSplinter
+const MAGIC: [u8; 4] = *b"SI32";
+
+const OFFSET: i64 = 1 << 31;
+
+/// Maps a value onto the u32 stored in its place
+fn to_stored(value: i32) -> u32 { value as u32 ^ 1 << 31 }
+
+/// Maps a stored u32 back onto the value it stands for
+fn from_stored(value: u32) -> i32 { (value ^ 1 << 31) as i32 }
+
+/// Shifts a bound on values onto the corresponding bound on stored values, leaving any bound
+/// outside of the i32s to be clamped by the Splinter method it's passed to
+fn shifted(bound: i64) -> i64 { bound.saturating_add(OFFSET) }
+
+/// Converts [start, stop) bounds on values into the range of stored values they cover
+fn stored_range(start: i64, stop: i64) -> RangeInclusive<u32> { value_range(shifted(start), shifted(stop)) }
+
+/// A Splinter of signed values, between -2**31 and 2**31 - 1.
+///
+/// Values are stored shifted up by 2**31, which keeps them in order, so ranges, rank(),
+/// select() and iteration behave as they would on the values themselves. Supports the same set
+/// operations, queries and pickling as Splinter. Operands can be SignedSplinters or any iterable
+/// of ints, but not Splinters, whose values are unsigned.
+#[pyclass(name="SignedSplinter", module="splynters")]
+#[derive(Clone)]
+pub struct SignedSplinterWrapper(
+    // the stored values, each 2**31 above the value it stands for
+    SplinterWrapper,
+);
+
+impl SignedSplinterWrapper {
+    fn new(splinter: CowSplinter<Bytes>) -> Self { Self(SplinterWrapper::new(splinter)) }
+
+    fn from_owned(splinter: Splinter) -> Self { Self::new(CowSplinter::from_owned(splinter)) }
+
+    fn encoded(&self) -> Bytes { self.0.encoded() }
+
+    /// Extracts the arguments of an in-place set method. An argument which is the
+    /// SignedSplinter being updated is copied, since it can't be borrowed again while it's being
+    /// modified
+    fn operands<'py>(slf: &Bound<'py, Self>, args: &Bound<'py, PyTuple>) -> PyResult<Vec<SignedArg<'py>>> {
+        args.iter()
+            .map(|arg| match arg.is(slf) {
+                true => Ok(SignedArg::Values((*slf.borrow().0 .0).clone())),
+                false => arg.extract(),
+            })
+            .collect()
+    }
+
+    /// Applies an in-place operation to a copy of the splinter with each argument in turn
+    fn fold(&self, rhs: &Bound<PyTuple>, op: fn(&mut Splinter, &CowSplinter<Bytes>)) -> PyResult<Self> {
+        let mut result = (*self.0 .0).clone();
+        for other in rhs.iter() {
+            op(result.to_mut(), other.extract::<SignedArg>()?.splinter());
+        }
+        Ok(Self::new(result))
+    }
+
+    fn fold_in_place(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>, op: fn(&mut Splinter, &CowSplinter<Bytes>)) -> PyResult<()> {
+        let others = Self::operands(slf, rhs)?;
+        slf.borrow_mut().0.modify(|splinter| others.iter().for_each(|other| op(splinter, other.splinter())));
+        Ok(())
+    }
+
+    /// Whether each of values is present, or absent if present is false. Values outside of the
+    /// i32s are never present
+    fn mask(&self, values: &Values, present: bool, parallel: bool) -> ArrayD<bool> {
+        values.map(parallel, |val| i32::try_from(val).is_ok_and(|val| self.0 .0.contains(to_stored(val))) == present)
+    }
+}
+
+#[pymethods]
+impl SignedSplinterWrapper {
+    #[new]
+    pub fn __new__() -> Self { Self::new(CowSplinter::default()) }
+    pub fn __len__(&self) -> usize { self.0.__len__() }
+    pub fn __sizeof__(&self) -> usize { MAGIC.len() + self.0.__sizeof__() }
+    pub fn __repr__(&self) -> String {
+        format!("SignedSplinterWrapper(len = {}, compressed_byte_size = {})", self.__len__(), self.__sizeof__())
+    }
+    fn __iter__(slf: &Bound<'_, Self>) -> SignedSplinterIter { SignedSplinterIter::new(slf, 0..=u32::MAX, false) }
+    fn __reversed__(slf: &Bound<'_, Self>) -> SignedSplinterIter { SignedSplinterIter::new(slf, 0..=u32::MAX, true) }
+
+    #[pyo3(signature = (value, reverse = false))]
+    /// Iterates over the elements greater than or equal to value, in ascending order.
+    ///
+    /// Args:
+    ///     value (int): The value to start from
+    ///     reverse (bool): If True, iterates instead over the elements less than or equal to
+    ///     value, in descending order
+    ///
+    /// Returns:
+    ///     SignedSplinterIter: A lazy iterator over the elements
+    fn iter_from(slf: &Bound<'_, Self>, value: i64, reverse: bool) -> SignedSplinterIter {
+        let range = match reverse {
+            true => stored_range(i32::MIN as i64, value.saturating_add(1)),
+            false => stored_range(value, i32::MAX as i64 + 1),
+        };
+        SignedSplinterIter::new(slf, range, reverse)
+    }
+
+    #[pyo3(signature = (start, stop, reverse = false))]
+    /// Iterates over the elements in the range [start, stop), in ascending order.
+    ///
+    /// Args:
+    ///     start (int): The lower bound of the range, inclusive
+    ///     stop (int): The upper bound of the range, exclusive
+    ///     reverse (bool): If True, iterates over the same elements in descending order
+    ///
+    /// Returns:
+    ///     SignedSplinterIter: A lazy iterator over the elements
+    fn iter_range(slf: &Bound<'_, Self>, start: i64, stop: i64, reverse: bool) -> SignedSplinterIter {
+        SignedSplinterIter::new(slf, stored_range(start, stop), reverse)
+    }
+
+    /// Returns an element or list of elements based on the input index or slice
+    ///
+    /// Operates according to Python's slice syntax: [start:stop:step]
+    /// Supports selection by negative indices and negative steps
+    fn __getitem__(&self, index: &Bound<PyAny>) -> PyResult<IntOrVec> {
+        Ok(match get_item(&self.0 .0, index)? {
+            UintOrVec::U32(value) => IntOrVec::I32(from_stored(value)),
+            UintOrVec::Vec(values) => IntOrVec::Vec(values.into_iter().map(from_stored).collect()),
+        })
+    }
+
+    #[staticmethod]
+    /// Constructs a SignedSplinter from an iterator of integers.
+    ///
+    /// A `range` with a step of 1 or -1 is inserted as a whole, without visiting each value.
+    ///
+    /// Args:
+    ///     data list[int] | range: The iterator from which to construct the SignedSplinter.
+    ///
+    /// Returns:
+    ///     SignedSplinter: A SignedSplinter object constructed from the input items
+    pub fn from_list(data: &Bound<PyAny>) -> PyResult<Self> {
+        if let Some((start, stop)) = ranges::contiguous(data)? {
+            return Self::from_range(start, stop);
+        }
+        let mut values = data.extract::<Vec<Bound<PyAny>>>()?.iter().map(|value| element(value).map(to_stored)).collect::<PyResult<Vec<u32>>>()?;
+        values.sort_unstable();
+        let mut splinter = Splinter::from_iter(values);
+        splinter.optimize();
+        Ok(Self::from_owned(splinter))
+    }
+
+    #[staticmethod]
+    /// Constructs a SignedSplinter holding every integer in [start, stop), like
+    /// `range(start, stop)`.
+    ///
+    /// Args:
+    ///     start (int): The first value in the SignedSplinter.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     SignedSplinter: A SignedSplinter containing the range
+    ///
+    /// Raises:
+    ///     OverflowError: If the range reaches outside of -2**31 to 2**31 - 1
+    pub fn from_range(start: i64, stop: i64) -> PyResult<Self> {
+        let range = insertable(start, stop, "from_range")?;
+        Ok(Self::from_owned(ranges::splinter_from_range(range)))
+    }
+    pub fn to_list(&self) -> Vec<i32> { self.0 .0.iter().map(from_stored).collect() }
+
+    #[staticmethod]
+    /// Constructs a SignedSplinter from a NumPy array, reading its buffer directly rather than
+    /// converting each element to a Python int.
+    ///
+    /// Args:
+    ///     data (numpy.ndarray): An array of dtype int32, or of dtype int64 with every value
+    ///     between -2**31 and 2**31 - 1. Arrays of any shape are flattened.
+    ///
+    /// Returns:
+    ///     SignedSplinter: A SignedSplinter object constructed from the array's elements
+    pub fn from_numpy(data: &Bound<PyAny>) -> PyResult<Self> {
+        let Some(values) = arrays::signed_values(data) else {
+            return Err(PyTypeError::new_err(format!(
+                "from_numpy() argument must be a numpy array of dtype int32 or int64, but received an object of type {:#?}",
+                data.get_type().name()?
+            )));
+        };
+        let values = values.map_err(|val| {
+            PyValueError::new_err(format!(
+                "from_numpy() received {val}, but SignedSplinter elements must be between {} and {}", i32::MIN, i32::MAX
+            ))
+        })?;
+        let mut splinter: Splinter = values.into_iter().map(to_stored).collect();
+        splinter.optimize();
+        Ok(Self::from_owned(splinter))
+    }
+
+    /// Returns the elements of the SignedSplinter, in ascending order, as an int32 NumPy array.
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    pub fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<i32>>> {
+        arrays::require_numpy(py, "to_numpy")?;
+        let mut values = Vec::with_capacity(self.__len__());
+        values.extend(self.0 .0.iter().map(from_stored));
+        Ok(values.into_pyarray(py))
+    }
+
+    pub fn to_bytes(&self, py: Python) -> Py<PyBytes> {
+        let encoded = self.encoded();
+        let mut bytes = Vec::with_capacity(MAGIC.len() + encoded.len());
+        bytes.extend(MAGIC);
+        bytes.extend_from_slice(&encoded);
+        PyBytes::new(py, &bytes).into()
+    }
+
+    #[classmethod]
+    /// Constructs a SignedSplinter from raw byte data written by to_bytes().
+    ///
+    /// Accepts any object exporting the buffer protocol. As with Splinter.from_bytes(), a
+    /// read-only buffer is read in place rather than copied, while a writable one is copied. The
+    /// bytes of a Splinter are rejected, since they hold unsigned values.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): The byte data from which to construct the SignedSplinter.
+    ///
+    /// Returns:
+    ///     SignedSplinter: A SignedSplinter object, or else an error explaining why
+    ///     construction failed.
+    pub fn from_bytes(
+        _cls: &Bound<'_, PyType>,
+        data: &Bound<'_, PyAny>,
+    ) -> PyResult<Self> {
+        decode(bytes_from_buffer(data)?).map(Self::new).map_err(|e| {
+            PyValueError::new_err(format!("SignedSplinter could not be constructed from bytes: {e}"))
+        })
+    }
+
+    #[classmethod]
+    /// Opens a serialized SignedSplinter file by memory-mapping it.
+    ///
+    /// As with Splinter.open(), the SignedSplinter reads directly from the mapped pages until it
+    /// is first modified. The file must not be modified or truncated while it is in use.
+    ///
+    /// Args:
+    ///     path (str | os.PathLike): The path of a file written with to_bytes().
+    ///
+    /// Returns:
+    ///     SignedSplinter: A SignedSplinter backed by the memory-mapped file.
+    pub fn open(
+        _cls: &Bound<'_, PyType>,
+        path: PathBuf,
+    ) -> PyResult<Self> {
+        decode(mmap_bytes(&path)?).map(Self::new).map_err(|e| {
+            PyValueError::new_err(format!("SignedSplinter could not be constructed from {}: {e}", path.display()))
+        })
+    }
+
+    /// Checks if the SignedSplinter contains a single value or multiple values.
+    ///
+    /// Args:
+    ///     value (int | list[int]): The value or values to check for.
+    ///
+    /// Returns:
+    ///     bool | list[bool]: A single boolean if the input was a single integer,
+    ///     or a list of booleans if the input was a list.
+    pub fn contains(&self, value: &Bound<PyAny>) -> PyResult<BoolOrVec> {
+        if value.is_instance_of::<PyInt>() {
+            Ok(BoolOrVec::Bool(self.__contains__(value)?))
+        } else if let Ok(values) = value.extract::<Vec<Bound<PyAny>>>() {
+            Ok(BoolOrVec::Vec(values.iter().map(|value| self.__contains__(value)).collect::<PyResult<_>>()?))
+        } else {
+            Err(PyTypeError::new_err(
+                format!(
+                    "contains() argument must be an integer or a list of integers, but received an object of type {:#?}",
+                    value.get_type().name()?
+                )
+            ))
+        }
+    }
+
+    fn __contains__(&self, value: &Bound<PyAny>) -> PyResult<bool> {
+        match value.extract::<i32>() {
+            Ok(value) => Ok(self.0 .0.contains(to_stored(value))),
+            // like a set, which holds no such int, rather than raising
+            Err(_) if value.is_instance_of::<PyInt>() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Checks if the SignedSplinter contains multiple values in parallel.
+    ///
+    /// As with Splinter.contains_many_parallel(), this is only worthwhile for at least 10,000
+    /// values.
+    ///
+    /// Args:
+    ///     values list[int]: The values to check for.
+    ///
+    /// Returns:
+    ///     list[bool]: A list of booleans.
+    pub fn contains_many_parallel(&self, values: Vec<i32>) -> Vec<bool> {
+        contains_many_parallel(&self.0 .0, values.into_iter().map(to_stored).collect())
+    }
+
+    /// Checks if the SignedSplinter contains each element of a NumPy array.
+    ///
+    /// Args:
+    ///     values (numpy.ndarray): An array of dtype int32, int64 or uint32. Values outside of
+    ///     -2**31 to 2**31 - 1 are reported as absent.
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A boolean array of the same shape as values.
+    pub fn contains_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        Ok(self.mask(&Values::extract_array(values, "contains_numpy")?, true, false).into_pyarray(py))
+    }
+
+    /// Checks if the SignedSplinter contains each of a list or NumPy array of values, without
+    /// building a list of Python bools.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to check for.
+    ///     packed (bool): Pack the results eight to a byte, in the layout of numpy.packbits()
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A boolean array of the same shape as values, or a uint8 array of
+    ///     packed bits if packed
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, packed = false, parallel = false))]
+    pub fn contains_mask(&self, py: Python, values: &Bound<PyAny>, packed: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::require_numpy(py, "contains_mask")?;
+        let mask = self.mask(&Values::extract(values, "contains_mask")?, true, parallel);
+        Ok(arrays::mask_to_py(py, mask, packed))
+    }
+
+    /// Returns the values which are in the SignedSplinter, in their original order.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to filter.
+    ///     indices (bool): Return the indices of the values instead, into the flattened input
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A one-dimensional array of the values present, with the dtype of
+    ///     values or int64 for a list, or of their int64 indices
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_present(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::require_numpy(py, "filter_present")?;
+        let values = Values::extract(values, "filter_present")?;
+        let mask = self.mask(&values, true, parallel);
+        Ok(if indices { arrays::indices_of(py, &mask) } else { values.filter(py, &mask) })
+    }
+
+    /// Returns the values which are not in the SignedSplinter, in their original order.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to filter.
+    ///     indices (bool): Return the indices of the values instead, into the flattened input
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A one-dimensional array of the values absent, with the dtype of
+    ///     values or int64 for a list, or of their int64 indices
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_absent(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::require_numpy(py, "filter_absent")?;
+        let values = Values::extract(values, "filter_absent")?;
+        let mask = self.mask(&values, false, parallel);
+        Ok(if indices { arrays::indices_of(py, &mask) } else { values.filter(py, &mask) })
+    }
+
+    /// Inserts a value into the SignedSplinter
+    ///
+    /// This method is overloaded. It can accept either a single integer or an
+    /// iterable of integers.
+    ///
+    /// A `range` with a step of 1 or -1 is inserted as a whole, like `add_range()`.
+    ///
+    /// Args:
+    ///     values (int | list[int] | range): The value or values to insert.
+    ///
+    /// Raises:
+    ///     OverflowError: If a value is outside of -2**31 to 2**31 - 1
+    pub fn add(&mut self, values: &Bound<PyAny>) -> PyResult<()> {
+        match values_arg(values, "add")? {
+            ValuesArg::One(value) => self.0.insert_all([to_stored(value)]),
+            ValuesArg::Range(start, stop) => return self.add_range(start, stop),
+            ValuesArg::Many(values) => self.0.insert_all(values.into_iter().map(to_stored)),
+        }
+        Ok(())
+    }
+
+    /// Removes a value from the SignedSplinter and returns an error if the value is missing.
+    ///
+    /// This method is overloaded. It can accept either a single integer or an
+    /// iterable of integers. Nothing is removed unless every value is present.
+    ///
+    /// A `range` with a step of 1 or -1 is removed as a whole, like `remove_range()`.
+    ///
+    /// Args:
+    ///     values (int | list[int] | range): The value or values to remove.
+    ///
+    /// Raises:
+    ///     OverflowError: If a value is outside of -2**31 to 2**31 - 1
+    pub fn remove(&mut self, value: &Bound<PyAny>) -> PyResult<()> {
+        let values = match values_arg(value, "remove")? {
+            ValuesArg::One(value) => vec![to_stored(value)],
+            ValuesArg::Range(start, stop) => return self.remove_range(start, stop),
+            ValuesArg::Many(values) => values.into_iter().map(to_stored).collect(),
+        };
+        self.0.remove_all(&values).map_err(|missing| missing_key(from_stored(missing)))
+    }
+
+    /// Removes a value from the SignedSplinter, or does nothing if the value is missing.
+    ///
+    /// This method is overloaded. It can accept either a single integer or an
+    /// iterable of integers.
+    ///
+    /// A `range` with a step of 1 or -1 is removed as a whole, like `discard_range()`.
+    ///
+    /// Args:
+    ///     values (int | list[int] | range): The value or values to remove.
+    ///
+    /// Raises:
+    ///     OverflowError: If a value is outside of -2**31 to 2**31 - 1
+    pub fn discard(&mut self, value: &Bound<PyAny>) -> PyResult<()> {
+        match values_arg(value, "discard")? {
+            ValuesArg::One(value) => self.0.discard_all([to_stored(value)]),
+            ValuesArg::Range(start, stop) => self.discard_range(start, stop),
+            ValuesArg::Many(values) => self.0.discard_all(values.into_iter().map(to_stored)),
+        }
+        Ok(())
+    }
+
+    /// Inserts every integer in [start, stop) into the SignedSplinter.
+    ///
+    /// Args:
+    ///     start (int): The first value to insert.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Raises:
+    ///     OverflowError: If the range reaches outside of -2**31 to 2**31 - 1
+    pub fn add_range(&mut self, start: i64, stop: i64) -> PyResult<()> {
+        insertable(start, stop, "add_range")?;
+        self.0.add_range(shifted(start), shifted(stop))
+    }
+
+    /// Removes every integer in [start, stop) from the SignedSplinter, and returns an error if
+    /// any of them is missing.
+    ///
+    /// Args:
+    ///     start (int): The first value to remove.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Raises:
+    ///     KeyError: If any value in the range is missing, in which case nothing is removed
+    pub fn remove_range(&mut self, start: i64, stop: i64) -> PyResult<()> {
+        // the error names the bounds as they were passed, not as they are stored
+        self.0.remove_range(shifted(start), shifted(stop)).map_err(|_| missing_range(start, stop))
+    }
+
+    /// Removes every integer in [start, stop) from the SignedSplinter, skipping any that are
+    /// missing.
+    ///
+    /// Args:
+    ///     start (int): The first value to remove.
+    ///     stop (int): The end of the range, itself excluded.
+    pub fn discard_range(&mut self, start: i64, stop: i64) {
+        self.0.discard_range(shifted(start), shifted(stop))
+    }
+
+    /// Compacts the SignedSplinter's internal representation, choosing the smallest encoding
+    /// for each of its partitions.
+    ///
+    /// As with Splinter, this runs automatically after every modification unless auto_optimize
+    /// has been turned off.
+    pub fn optimize(&mut self) { self.0.optimize() }
+
+    /// Whether methods which modify the SignedSplinter, such as add() and discard(), optimize
+    /// it after every call. Defaults to True.
+    #[getter]
+    fn get_auto_optimize(&self) -> bool { self.0 .1 }
+
+    #[setter]
+    fn set_auto_optimize(&mut self, auto_optimize: bool) { self.0 .1 = auto_optimize }
+
+    /// Merges one or more SignedSplinters into the SignedSplinter
+    ///
+    /// This method is overloaded. It can accept either a single SignedSplinter or a list of
+    /// them.
+    ///
+    /// Args:
+    ///     splinters (SignedSplinter | FrozenSignedSplinter | list[SignedSplinter | FrozenSignedSplinter]): The object or objects to merge with
+    pub fn merge(slf: &Bound<'_, Self>, splinters: &Bound<PyAny>) -> PyResult<()> {
+        let args = match is_signed(splinters) {
+            true => PyTuple::new(slf.py(), [splinters])?,
+            false => match splinters.extract::<Vec<Bound<PyAny>>>() {
+                Ok(list) if list.iter().all(is_signed) => PyTuple::new(slf.py(), list)?,
+                _ => return Err(PyTypeError::new_err(format!(
+                    "merge() argument must be a SignedSplinter or FrozenSignedSplinter, or a list of them, but received an object of type {:#?}",
+                    splinters.get_type().name()?
+                ))),
+            },
+        };
+        Self::fold_in_place(slf, &args, |a, b| *a |= b)
+    }
+
+    /// Removes and returns the intersection between self and splinter.
+    ///
+    /// If self and splinter have no overlap, it returns an empty SignedSplinter and does not
+    /// modify self. Otherwise, any elements in common between the two will be removed from
+    /// self and returned to the caller.
+    ///
+    /// Args:
+    ///     splinter (SignedSplinter | FrozenSignedSplinter): A SignedSplinter to intersect with
+    ///
+    /// Returns:
+    ///     SignedSplinter
+    pub fn cut(slf: &Bound<'_, Self>, rhs: &Bound<PyAny>) -> PyResult<Self> {
+        if !is_signed(rhs) {
+            return Err(PyTypeError::new_err(format!(
+                "cut() argument must be a SignedSplinter or FrozenSignedSplinter, but received an object of type {:#?}",
+                rhs.get_type().name()?
+            )));
+        }
+        let rhs = Self::operands(slf, &PyTuple::new(slf.py(), [rhs])?)?.remove(0);
+        let mut cut = Splinter::default();
+        slf.borrow_mut().0.modify(|splinter| cut = splinter.cut(rhs.splinter()));
+        cut.optimize();
+        Ok(Self::from_owned(cut))
+    }
+
+    /// Returns the number of elements in the SignedSplinter that are less than or equal to the
+    /// given value.
+    ///
+    /// Args:
+    ///     value int: the value to compare against
+    ///
+    /// Returns:
+    ///     int: the number of elements less than or equal to the given value
+    pub fn rank(&self, value: i64) -> usize { count_within(&self.0 .0, &stored_range(i32::MIN as i64, value.saturating_add(1))) }
+
+    /// Returns the element at the given index in the sorted sequence, or None if it is out of
+    /// bounds.
+    ///
+    /// Args:
+    ///     idx int: the index of the sequence to grab. Negative indices count back
+    ///     from the end.
+    ///
+    /// Returns:
+    ///     (int | None): the element at the relevant index, or else a None if overflowed
+    pub fn select(&self, idx: i64) -> Option<i32> { select_index(&self.0 .0, self.__len__(), idx).map(from_stored) }
+
+    pub fn position(&self, value: i32) -> PyResult<usize> {
+        self.0 .0.position(to_stored(value)).ok_or_else(|| {
+            PyValueError::new_err(format!("element {value} does not exist in this SignedSplinter"))
+        })
+    }
+
+    /// Ranks each of a list or NumPy array of values, like rank().
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to rank. Values below -2**31 rank 0.
+    ///     parallel (bool): Rank the values in parallel, which as with contains_many_parallel()
+    ///     is only worthwhile for at least 10,000 values
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A uint64 array of the same shape as values
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn rank_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        arrays::require_numpy(py, "rank_many")?;
+        let ranks = Values::extract(values, "rank_many")?.map(parallel, |val| match i32::try_from(val) {
+            Ok(val) => self.0 .0.rank(to_stored(val)) as u64,
+            Err(_) if val < 0 => 0,
+            Err(_) => self.__len__() as u64,
+        });
+        Ok(ranks.into_pyarray(py))
+    }
+
+    /// Selects the element at each of a list or NumPy array of indices, like select().
+    ///
+    /// Unlike Splinter.select_many(), there is no -1 to stand for an index out of bounds, since
+    /// -1 may be an element, so any such index raises an error instead.
+    ///
+    /// Args:
+    ///     indices (list[int] | numpy.ndarray): The indices to select. Negative indices count
+    ///     back from the end.
+    ///     parallel (bool): Select the elements in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: An int32 array of the same shape as indices
+    ///
+    /// Raises:
+    ///     IndexError: If any index is out of bounds
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (indices, parallel = false))]
+    pub fn select_many<'py>(&self, py: Python<'py>, indices: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i32>>> {
+        arrays::require_numpy(py, "select_many")?;
+        let len = self.__len__();
+        let selected = Values::extract(indices, "select_many")?.map(parallel, |idx| select_index(&self.0 .0, len, idx).map(from_stored));
+        if selected.iter().any(Option::is_none) {
+            return Err(PyIndexError::new_err(format!(
+                "select_many() received an index out of range for a SignedSplinter of {len} elements"
+            )));
+        }
+        Ok(selected.mapv(|value| value.unwrap_or_default()).into_pyarray(py))
+    }
+
+    /// Finds the index of each of a list or NumPy array of values, like position().
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to look for
+    ///     parallel (bool): Look for the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: An int64 array of the same shape as values, holding -1 rather than
+    ///     raising an error for values which are absent
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn position_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        arrays::require_numpy(py, "position_many")?;
+        let positions = Values::extract(values, "position_many")?.map(parallel, |val| {
+            let position = i32::try_from(val).ok().and_then(|val| self.0 .0.position(to_stored(val)));
+            position.map_or(-1, |pos| pos as i64)
+        });
+        Ok(positions.into_pyarray(py))
+    }
+
+    /// Returns the smallest element of the SignedSplinter, or None if it is empty.
+    pub fn min(&self) -> Option<i32> { self.0.min().map(from_stored) }
+
+    /// Returns the largest element of the SignedSplinter, or None if it is empty.
+    pub fn max(&self) -> Option<i32> { self.0.max().map(from_stored) }
+
+    /// Returns the smallest element of the SignedSplinter greater than the given value.
+    ///
+    /// Args:
+    ///     value (int): the value to search after, which does not need to be in the SignedSplinter
+    ///
+    /// Returns:
+    ///     (int | None): the next element, or None if no element is greater than value
+    pub fn successor(&self, value: i64) -> Option<i32> { self.0.successor(shifted(value)).map(from_stored) }
+
+    /// Returns the largest element of the SignedSplinter less than the given value.
+    ///
+    /// Args:
+    ///     value (int): the value to search before, which does not need to be in the SignedSplinter
+    ///
+    /// Returns:
+    ///     (int | None): the previous element, or None if no element is less than value
+    pub fn predecessor(&self, value: i64) -> Option<i32> { self.0.predecessor(shifted(value)).map(from_stored) }
+
+    /// Returns the smallest value greater than or equal to the given value which is not in the
+    /// SignedSplinter.
+    ///
+    /// Args:
+    ///     value (int): the value to start searching from
+    ///
+    /// Returns:
+    ///     (int | None): the next absent value, or None if every value from here up to
+    ///     2**31 - 1 is in the SignedSplinter
+    pub fn next_absent(&self, value: i64) -> Option<i32> { self.0.next_absent(shifted(value)).map(from_stored) }
+
+    /// Returns the elements of the SignedSplinter between lo and hi as a new SignedSplinter,
+    /// including lo and excluding hi.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to include.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     SignedSplinter: The elements within [lo, hi)
+    pub fn range(&self, lo: i64, hi: i64) -> Self { Self(self.0.range(shifted(lo), shifted(hi))) }
+
+    /// Counts the elements of the SignedSplinter between lo and hi, including lo and excluding
+    /// hi.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to count.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     int: The number of elements within [lo, hi)
+    pub fn range_count(&self, lo: i64, hi: i64) -> usize { self.0.range_count(shifted(lo), shifted(hi)) }
+
+    // basic bitwise set operators
+    // the right-hand side may also be a set, frozenset, range, list or NumPy array of ints,
+    // while anything else, Splinters included, returns NotImplemented
+    fn __and__(&self, rhs: SignedOperand) -> Self { Self::new(&*self.0 .0 & rhs.0.splinter()) }
+    fn __or__(&self, rhs: SignedOperand) -> Self { Self::new(&*self.0 .0 | rhs.0.splinter()) }
+    fn __xor__(&self, rhs: SignedOperand) -> Self { Self::new(&*self.0 .0 ^ rhs.0.splinter()) }
+    fn __sub__(&self, rhs: SignedOperand) -> Self { Self::new(&*self.0 .0 - rhs.0.splinter()) }
+
+    fn __rand__(&self, rhs: SignedOperand) -> Self { self.__and__(rhs) }
+    fn __ror__(&self, rhs: SignedOperand) -> Self { self.__or__(rhs) }
+    fn __rxor__(&self, rhs: SignedOperand) -> Self { self.__xor__(rhs) }
+    // unlike the others, subtraction doesn't commute: this computes rhs - self
+    fn __rsub__(&self, rhs: SignedOperand) -> Self { Self::new(rhs.0.splinter() - &*self.0 .0) }
+
+    fn __iand__(&mut self, rhs: SignedOperand) { *self.0 .0.to_mut() &= rhs.0.splinter() }
+    fn __ior__(&mut self, rhs: SignedOperand) { *self.0 .0.to_mut() |= rhs.0.splinter() }
+    fn __ixor__(&mut self, rhs: SignedOperand) { *self.0 .0.to_mut() ^= rhs.0.splinter() }
+    fn __isub__(&mut self, rhs: SignedOperand) { *self.0 .0.to_mut() -= rhs.0.splinter() }
+
+    // set comparison operations
+    // only other SignedSplinters, sets and frozensets can compare equal
+    fn __eq__(&self, rhs: SignedSetOperand) -> bool { *self.0 .0 == *rhs.0.splinter() }
+    fn __ne__(&self, rhs: SignedSetOperand) -> bool { !self.__eq__(rhs) }
+    fn __le__(&self, rhs: SignedOperand) -> bool { overlap::is_subset(&self.encoded(), &rhs.0.encoded()) }
+    fn __lt__(&self, rhs: SignedOperand) -> bool { self.__len__() < rhs.0.splinter().cardinality() && self.__le__(rhs) }
+    fn __ge__(&self, rhs: SignedOperand) -> bool { overlap::is_subset(&rhs.0.encoded(), &self.encoded()) }
+    fn __gt__(&self, rhs: SignedOperand) -> bool { self.__len__() > rhs.0.splinter().cardinality() && self.__ge__(rhs) }
+
+    // stops NumPy from applying operators element by element when an array is on the left
+    #[classattr]
+    fn __array_ufunc__(py: Python) -> PyObject { py.None() }
+
+    // for serialization with pickle, along with whether the SignedSplinter optimizes itself
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        Ok((self.to_bytes(py), self.0 .1).into_pyobject(py)?.into_any().unbind())
+    }
+    // for deserializing from pickle, which also reads the bare bytes pickled by earlier versions
+    fn __setstate__(&mut self, state: &Bound<PyAny>) -> PyResult<()> {
+        let (bytes, auto_optimize) = match state.downcast::<PyTuple>() {
+            Ok(state) => state.extract::<(Bound<PyAny>, bool)>()?,
+            Err(_) => (state.clone(), true),
+        };
+        let splinter = decode(bytes_from_buffer(&bytes)?).map_err(|e| {
+            PyValueError::new_err(format!("Failed to deserialize SignedSplinter from bytes: {e}"))
+        })?;
+        self.0 .0 = splinter.into();
+        self.0 .1 = auto_optimize;
+        Ok(())
+    }
+
+    /// tells pickle how to find the class and serialize it
+    fn __reduce__(&self, py: Python) -> PyResult<(PyObject, PyObject, PyObject)> {
+        let class = Self::type_object(py).into();
+        let args = PyTuple::empty(py).into();
+        Ok((class, args, self.__getstate__(py)?))
+    }
+
+    // copy protocol
+    fn copy(&self) -> Self { self.clone() }
+    fn __copy__(&self) -> Self { self.clone() }
+    // a SignedSplinter holds no Python objects, so a deep copy is the same as a shallow one
+    fn __deepcopy__(&self, _memo: &Bound<PyAny>) -> Self { self.clone() }
+
+    /// Returns an immutable FrozenSignedSplinter holding the same elements.
+    ///
+    /// If this SignedSplinter was loaded from bytes and has not been modified since, the
+    /// FrozenSignedSplinter shares its buffer without copying. Otherwise the SignedSplinter is
+    /// encoded once.
+    ///
+    /// Returns:
+    ///     FrozenSignedSplinter: an immutable, hashable copy of this SignedSplinter
+    pub fn freeze(&self, py: Python) -> PyResult<FrozenSignedSplinterWrapper> { FrozenSignedSplinterWrapper::new(py, self) }
+
+    /// Returns true if self and rhs have no overlap, and false otherwise.
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     bool: true if there is no overlap, false otherwise
+    fn isdisjoint(&self, rhs: SignedArg) -> bool { overlap::is_disjoint(&self.encoded(), &rhs.encoded()) }
+
+    /// Returns true if self is a subset of rhs, and false otherwise.
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     bool: true if self is a subset of rhs, false otherwise
+    fn issubset(&self, rhs: SignedArg) -> bool { overlap::is_subset(&self.encoded(), &rhs.encoded()) }
+
+    /// Returns true if self is a superset of rhs, and false otherwise.
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     bool: true if self is a superset of rhs, false otherwise
+    fn issuperset(&self, rhs: SignedArg) -> bool { overlap::is_subset(&rhs.encoded(), &self.encoded()) }
+
+    /// Returns the number of elements in both self and rhs, without building the intersection.
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     int: len(self & rhs)
+    fn intersection_len(&self, rhs: SignedArg) -> usize { overlap::intersection_len(&self.encoded(), &rhs.encoded()) }
+
+    /// Returns the number of elements in either self or rhs, without building the union.
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     int: len(self | rhs)
+    fn union_len(&self, rhs: SignedArg) -> usize {
+        self.__len__() + rhs.splinter().cardinality() - self.intersection_len(rhs)
+    }
+
+    /// Returns the number of elements in self but not in rhs, without building the difference.
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     int: len(self - rhs)
+    fn difference_len(&self, rhs: SignedArg) -> usize { self.__len__() - self.intersection_len(rhs) }
+
+    /// Returns the Jaccard similarity of self and rhs, the size of their intersection divided by
+    /// the size of their union.
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     float: a similarity between 0.0 and 1.0, which is 1.0 if both are empty
+    fn jaccard(&self, rhs: SignedArg) -> f64 {
+        let (len, rhs_len) = (self.__len__(), rhs.splinter().cardinality());
+        overlap::jaccard(self.intersection_len(rhs), len, rhs_len)
+    }
+
+    /// Returns the union of self and one or more others
+    ///
+    /// Args:
+    ///     rhs list[SignedSplinter | Iterable[int]]: the values to combine
+    ///
+    /// Returns:
+    ///     SignedSplinter: the union of all provided values
+    #[pyo3(signature = (*rhs))]
+    fn union(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> { self.fold(rhs, |a, b| *a |= b) }
+
+    /// Returns the intersection of self and one or more others
+    ///
+    /// Args:
+    ///     rhs list[SignedSplinter | Iterable[int]]: the values to combine
+    ///
+    /// Returns:
+    ///     SignedSplinter: the intersection of all provided values
+    #[pyo3(signature = (*rhs))]
+    fn intersection(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> { self.fold(rhs, |a, b| *a &= b) }
+
+    /// Returns the elements of self which are in none of the others
+    ///
+    /// Args:
+    ///     rhs list[SignedSplinter | Iterable[int]]: the values to subtract
+    ///
+    /// Returns:
+    ///     SignedSplinter: the difference between self and all provided values
+    #[pyo3(signature = (*rhs))]
+    fn difference(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> { self.fold(rhs, |a, b| *a -= b) }
+
+    /// Returns the elements which are in exactly one of self and rhs
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     SignedSplinter: the symmetric difference of self and rhs
+    fn symmetric_difference(&self, rhs: SignedArg) -> Self { Self::new(&*self.0 .0 ^ rhs.splinter()) }
+
+    /// Adds the elements of one or more others to self
+    ///
+    /// Args:
+    ///     rhs list[SignedSplinter | Iterable[int]]: the values to add
+    #[pyo3(signature = (*rhs))]
+    fn update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> { Self::fold_in_place(slf, rhs, |a, b| *a |= b) }
+
+    /// Keeps only the elements of self which are also in every one of the others
+    ///
+    /// Args:
+    ///     rhs list[SignedSplinter | Iterable[int]]: the values to intersect with
+    #[pyo3(signature = (*rhs))]
+    fn intersection_update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> {
+        Self::fold_in_place(slf, rhs, |a, b| *a &= b)
+    }
+
+    /// Removes the elements of one or more others from self
+    ///
+    /// Args:
+    ///     rhs list[SignedSplinter | Iterable[int]]: the values to subtract
+    #[pyo3(signature = (*rhs))]
+    fn difference_update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> {
+        Self::fold_in_place(slf, rhs, |a, b| *a -= b)
+    }
+
+    /// Keeps only the elements which are in exactly one of self and rhs
+    ///
+    /// Args:
+    ///     rhs (SignedSplinter | Iterable[int]): the values to compare against
+    fn symmetric_difference_update(slf: &Bound<'_, Self>, rhs: &Bound<PyAny>) -> PyResult<()> {
+        Self::fold_in_place(slf, &PyTuple::new(slf.py(), [rhs])?, |a, b| *a ^= b)
+    }
+
+    /// Removes and returns the largest element of the SignedSplinter
+    ///
+    /// Returns:
+    ///     int: the removed element
+    ///
+    /// Raises:
+    ///     KeyError: if the SignedSplinter is empty
+    fn pop(&mut self) -> PyResult<i32> {
+        match self.0.pop() {
+            Ok(stored) => Ok(from_stored(stored)),
+            Err(_) => Err(PyKeyError::new_err("pop from an empty SignedSplinter")),
+        }
+    }
+
+    /// Removes every element from the SignedSplinter
+    fn clear(&mut self) { self.0.clear() }
+}
+
+/// Converts [start, stop) bounds into the stored values to insert, raising an OverflowError if
+/// the bounds reach outside of the values a SignedSplinter can hold
+fn insertable(start: i64, stop: i64, method: &str) -> PyResult<RangeInclusive<u32>> {
+    if start < stop && (start < i32::MIN as i64 || stop > i32::MAX as i64 + 1) {
+        return Err(PyOverflowError::new_err(format!(
+            "{method}() can only insert values between -2**31 and 2**31 - 1, but received the range [{start}, {stop})"
+        )));
+    }
+    Ok(stored_range(start, stop))
+}
+
+/// Reads an int as a value of a SignedSplinter, raising an OverflowError for ints it can't hold
+fn element(value: &Bound<PyAny>) -> PyResult<i32> {
+    value.extract().map_err(|err| {
+        if value.is_instance_of::<PyInt>() {
+            PyOverflowError::new_err("SignedSplinter elements must be between -2**31 and 2**31 - 1")
+        } else {
+            err
+        }
+    })
+}
+
+/// Extracts the argument of add(), remove() or discard(), raising an OverflowError rather than a
+/// TypeError for ints, or lists of ints, which a SignedSplinter can't hold
+fn values_arg(values: &Bound<PyAny>, method: &str) -> PyResult<ValuesArg<i32>> {
+    match ValuesArg::extract(values, method) {
+        Err(_) if values.is_instance_of::<PyInt>() => element(values).map(ValuesArg::One),
+        Err(err) => match values.extract::<Vec<Bound<PyAny>>>() {
+            Ok(vals) => vals.iter().map(element).collect::<PyResult<_>>().map(ValuesArg::Many),
+            Err(_) => Err(err),
+        },
+        arg => arg,
+    }
+}
+
+/// Reads the stored values of a serialized SignedSplinter, sharing the memory of bytes
+fn decode(bytes: Bytes) -> Result<CowSplinter<Bytes>, String> {
+    if bytes.get(..MAGIC.len()) != Some(&MAGIC[..]) {
+        return Err("the data does not start with a SignedSplinter header".into());
+    }
+    CowSplinter::from_bytes(bytes.slice(MAGIC.len()..)).map_err(|e| e.to_string())
+}
+
+/// Collects a range, NumPy array or any other iterable of ints into a splinter of their
+/// stored values
+fn stored_from_values(values: &Bound<PyAny>) -> PyResult<Splinter> {
+    if let Some((start, stop)) = ranges::contiguous(values)? {
+        if start < stop && (start < i32::MIN as i64 || stop > i32::MAX as i64 + 1) {
+            return Err(PyOverflowError::new_err(format!(
+                "SignedSplinters can only hold values between -2**31 and 2**31 - 1, but received the range [{start}, {stop})"
+            )));
+        }
+        return Ok(ranges::splinter_from_range(stored_range(start, stop)));
+    }
+    let vals = match arrays::signed_values(values) {
+        Some(vals) => vals.map_err(|val| {
+            PyOverflowError::new_err(format!("SignedSplinters can only hold values between -2**31 and 2**31 - 1, but received {val}"))
+        })?,
+        None => values.try_iter()?.map(|value| element(&value?)).collect::<PyResult<Vec<i32>>>()?,
+    };
+    let mut vals: Vec<u32> = vals.into_iter().map(to_stored).collect();
+    vals.sort_unstable();
+    Ok(Splinter::from_iter(vals))
+}
+
+#[derive(IntoPyObject)]
+pub enum IntOrVec {
+    I32(i32),
+    Vec(Vec<i32>),
+}
+
+/// An argument to a named set method of SignedSplinter, which can be any iterable of ints as
+/// well as a SignedSplinter or FrozenSignedSplinter
+pub enum SignedArg<'py> {
+    Signed(PyRef<'py, SignedSplinterWrapper>),
+    Values(CowSplinter<Bytes>),
+}
+
+impl<'py> FromPyObject<'py> for SignedArg<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(wrapper) = obj.extract::<PyRef<SignedSplinterWrapper>>() {
+            return Ok(Self::Signed(wrapper));
+        }
+        if let Ok(frozen) = obj.downcast::<FrozenSignedSplinterWrapper>() {
+            return Ok(Self::Signed(frozen.borrow().inner.clone_ref(obj.py()).into_bound(obj.py()).borrow()));
+        }
+        stored_from_values(obj).map(|splinter| Self::Values(CowSplinter::from_owned(splinter)))
+    }
+}
+
+impl SignedArg<'_> {
+    /// The stored values of the argument
+    fn splinter(&self) -> &CowSplinter<Bytes> {
+        match self {
+            SignedArg::Signed(wrapper) => &wrapper.0 .0,
+            SignedArg::Values(splinter) => splinter,
+        }
+    }
+
+    fn encoded(&self) -> Bytes {
+        match self {
+            SignedArg::Signed(wrapper) => wrapper.encoded(),
+            SignedArg::Values(splinter) => codec::encode_cow(splinter),
+        }
+    }
+}
+
+/// Whether obj is a SignedSplinter or FrozenSignedSplinter
+fn is_signed(obj: &Bound<PyAny>) -> bool {
+    obj.is_instance_of::<SignedSplinterWrapper>() || obj.is_instance_of::<FrozenSignedSplinterWrapper>()
+}
+
+/// The right-hand side of a SignedSplinter operator: a SignedSplinter, or a set, frozenset,
+/// range, list or NumPy array of ints. Any other type fails to extract, which makes pyo3 return
+/// NotImplemented
+pub struct SignedOperand<'py>(SignedArg<'py>);
+
+impl<'py> FromPyObject<'py> for SignedOperand<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let convertible = is_signed(obj)
+            || obj.is_instance_of::<PySet>()
+            || obj.is_instance_of::<PyFrozenSet>()
+            || obj.is_instance_of::<PyList>()
+            || arrays::is_array(obj)
+            || ranges::contiguous(obj)?.is_some();
+        if !convertible {
+            return Err(PyTypeError::new_err(format!("cannot use an object of type {} as a set operand", obj.get_type().name()?)));
+        }
+        obj.extract().map(Self)
+    }
+}
+
+/// The right-hand side of SignedSplinter's == and !=: another SignedSplinter or
+/// FrozenSignedSplinter, a set or a frozenset. Any other type fails to extract, which makes pyo3
+/// return NotImplemented, so that a SignedSplinter never equals a list or a range, as a set
+/// wouldn't
+pub struct SignedSetOperand<'py>(SignedArg<'py>);
+
+impl<'py> FromPyObject<'py> for SignedSetOperand<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let comparable = is_signed(obj)
+            || obj.is_instance_of::<PySet>()
+            || obj.is_instance_of::<PyFrozenSet>();
+        if !comparable {
+            return Err(PyTypeError::new_err(format!("cannot compare a SignedSplinter with an object of type {}", obj.get_type().name()?)));
+        }
+        obj.extract().map(Self)
+    }
+}
+
+/// An immutable SignedSplinter, backed by its serialized bytes.
+///
+/// Supports the same queries and set operations as SignedSplinter, which return new
+/// FrozenSignedSplinters. Like frozenset, it is hashable, and hashes the same as an equal
+/// frozenset.
+#[pyclass(name="FrozenSignedSplinter", module="splynters")]
+pub struct FrozenSignedSplinterWrapper {
+    // a SignedSplinter of serialized values, which is never handed out and so never modified,
+    // and which iterators read from directly
+    inner: Py<SignedSplinterWrapper>,
+    // content hash, computed on the first call to __hash__
+    hash: OnceLock<isize>,
+}
+
+impl FrozenSignedSplinterWrapper {
+    /// Freezes a SignedSplinter, sharing its encoding if it's serialized or has been encoded
+    /// since it last changed
+    fn new(py: Python, wrapper: &SignedSplinterWrapper) -> PyResult<Self> {
+        let splinter_ref = SplinterRef::from_bytes(wrapper.encoded()).expect("encoded splinters are always valid");
+        let inner = SignedSplinterWrapper::new(CowSplinter::Ref(splinter_ref));
+        Ok(Self { inner: Py::new(py, inner)?, hash: OnceLock::new() })
+    }
+
+    fn get<'py>(&'py self, py: Python<'py>) -> PyRef<'py, SignedSplinterWrapper> { self.inner.borrow(py) }
+}
+
+#[pymethods]
+impl FrozenSignedSplinterWrapper {
+    #[new]
+    pub fn __new__(py: Python) -> PyResult<Self> { Self::new(py, &SignedSplinterWrapper::__new__()) }
+    pub fn __len__(&self, py: Python) -> usize { self.get(py).__len__() }
+    pub fn __sizeof__(&self, py: Python) -> usize { self.get(py).__sizeof__() }
+    pub fn __repr__(&self, py: Python) -> String {
+        format!("FrozenSignedSplinterWrapper(len = {}, compressed_byte_size = {})", self.__len__(py), self.__sizeof__(py))
+    }
+    fn __iter__(&self, py: Python) -> SignedSplinterIter { SignedSplinterWrapper::__iter__(self.inner.bind(py)) }
+    fn __reversed__(&self, py: Python) -> SignedSplinterIter { SignedSplinterWrapper::__reversed__(self.inner.bind(py)) }
+
+    #[pyo3(signature = (value, reverse = false))]
+    /// Iterates over the elements greater than or equal to value in ascending order, or, if
+    /// reverse is set, over the elements less than or equal to value in descending order.
+    fn iter_from(&self, py: Python, value: i64, reverse: bool) -> SignedSplinterIter {
+        SignedSplinterWrapper::iter_from(self.inner.bind(py), value, reverse)
+    }
+
+    #[pyo3(signature = (start, stop, reverse = false))]
+    /// Iterates over the elements in the range [start, stop), in ascending order or in
+    /// descending order if reverse is set.
+    fn iter_range(&self, py: Python, start: i64, stop: i64, reverse: bool) -> SignedSplinterIter {
+        SignedSplinterWrapper::iter_range(self.inner.bind(py), start, stop, reverse)
+    }
+
+    /// Returns an element or list of elements based on the input index or slice
+    fn __getitem__(&self, index: &Bound<PyAny>) -> PyResult<IntOrVec> { self.get(index.py()).__getitem__(index) }
+
+    #[staticmethod]
+    /// Constructs a FrozenSignedSplinter from an iterator of integers.
+    ///
+    /// Args:
+    ///     data list[int] | range: The iterator from which to construct the FrozenSignedSplinter.
+    ///
+    /// Returns:
+    ///     FrozenSignedSplinter: A FrozenSignedSplinter object constructed from the input items
+    pub fn from_list(data: &Bound<PyAny>) -> PyResult<Self> { Self::new(data.py(), &SignedSplinterWrapper::from_list(data)?) }
+
+    pub fn to_list(&self, py: Python) -> Vec<i32> { self.get(py).to_list() }
+    pub fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<i32>>> { self.get(py).to_numpy(py) }
+    pub fn to_bytes(&self, py: Python) -> Py<PyBytes> { self.get(py).to_bytes(py) }
+
+    #[classmethod]
+    /// Constructs a FrozenSignedSplinter from raw byte data written by to_bytes().
+    ///
+    /// As with SignedSplinter.from_bytes(), a read-only buffer is read in place, while a
+    /// writable buffer is copied.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): The byte data from which to construct the FrozenSignedSplinter.
+    ///
+    /// Returns:
+    ///     FrozenSignedSplinter: A FrozenSignedSplinter object, or else an error explaining why
+    ///     construction failed.
+    pub fn from_bytes(cls: &Bound<'_, PyType>, data: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Self::new(cls.py(), &SignedSplinterWrapper::from_bytes(cls, data)?)
+    }
+
+    /// Checks if the FrozenSignedSplinter contains a single value or multiple values.
+    pub fn contains(&self, value: &Bound<PyAny>) -> PyResult<BoolOrVec> { self.get(value.py()).contains(value) }
+    /// Checks if the FrozenSignedSplinter contains multiple values in parallel.
+    pub fn contains_many_parallel(&self, py: Python, values: Vec<i32>) -> Vec<bool> { self.get(py).contains_many_parallel(values) }
+    /// Checks if the FrozenSignedSplinter contains each element of a NumPy array.
+    pub fn contains_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        self.get(py).contains_numpy(py, values)
+    }
+    /// Checks if the FrozenSignedSplinter contains each of a list or NumPy array of values.
+    #[pyo3(signature = (values, packed = false, parallel = false))]
+    pub fn contains_mask(&self, py: Python, values: &Bound<PyAny>, packed: bool, parallel: bool) -> PyResult<PyObject> {
+        self.get(py).contains_mask(py, values, packed, parallel)
+    }
+    /// Returns the values which are in the FrozenSignedSplinter, in their original order.
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_present(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        self.get(py).filter_present(py, values, indices, parallel)
+    }
+    /// Returns the values which are not in the FrozenSignedSplinter, in their original order.
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_absent(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        self.get(py).filter_absent(py, values, indices, parallel)
+    }
+    fn __contains__(&self, py: Python, value: &Bound<PyAny>) -> PyResult<bool> { self.get(py).__contains__(value) }
+
+    /// Returns the number of elements less than or equal to the given value.
+    pub fn rank(&self, py: Python, value: i64) -> usize { self.get(py).rank(value) }
+    /// Returns the element at the given index in the sorted sequence, or None if it is out of
+    /// bounds.
+    pub fn select(&self, py: Python, idx: i64) -> Option<i32> { self.get(py).select(idx) }
+    pub fn position(&self, py: Python, value: i32) -> PyResult<usize> { self.get(py).position(value) }
+    /// Ranks each of a list or NumPy array of values, like rank().
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn rank_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        self.get(py).rank_many(py, values, parallel)
+    }
+    /// Selects the element at each of a list or NumPy array of indices, like select().
+    #[pyo3(signature = (indices, parallel = false))]
+    pub fn select_many<'py>(&self, py: Python<'py>, indices: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i32>>> {
+        self.get(py).select_many(py, indices, parallel)
+    }
+    /// Finds the index of each of a list or NumPy array of values, like position().
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn position_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        self.get(py).position_many(py, values, parallel)
+    }
+    /// Returns the smallest element, or None if the FrozenSignedSplinter is empty.
+    pub fn min(&self, py: Python) -> Option<i32> { self.get(py).min() }
+    /// Returns the largest element, or None if the FrozenSignedSplinter is empty.
+    pub fn max(&self, py: Python) -> Option<i32> { self.get(py).max() }
+    /// Returns the smallest element greater than the given value.
+    pub fn successor(&self, py: Python, value: i64) -> Option<i32> { self.get(py).successor(value) }
+    /// Returns the largest element less than the given value.
+    pub fn predecessor(&self, py: Python, value: i64) -> Option<i32> { self.get(py).predecessor(value) }
+    /// Returns the smallest value greater than or equal to the given value which is absent.
+    pub fn next_absent(&self, py: Python, value: i64) -> Option<i32> { self.get(py).next_absent(value) }
+    /// Counts the elements between lo and hi, including lo and excluding hi.
+    pub fn range_count(&self, py: Python, lo: i64, hi: i64) -> usize { self.get(py).range_count(lo, hi) }
+    /// Returns the elements between lo and hi, including lo and excluding hi, as a new
+    /// FrozenSignedSplinter.
+    pub fn range(&self, py: Python, lo: i64, hi: i64) -> PyResult<Self> { Self::new(py, &self.get(py).range(lo, hi)) }
+
+    // as with SignedSplinter, set operators return the type of the left operand
+    fn __and__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { Self::new(py, &self.get(py).__and__(rhs)) }
+    fn __or__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { Self::new(py, &self.get(py).__or__(rhs)) }
+    fn __xor__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { Self::new(py, &self.get(py).__xor__(rhs)) }
+    fn __sub__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { Self::new(py, &self.get(py).__sub__(rhs)) }
+    fn __rand__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { self.__and__(py, rhs) }
+    fn __ror__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { self.__or__(py, rhs) }
+    fn __rxor__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { self.__xor__(py, rhs) }
+    fn __rsub__(&self, py: Python, rhs: SignedOperand) -> PyResult<Self> { Self::new(py, &self.get(py).__rsub__(rhs)) }
+
+    fn __eq__(&self, py: Python, rhs: SignedSetOperand) -> bool { self.get(py).__eq__(rhs) }
+    fn __ne__(&self, py: Python, rhs: SignedSetOperand) -> bool { self.get(py).__ne__(rhs) }
+    fn __le__(&self, py: Python, rhs: SignedOperand) -> bool { self.get(py).__le__(rhs) }
+    fn __lt__(&self, py: Python, rhs: SignedOperand) -> bool { self.get(py).__lt__(rhs) }
+    fn __ge__(&self, py: Python, rhs: SignedOperand) -> bool { self.get(py).__ge__(rhs) }
+    fn __gt__(&self, py: Python, rhs: SignedOperand) -> bool { self.get(py).__gt__(rhs) }
+
+    // stops NumPy from applying operators element by element when an array is on the left
+    #[classattr]
+    fn __array_ufunc__(py: Python) -> PyObject { py.None() }
+
+    // hashes like the equal frozenset, as FrozenSplinter does
+    fn __hash__(&self, py: Python) -> isize {
+        *self.hash.get_or_init(|| {
+            let inner = self.get(py);
+            frozenset_hash(inner.0 .0.iter().map(|stored| int_hash(from_stored(stored) as i128)), inner.__len__())
+        })
+    }
+
+    /// tells pickle to rebuild the FrozenSignedSplinter from its serialized bytes
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(PyObject, Py<PyTuple>)> {
+        let constructor = Self::type_object(py).getattr("from_bytes")?.unbind();
+        let args = PyTuple::new(py, [self.to_bytes(py)])?.unbind();
+        Ok((constructor, args))
+    }
+
+    // copy protocol, which as with frozenset can hand back the same object
+    fn copy(slf: Py<Self>) -> Py<Self> { slf }
+    fn __copy__(slf: Py<Self>) -> Py<Self> { slf }
+    fn __deepcopy__(slf: Py<Self>, _memo: &Bound<PyAny>) -> Py<Self> { slf }
+
+    /// Returns a mutable SignedSplinter holding the same elements.
+    ///
+    /// The SignedSplinter shares this FrozenSignedSplinter's buffer until it's modified.
+    ///
+    /// Returns:
+    ///     SignedSplinter: a mutable copy of this FrozenSignedSplinter
+    pub fn thaw(&self, py: Python) -> SignedSplinterWrapper { self.get(py).copy() }
+
+    /// Returns true if self and rhs have no overlap, and false otherwise.
+    fn isdisjoint(&self, py: Python, rhs: SignedArg) -> bool { self.get(py).isdisjoint(rhs) }
+    /// Returns true if every element of self is in rhs.
+    fn issubset(&self, py: Python, rhs: SignedArg) -> bool { self.get(py).issubset(rhs) }
+    /// Returns true if every element of rhs is in self.
+    fn issuperset(&self, py: Python, rhs: SignedArg) -> bool { self.get(py).issuperset(rhs) }
+    /// Returns the number of elements in both self and rhs.
+    fn intersection_len(&self, py: Python, rhs: SignedArg) -> usize { self.get(py).intersection_len(rhs) }
+    /// Returns the number of elements in either self or rhs.
+    fn union_len(&self, py: Python, rhs: SignedArg) -> usize { self.get(py).union_len(rhs) }
+    /// Returns the number of elements in self but not in rhs.
+    fn difference_len(&self, py: Python, rhs: SignedArg) -> usize { self.get(py).difference_len(rhs) }
+    /// Returns the Jaccard similarity of self and rhs.
+    fn jaccard(&self, py: Python, rhs: SignedArg) -> f64 { self.get(py).jaccard(rhs) }
+
+    /// Returns the union of self and every argument as a new FrozenSignedSplinter.
+    #[pyo3(signature = (*rhs))]
+    fn union(&self, py: Python, rhs: &Bound<PyTuple>) -> PyResult<Self> { Self::new(py, &self.get(py).union(rhs)?) }
+    /// Returns the elements in self and every argument as a new FrozenSignedSplinter.
+    #[pyo3(signature = (*rhs))]
+    fn intersection(&self, py: Python, rhs: &Bound<PyTuple>) -> PyResult<Self> { Self::new(py, &self.get(py).intersection(rhs)?) }
+    /// Returns the elements in self but in none of the arguments as a new FrozenSignedSplinter.
+    #[pyo3(signature = (*rhs))]
+    fn difference(&self, py: Python, rhs: &Bound<PyTuple>) -> PyResult<Self> { Self::new(py, &self.get(py).difference(rhs)?) }
+    /// Returns the elements in exactly one of self and rhs as a new FrozenSignedSplinter.
+    fn symmetric_difference(&self, py: Python, rhs: SignedArg) -> PyResult<Self> {
+        Self::new(py, &self.get(py).symmetric_difference(rhs))
+    }
+}
+
+/// Iterator class to implement __iter__ on SignedSplinter.
+#[pyclass(name = "SignedSplinterIter", module = "splynters")]
+pub struct SignedSplinterIter {
+    state: IterState,
+    // the SignedSplinter being iterated over, which is checked on every step in case it
+    // changes size
+    source: Py<SignedSplinterWrapper>,
+}
+
+impl SignedSplinterIter {
+    /// Iterates over the values stored within range
+    fn new(splinter: &Bound<'_, SignedSplinterWrapper>, range: RangeInclusive<u32>, rev: bool) -> Self {
+        Self { state: IterState::new(&splinter.borrow().0 .0, range, rev), source: splinter.clone().unbind() }
+    }
+}
+
+#[pymethods]
+impl SignedSplinterIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> { slf }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<i32>> {
+        let source = self.source.borrow(py);
+        // mirrors the check Python's own set iterator makes
+        if self.state.changed(&source.0 .0) {
+            return Err(PyRuntimeError::new_err("SignedSplinter changed size during iteration"));
+        }
+        Ok(self.state.next(|| source.encoded()).map(from_stored))
+    }
+
+    /// Returns the number of elements left to iterate over
+    fn __length_hint__(&self) -> usize { self.state.remaining() }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::{ffi::c_str, types::PyList};
+
+    use super::*;
+    use crate::testing::run_python;
+
+    #[test]
+    fn masks_values_outside_of_the_i32s_as_absent() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let splinter = SignedSplinterWrapper::from_owned([-5, 0, i32::MAX].into_iter().map(to_stored).collect());
+            let probes = PyList::new(py, [i32::MIN as i64 - 1, -5, -4, 0, i32::MAX as i64, 1 << 40]).unwrap();
+            let values = Values::extract(&probes, "contains_mask").unwrap();
+            assert_eq!(splinter.mask(&values, true, false).into_raw_vec_and_offset().0, [false, true, false, true, true, false]);
+            assert_eq!(splinter.mask(&values, false, true).into_raw_vec_and_offset().0, [true, false, true, false, false, true]);
+        });
+    }
+
+    #[test]
+    fn behaves_like_a_splinter_of_the_shifted_values() {
+        run_python(c_str!(
+            r#"
+values = [-2**31, -70000, -5, -1, 0, 1, 5, 70000, 2**31 - 1]
+s = SignedSplinter.from_list(values)
+assert s.to_list() == values and list(s) == values and list(reversed(s)) == values[::-1]
+assert [s.min(), s.max(), s.select(-1), s.rank(0), s.position(-1)] == [-2**31, 2**31 - 1, 2**31 - 1, 5, 3]
+assert [s.successor(-5), s.predecessor(-5), s.next_absent(-1), s.next_absent(2**31 - 1)] == [-1, -70000, 2, None]
+assert s.range(-5, 6).to_list() == [-5, -1, 0, 1, 5] and s.range_count(-2**40, 2**40) == len(values)
+
+s.add([-3, -2])
+s.add(range(-10, -8))
+s.remove([-3, -2])
+s.discard(range(-10, -8))
+assert s.to_list() == values
+try:
+    s.remove([-2, -1])
+    raise AssertionError("removed a missing value")
+except KeyError as e:
+    assert "-2" in str(e) and -1 in s
+try:
+    s.remove_range(-2, 1)
+    raise AssertionError("removed a missing range")
+except KeyError as e:
+    assert "[-2, 1)" in str(e) and -1 in s
+try:
+    s.add_range(-2**31 - 1, 0)
+    raise AssertionError("added a value below -2**31")
+except OverflowError:
+    pass
+for method, bad in [(m, b) for m in [s.add, s.remove, s.discard] for b in [2**31, -2**31 - 1, [0, 2**31], [2**70]]] + [(SignedSplinter.from_list, [2**31])]:
+    try:
+        method(bad)
+        raise AssertionError(method, "accepted", bad)
+    except OverflowError as e:
+        assert str(e) == "SignedSplinter elements must be between -2**31 and 2**31 - 1", e
+assert s.to_list() == values
+assert 2**31 not in s and -2**31 - 1 not in s and s.contains([2**31, -1, 2**70]) == [False, True, False]
+for bad in ["1", 1.5, [None]]:
+    try:
+        s.add(bad)
+        raise AssertionError("add() accepted", bad)
+    except TypeError:
+        pass
+
+s.add_range(-100, 100)
+s.remove_range(-50, 50)
+assert s.range_count(-100, 100) == 100 and s.pop() == 2**31 - 1
+assert SignedSplinter.from_bytes(s.to_bytes()) == s
+
+t = s.copy()
+t.auto_optimize = False
+t.update([-2**31 + 1], SignedSplinter.from_list([7]))
+assert t - s == {-2**31 + 1, 7} and (s | t) == t and s.issubset(t) and not t.issubset(s)
+t.clear()
+assert len(t) == 0 and len(s) > 0
+
+import pickle
+assert pickle.loads(pickle.dumps(t)).auto_optimize is False and pickle.loads(pickle.dumps(s)).auto_optimize is True
+t.__setstate__(s.to_bytes())
+assert t == s and t.auto_optimize is True
+"#
+        ));
+    }
+
+    #[test]
+    fn matches_the_surface_of_splinter() {
+        run_python(c_str!(
+            r#"
+import collections.abc, pickle
+values = [-2**31, -70000, -5, -1, 0, 1, 5, 70000, 2**31 - 1]
+s = SignedSplinter.from_list(values)
+for v in [-2**40, -2**31, -6, -1, 0, 6, 2**31 - 1, 2**40]:
+    assert list(s.iter_from(v)) == [x for x in values if x >= v]
+    assert list(s.iter_from(v, reverse=True)) == [x for x in values if x <= v][::-1]
+for lo, hi in [(-2**40, 2**40), (-5, 1), (0, 0), (-70000, 70001), (3, -3)]:
+    expected = [x for x in values if lo <= x < hi]
+    it = s.iter_range(lo, hi)
+    assert it.__length_hint__() == len(expected) and list(it) == expected
+    assert list(s.iter_range(lo, hi, True)) == expected[::-1]
+assert s.contains_many_parallel([-5, -4, 2**31 - 1]) == [True, False, True]
+
+t = SignedSplinter.from_list(values)
+t.merge([SignedSplinter.from_list([-3]), SignedSplinter.from_list([4]).freeze()])
+t.merge(SignedSplinter.from_list([-2**30]))
+assert sorted(set(t) - set(values)) == [-2**30, -3, 4]
+cut = t.cut(SignedSplinter.from_list([-2**30, -3, 4, 99]))
+assert list(cut) == [-2**30, -3, 4] and list(t) == values
+assert len(t.cut(SignedSplinter.from_list([2]).freeze())) == 0 and list(t) == values
+assert list(t.cut(t)) == values and len(t) == 0
+for bad in [[1], 5, Splinter.from_list([1]), [SignedSplinter(), 2]]:
+    for method in [t.merge, t.cut]:
+        try:
+            method(bad)
+            raise AssertionError(method, "accepted", bad)
+        except TypeError:
+            pass
+
+f = s.freeze()
+assert type(f) is FrozenSignedSplinter and f == s and s == f and list(f) == values and len(f) == len(values)
+assert hash(f) == hash(frozenset(values)) and {f: 1}[FrozenSignedSplinter.from_list(values)] == 1
+assert list(reversed(f)) == values[::-1] and list(f.iter_from(0)) == values[4:] and list(f.iter_range(-5, 1)) == [-5, -1, 0]
+assert f[0] == -2**31 and f[-1] == 2**31 - 1 and f[2:4] == [-5, -1] and -1 in f and 2**31 not in f
+assert (f.rank(0), f.select(-2), f.position(-1), f.min(), f.max()) == (5, 70000, 3, -2**31, 2**31 - 1)
+assert (f.successor(-5), f.predecessor(-5), f.next_absent(-1), f.range_count(-5, 5)) == (-1, -70000, 2, 4)
+assert type(f & s) is FrozenSignedSplinter and type(s & f) is SignedSplinter and type(f | [3]) is FrozenSignedSplinter
+assert list(f - [0, 1]) == values[:4] + values[6:] and type(f.range(-5, 5)) is FrozenSignedSplinter
+assert f.issubset(s) and f.intersection_len([-1, 2]) == 1 and type(f.union([2])) is FrozenSignedSplinter
+assert pickle.loads(pickle.dumps(f)) == f and type(pickle.loads(pickle.dumps(f))) is FrozenSignedSplinter
+assert FrozenSignedSplinter.from_bytes(f.to_bytes()) == s
+t = f.thaw()
+t.add(3)
+assert 3 not in f and 3 in t and type(t) is SignedSplinter
+assert isinstance(f, collections.abc.Set) and not isinstance(f, collections.abc.MutableSet)
+assert not hasattr(f, "add") and not hasattr(f, "merge")
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn answers_batch_queries_with_numpy() {
+        run_python(c_str!(
+            r#"
+import numpy as np
+
+values = [-2**31, -70000, -5, -1, 0, 1, 5, 70000, 2**31 - 1]
+s = SignedSplinter.from_list(values)
+f = s.freeze()
+for dtype in [np.int32, np.int64]:
+    assert SignedSplinter.from_numpy(np.array(values[::-1], dtype=dtype)).to_list() == values
+for t in [s, f]:
+    out = t.to_numpy()
+    assert out.dtype == np.int32 and out.tolist() == values
+for bad in [2**31, -2**31 - 1]:
+    try:
+        SignedSplinter.from_numpy(np.array([1, bad], dtype=np.int64))
+        raise AssertionError(f"from_numpy() accepted {bad}")
+    except ValueError:
+        pass
+
+probes = [-2**40, -2**31, -6, -1, 0, 6, 2**31 - 1, 2**40]
+for arr in [probes, np.array(probes, dtype=np.int64)]:
+    assert s.rank_many(arr).tolist() == [sum(x <= p for x in values) for p in probes]
+    assert s.contains_mask(arr).tolist() == [p in values for p in probes]
+    assert s.position_many(arr).tolist() == [values.index(p) if p in values else -1 for p in probes]
+small = np.array([-5, -4, 70000, 3], dtype=np.int32)
+assert s.contains_numpy(small.reshape(2, 2)).tolist() == [[True, False], [True, False]]
+assert s.filter_present(small).dtype == np.int32 and s.filter_present(small).tolist() == [-5, 70000]
+assert s.filter_absent(small, indices=True).tolist() == [1, 3] and s.filter_absent([-4, 5]).tolist() == [-4]
+assert s.select_many(np.array([0, -1, 3])).tolist() == [-2**31, 2**31 - 1, -1]
+assert s.contains_mask(small, packed=True).tolist() == [0b10100000]
+try:
+    s.select_many([len(values)])
+    raise AssertionError("select_many() accepted an index out of range")
+except IndexError:
+    pass
+assert f.rank_many([0], parallel=True).tolist() == [5]
+"#
+        ));
+    }
+
+    #[test]
+    fn compares_equal_only_to_sets() {
+        run_python(c_str!(
+            r#"
+s = SignedSplinter.from_list([-1, 0, 1])
+for other in [{-1, 0, 1}, frozenset({1, 0, -1}), SignedSplinter.from_list([1, 0, -1])]:
+    assert s == other and other == s and not s != other, other
+for other in [range(-1, 2), [-1, 0, 1], (-1, 0, 1), {0, 1}, Splinter.from_list([0, 1]), None, 3]:
+    assert s != other and other != s and not s == other, other
+"#
+        ));
+    }
+
+    #[test]
+    fn stops_iterating_once_modified() {
+        run_python(c_str!(
+            r#"
+s = SignedSplinter.from_range(-5000, 5000)
+it = iter(s)
+assert [next(it) for _ in range(100)] == list(range(-5000, -4900))
+assert it.__length_hint__() == 9900
+s.discard(0)
+try:
+    next(it)
+    raise AssertionError("kept iterating over a modified SignedSplinter")
+except RuntimeError:
+    pass
+# one backed by bytes is read in place
+s = SignedSplinter.from_bytes(SignedSplinter.from_list([-3, 4]).to_bytes())
+assert list(reversed(s)) == [4, -3]
+"#
+        ));
+    }
+}
@@ -0,0 +1,75 @@
+use std::ffi::CStr;
+
+use pyo3::{prelude::*, types::PyDict, wrap_pymodule};
+use splinter_rs::{Optimizable, Splinter};
+
+use crate::format::{Node, BITMAP, EMPTY, FULL, RUN, TREE, VEC};
+
+// Helpers shared by the unit tests of each module.
+
+/// Builds a splinter from values and optimizes it, as every Splinter built from Python is
+pub(crate) fn optimized(values: impl IntoIterator<Item = u32>) -> Splinter {
+    let mut splinter = Splinter::from_iter(values);
+    splinter.optimize();
+    splinter
+}
+
+/// Splinters which between them hold every kind of partition at every level, along with trees
+/// of exactly 32 children, which splinter-rs 0.7 can't read back from its own encoding
+pub(crate) fn cases() -> Vec<Splinter> {
+    vec![
+        Splinter::EMPTY,
+        optimized([0]),
+        optimized([u32::MAX]),
+        optimized([3, 64, 65, 1000, 70_000, 1 << 24, u32::MAX - 1]),
+        optimized((0..5000).map(|i| i * 3)),
+        optimized((0..256).filter(|i| i % 3 != 0).chain((1 << 16..1 << 17).filter(|i| i % 5 != 0))),
+        optimized((100..200).chain(5000..9000).chain(65_000..65_600)),
+        optimized(0..1 << 16),
+        optimized((0..256).chain((256..10_240).filter(|i| i % 4 == 0))),
+        optimized((0..1 << 16).chain((1..20).flat_map(|s| (0..60).map(move |v| (s << 16) | (v * 4))))),
+        optimized((0..1 << 24).step_by(1 << 8).filter(|i| i % (3 << 8) != 0)),
+        optimized((0..32).flat_map(|s| (0..50).map(move |v| (s << 8) | (v * 2)))),
+        optimized((0..32).flat_map(|s| (0..50).map(move |v| (s << 24) | (v * 2)))),
+        optimized((0..40).map(|i| i * 256).chain((0..256).map(|i| 1 << 20 | i << 8))),
+        optimized((0..40_000).filter(|i| i % 7 != 0).chain([(1 << 20) + 5])),
+    ]
+}
+
+/// Collects the kind of every partition of an encoded splinter, with the width of its values
+pub(crate) fn kinds(bytes: &[u8]) -> Vec<(u8, usize)> {
+    fn walk(node: &Node, width: usize, out: &mut Vec<(u8, usize)>) {
+        let kind = match node {
+            Node::Empty => EMPTY,
+            Node::Full => FULL,
+            Node::Bitmap(_) => BITMAP,
+            Node::Vec(_) => VEC,
+            Node::Run(_) => RUN,
+            Node::Tree { .. } => TREE,
+        };
+        out.push((kind, width));
+        node.children(width).for_each(|(_, child)| walk(&child, width - 1, out));
+    }
+    let mut out = Vec::new();
+    walk(&Node::root(bytes).expect("test encodings parse"), 4, &mut out);
+    out
+}
+
+/// Runs Python code with every name of the splynters module in scope, panicking with the
+/// traceback if it raises
+pub(crate) fn run_python(code: &CStr) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        // a module can only be initialized once per interpreter, so it's kept in sys.modules
+        let modules = py.import("sys").and_then(|sys| sys.getattr("modules")).unwrap();
+        if !modules.contains("splynters").unwrap() {
+            modules.set_item("splynters", wrap_pymodule!(crate::splynters)(py)).unwrap();
+        }
+        let globals = PyDict::new(py);
+        py.run(c"from splynters import *", Some(&globals), None).unwrap();
+        if let Err(err) = py.run(code, Some(&globals), None) {
+            err.display(py);
+            panic!("{err}");
+        }
+    });
+}
@@ -0,0 +1,2091 @@
+use std::{collections::{BTreeMap, BTreeSet}, path::PathBuf, sync::OnceLock};
+
+use bytes::Bytes;
+use numpy::{ndarray::ArrayD, IntoPyArray, PyArray1, PyArrayDyn};
+use pyo3::{exceptions::{PyIndexError, PyKeyError, PyOverflowError, PyRuntimeError, PyTypeError, PyValueError}, prelude::*, types::{PyBytes, PyFrozenSet, PyInt, PyList, PySet, PySlice, PyTuple, PyType}, PyTypeInfo};
+use rayon::prelude::*;
+use splinter_rs::{CowSplinter, Cut, Encodable, Optimizable, PartitionRead, PartitionWrite, Splinter, SplinterRef};
+
+use crate::{arrays::{self, WideValues}, bytes_from_buffer, codec::CachedSplinter, frozen::{frozenset_hash, int_hash}, iter::{count_within, Cursor}, mmap_bytes, next_absent, overlap, predecessor, ranges, successor, BoolOrVec, SplinterArg};
+
+// Splinters of 64-bit values.
+//
+// splinter-rs only holds u32 values, so a Splinter64 splits each value into its high and low 32
+// bits, and keeps a splinter of the low bits for every distinct high half, in a map sorted by
+// the high bits. Set operations pair up the partitions with the same high bits, so values which
+// share their high bits, as IDs handed out in sequence do, compress as well as in a Splinter.
+//
+// A serialized Splinter64 is a directory followed by the splinter-rs encoding of each partition:
+//
+//   b"SP64" | partition count: u32 | (high bits: u32, encoding length: u32) per partition | encodings
+//
+// with every integer little-endian and the partitions in ascending order. Each encoding carries
+// its own checksum, and is read in place without copying when loaded. Each partition also caches
+// its encoding once it's been compared or iterated over, until it's next modified.
+
+// the first four bytes of every serialized Splinter64
+const MAGIC: [u8; 4] = *b"SP64";
+
+/// The splinters of the low bits of a Splinter64's values, keyed by their high bits. A partition
+/// is removed as soon as it becomes empty
+type Partitions = BTreeMap<u32, CachedSplinter>;
+
+fn split(value: u64) -> (u32, u32) { ((value >> 32) as u32, value as u32) }
+
+fn join(high: u32, low: u32) -> u64 { (high as u64) << 32 | low as u64 }
+
+/// A Splinter of 64-bit values, between 0 and 2**64 - 1.
+///
+/// Values are partitioned by their high 32 bits into ordinary splinters of their low bits, so
+/// values sharing their high bits compress as well as they would in a Splinter. Supports the
+/// same set operations, queries and pickling as Splinter, and any Splinter or FrozenSplinter can
+/// be used as an operand, standing for the values below 2**32.
+#[pyclass(name="Splinter64", module="splynters")]
+#[derive(Clone)]
+pub struct Splinter64Wrapper {
+    partitions: Partitions,
+    // whether methods which modify the Splinter64 optimize the partitions they touch
+    auto_optimize: bool,
+    // bumped on every modification, so that iterators can tell when the set changes under them
+    version: u64,
+}
+
+impl Splinter64Wrapper {
+    fn new(partitions: Partitions) -> Self { Self { partitions, auto_optimize: true, version: 0 } }
+
+    /// Borrows the partitions for modification
+    fn partitions_mut(&mut self) -> &mut Partitions {
+        self.version += 1;
+        &mut self.partitions
+    }
+
+    fn optimize_if_auto(&mut self, highs: impl IntoIterator<Item = u32>) {
+        if !self.auto_optimize {
+            return;
+        }
+        for high in highs {
+            if let Some(partition) = self.partitions.get_mut(&high) {
+                optimize(partition);
+            }
+        }
+    }
+
+    fn len(&self) -> usize { self.partitions.values().map(|p| p.cardinality()).sum() }
+
+    /// Yields every value in ascending order
+    fn values(&self) -> impl Iterator<Item = u64> + '_ {
+        self.partitions.iter().flat_map(|(&high, p)| p.iter().map(move |low| join(high, low)))
+    }
+
+    /// Inserts values, which must be sorted, returning the high bits of the partitions touched
+    fn insert_sorted(&mut self, values: &[u64]) -> Vec<u32> {
+        let mut touched = Vec::new();
+        for &value in values {
+            let (high, low) = split(value);
+            if touched.last() != Some(&high) {
+                touched.push(high);
+            }
+            self.partitions_mut().entry(high).or_default().to_mut().insert(low);
+        }
+        touched
+    }
+
+    /// Removes a value, returning whether it was present
+    fn remove_value(&mut self, value: u64) -> bool {
+        let (high, low) = split(value);
+        if !self.partitions.get(&high).is_some_and(|p| p.contains(low)) {
+            return false;
+        }
+        let partitions = self.partitions_mut();
+        let partition = partitions.get_mut(&high).expect("the partition was just found");
+        partition.to_mut().remove(low);
+        if partition.is_empty() {
+            partitions.remove(&high);
+        }
+        true
+    }
+
+    /// Counts the values in [lo, hi]
+    fn count_within(&self, lo: u64, hi: u64) -> usize {
+        self.partitions
+            .range(split(lo).0..=split(hi).0)
+            .map(|(&high, partition)| count_within(partition, &low_range(high, lo, hi)))
+            .sum()
+    }
+
+    fn contains_value(&self, value: u64) -> bool {
+        let (high, low) = split(value);
+        self.partitions.get(&high).is_some_and(|p| p.contains(low))
+    }
+
+    /// Selects the element at an index, with negative indices counting back from the end
+    fn select_index(&self, idx: i64) -> Option<u64> {
+        let mut idx = if idx < 0 { self.len().checked_sub(idx.unsigned_abs() as usize)? } else { idx as usize };
+        for (&high, partition) in &self.partitions {
+            let len = partition.cardinality();
+            if idx < len {
+                return partition.select(idx).map(|low| join(high, low));
+            }
+            idx -= len;
+        }
+        None
+    }
+
+    /// Lists the partitions with the number of values before each one, for looking up many
+    /// ranks and indices at once
+    fn index(&self) -> Index<'_> {
+        let mut len = 0;
+        let partitions = self
+            .partitions
+            .iter()
+            .map(|(&high, partition)| {
+                let entry = (high, len, partition);
+                len += partition.cardinality();
+                entry
+            })
+            .collect();
+        Index { partitions, len }
+    }
+
+    /// Checks every element of a list or array for membership, or for absence if present is false
+    fn mask(&self, values: &WideValues, present: bool, parallel: bool) -> ArrayD<bool> {
+        values.map(parallel, |val| u64::try_from(val).is_ok_and(|val| self.contains_value(val)) == present)
+    }
+
+    /// Extracts the arguments of an in-place set method. An argument which is the Splinter64
+    /// being updated is copied, since it can't be borrowed again while it's being modified
+    fn operands<'py>(slf: &Bound<'py, Self>, args: &Bound<'py, PyTuple>) -> PyResult<Vec<Set64Arg<'py>>> {
+        args.iter()
+            .map(|arg| match arg.is(slf) {
+                true => Ok(Set64Arg::Values(slf.borrow().partitions.clone())),
+                false => arg.extract(),
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl Splinter64Wrapper {
+    #[new]
+    pub fn __new__() -> Self { Self::new(Partitions::new()) }
+    pub fn __len__(&self) -> usize { self.len() }
+    pub fn __sizeof__(&self) -> usize { encoded_size(&self.partitions) }
+    pub fn __repr__(&self) -> String {
+        format!(
+            "Splinter64Wrapper(len = {}, partitions = {}, compressed_byte_size = {})",
+            self.len(), self.partitions.len(), encoded_size(&self.partitions)
+        )
+    }
+    fn __iter__(slf: &Bound<'_, Self>) -> Splinter64Iter { Splinter64Iter::new(slf, Some((0, u64::MAX)), false) }
+    fn __reversed__(slf: &Bound<'_, Self>) -> Splinter64Iter { Splinter64Iter::new(slf, Some((0, u64::MAX)), true) }
+
+    #[pyo3(signature = (value, reverse = false))]
+    /// Iterates over the elements greater than or equal to value, in ascending order.
+    ///
+    /// Iteration starts directly at value, without decoding any of the elements before it.
+    ///
+    /// Args:
+    ///     value (int): The value to start from
+    ///     reverse (bool): If True, iterates instead over the elements less than or equal to
+    ///     value, in descending order
+    ///
+    /// Returns:
+    ///     Splinter64Iter: A lazy iterator over the elements
+    fn iter_from(slf: &Bound<'_, Self>, value: i128, reverse: bool) -> Splinter64Iter {
+        let range = if reverse { clamped(0, value.saturating_add(1)) } else { clamped(value, 1 << 64) };
+        Splinter64Iter::new(slf, range, reverse)
+    }
+
+    #[pyo3(signature = (start, stop, reverse = false))]
+    /// Iterates over the elements in the range [start, stop), in ascending order.
+    ///
+    /// Args:
+    ///     start (int): The lower bound of the range, inclusive
+    ///     stop (int): The upper bound of the range, exclusive
+    ///     reverse (bool): If True, iterates over the same elements in descending order
+    ///
+    /// Returns:
+    ///     Splinter64Iter: A lazy iterator over the elements
+    fn iter_range(slf: &Bound<'_, Self>, start: i128, stop: i128, reverse: bool) -> Splinter64Iter {
+        Splinter64Iter::new(slf, clamped(start, stop), reverse)
+    }
+
+    /// Returns an element or list of elements based on the input index or slice
+    ///
+    /// Operates according to Python's slice syntax: [start:stop:step]
+    /// Supports selection by negative indices and negative steps
+    fn __getitem__(&self, index: &Bound<PyAny>) -> PyResult<PyObject> {
+        let py = index.py();
+        if let Ok(idx) = index.extract::<i64>() {
+            match self.select_index(idx) {
+                Some(value) => Ok(value.into_pyobject(py)?.into_any().unbind()),
+                None => Err(PyIndexError::new_err("splinter index out of range")),
+            }
+        } else if let Ok(slice) = index.downcast::<PySlice>() {
+            let indices = slice.indices(self.len() as isize)?;
+            let step = indices.step.unsigned_abs();
+            // as with Splinter, a negative step walks the matching forward slice and flips it
+            let first = match indices.step > 0 {
+                true => indices.start as usize,
+                false => (indices.start as usize).saturating_sub(indices.slicelength.saturating_sub(1) * step),
+            };
+            let mut values: Vec<u64> = self.values().skip(first).step_by(step).take(indices.slicelength).collect();
+            if indices.step < 0 {
+                values.reverse();
+            }
+            Ok(values.into_pyobject(py)?.into_any().unbind())
+        } else {
+            Err(PyTypeError::new_err("splinter indices must be integers or slices"))
+        }
+    }
+
+    #[staticmethod]
+    /// Constructs a Splinter64 from an iterator of unsigned integers.
+    ///
+    /// A `range` with a step of 1 or -1 is inserted as a whole, without visiting each value.
+    ///
+    /// Args:
+    ///     data list[int] | range: The iterator from which to construct the Splinter64.
+    ///
+    /// Returns:
+    ///     Splinter64: A Splinter64 object constructed from the input items
+    pub fn from_list(data: &Bound<PyAny>) -> PyResult<Self> {
+        if let Some((start, stop)) = ranges::contiguous_wide(data)? {
+            return Self::from_range(start, stop);
+        }
+        let mut values = data.extract::<Vec<Bound<PyAny>>>()?.iter().map(element).collect::<PyResult<Vec<u64>>>()?;
+        values.sort_unstable();
+        Ok(Self::new(from_sorted(values)))
+    }
+
+    #[staticmethod]
+    /// Constructs a Splinter64 holding every integer in [start, stop), like `range(start, stop)`.
+    ///
+    /// Args:
+    ///     start (int): The first value in the Splinter64.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     Splinter64: A Splinter64 containing the range
+    ///
+    /// Raises:
+    ///     OverflowError: If the range reaches outside of 0 to 2**64 - 1
+    pub fn from_range(start: i128, stop: i128) -> PyResult<Self> {
+        let mut partitions = Partitions::new();
+        if let Some((lo, hi)) = insertable(start, stop, "from_range")? {
+            insert_range(&mut partitions, lo, hi);
+        }
+        Ok(Self::new(partitions))
+    }
+    pub fn to_list(&self) -> Vec<u64> { self.values().collect() }
+
+    #[staticmethod]
+    /// Constructs a Splinter64 from a NumPy array, reading its buffer directly rather than
+    /// converting each element to a Python int.
+    ///
+    /// Args:
+    ///     data (numpy.ndarray): An array of dtype uint64 or uint32, or of dtype int64 with no
+    ///     negative values. Arrays of any shape are flattened.
+    ///
+    /// Returns:
+    ///     Splinter64: A Splinter64 object constructed from the array's elements
+    pub fn from_numpy(data: &Bound<PyAny>) -> PyResult<Self> {
+        let Some(values) = arrays::wide_values(data) else {
+            return Err(PyTypeError::new_err(format!(
+                "from_numpy() argument must be a numpy array of dtype uint64, uint32 or int64, but received an object of type {:#?}",
+                data.get_type().name()?
+            )));
+        };
+        let mut values = values.map_err(|val| {
+            PyValueError::new_err(format!("from_numpy() received {val}, but Splinter64 elements must be between 0 and {}", u64::MAX))
+        })?;
+        values.sort_unstable();
+        Ok(Self::new(from_sorted(values)))
+    }
+
+    /// Returns the elements of the Splinter64, in ascending order, as a uint64 NumPy array.
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    pub fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<u64>>> {
+        arrays::require_numpy(py, "to_numpy")?;
+        let mut values = Vec::with_capacity(self.len());
+        values.extend(self.values());
+        Ok(values.into_pyarray(py))
+    }
+
+    pub fn to_bytes(&self, py: Python) -> Py<PyBytes> { PyBytes::new(py, &encode(&self.partitions)).into() }
+
+    #[classmethod]
+    /// Constructs a Splinter64 from raw byte data written by to_bytes().
+    ///
+    /// Accepts any object exporting the buffer protocol. As with Splinter.from_bytes(), a
+    /// read-only buffer is not copied: each partition reads directly from the source until it
+    /// is first modified. A writable buffer is copied.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): The byte data from which to construct the Splinter64.
+    ///
+    /// Returns:
+    ///     Splinter64: A Splinter64 object, or else an error explaining why
+    ///     construction failed.
+    pub fn from_bytes(
+        _cls: &Bound<'_, PyType>,
+        data: &Bound<'_, PyAny>,
+    ) -> PyResult<Self> {
+        let partitions = decode(bytes_from_buffer(data)?).map_err(|e| {
+            PyValueError::new_err(format!("Splinter64 could not be constructed from bytes: {e}"))
+        })?;
+        Ok(Self::new(partitions))
+    }
+
+    #[classmethod]
+    /// Opens a serialized Splinter64 file by memory-mapping it.
+    ///
+    /// The partitions read directly from the mapped pages until they are modified. The file
+    /// must not be modified or truncated while the Splinter64 is in use.
+    ///
+    /// Args:
+    ///     path (str | os.PathLike): The path of a file written with to_bytes().
+    ///
+    /// Returns:
+    ///     Splinter64: A Splinter64 backed by the memory-mapped file.
+    pub fn open(
+        _cls: &Bound<'_, PyType>,
+        path: PathBuf,
+    ) -> PyResult<Self> {
+        let partitions = decode(mmap_bytes(&path)?).map_err(|e| {
+            PyValueError::new_err(format!("Splinter64 could not be constructed from {}: {e}", path.display()))
+        })?;
+        Ok(Self::new(partitions))
+    }
+
+    /// Checks if the Splinter64 contains a single value or multiple values.
+    ///
+    /// Args:
+    ///     value (int | list[int]): The value or values to check for.
+    ///
+    /// Returns:
+    ///     bool | list[bool]: A single boolean if the input was a single integer,
+    ///     or a list of booleans if the input was a list.
+    pub fn contains(&self, value: &Bound<PyAny>) -> PyResult<BoolOrVec> {
+        if let Ok(value) = value.extract::<u64>() {
+            Ok(BoolOrVec::Bool(self.contains_value(value)))
+        } else if let Ok(values) = value.extract::<Vec<u64>>() {
+            Ok(BoolOrVec::Vec(values.into_iter().map(|value| self.contains_value(value)).collect()))
+        } else {
+            Err(PyTypeError::new_err(
+                format!(
+                    "contains() argument must be an integer or a list of integers, but received an object of type {:#?}",
+                    value.get_type().name()?
+                )
+            ))
+        }
+    }
+
+    /// Checks if the Splinter64 contains multiple values in parallel.
+    ///
+    /// As with Splinter.contains_many_parallel(), this is only worthwhile for at least 10,000
+    /// values.
+    ///
+    /// Args:
+    ///     values list[int]: The values to check for.
+    ///
+    /// Returns:
+    ///     list[bool]: A list of booleans.
+    pub fn contains_many_parallel(&self, values: Vec<u64>) -> Vec<bool> {
+        values.par_iter().map(|&value| self.contains_value(value)).collect()
+    }
+
+    /// Checks if the Splinter64 contains each element of a NumPy array.
+    ///
+    /// Args:
+    ///     values (numpy.ndarray): An array of dtype uint64, int64 or uint32. Negative values
+    ///     are reported as absent.
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A boolean array of the same shape as values.
+    pub fn contains_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        Ok(self.mask(&WideValues::extract_array(values, "contains_numpy")?, true, false).into_pyarray(py))
+    }
+
+    /// Checks if the Splinter64 contains each of a list or NumPy array of values, without
+    /// building a list of Python bools.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to check for.
+    ///     packed (bool): Pack the results eight to a byte, in the layout of numpy.packbits()
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A boolean array of the same shape as values, or a uint8 array of
+    ///     packed bits if packed
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, packed = false, parallel = false))]
+    pub fn contains_mask(&self, py: Python, values: &Bound<PyAny>, packed: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::require_numpy(py, "contains_mask")?;
+        let mask = self.mask(&WideValues::extract(values, "contains_mask")?, true, parallel);
+        Ok(arrays::mask_to_py(py, mask, packed))
+    }
+
+    /// Returns the values which are in the Splinter64, in their original order.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to filter.
+    ///     indices (bool): Return the indices of the values instead, into the flattened input
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A one-dimensional array of the values present, with the dtype of
+    ///     values, or uint64 for a list, or of their int64 indices
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_present(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::require_numpy(py, "filter_present")?;
+        let values = WideValues::extract(values, "filter_present")?;
+        let mask = self.mask(&values, true, parallel);
+        Ok(if indices { arrays::indices_of(py, &mask) } else { values.filter(py, &mask) })
+    }
+
+    /// Returns the values which are not in the Splinter64, in their original order.
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to filter.
+    ///     indices (bool): Return the indices of the values instead, into the flattened input
+    ///     parallel (bool): Check the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A one-dimensional array of the values absent, with the dtype of
+    ///     values, or for a list uint64 unless it holds negative values, or of their int64
+    ///     indices
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_absent(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        arrays::require_numpy(py, "filter_absent")?;
+        let values = WideValues::extract(values, "filter_absent")?;
+        let mask = self.mask(&values, false, parallel);
+        Ok(if indices { arrays::indices_of(py, &mask) } else { values.filter(py, &mask) })
+    }
+
+    fn __contains__(&self, value: &Bound<PyAny>) -> PyResult<bool> {
+        match value.extract() {
+            Ok(value) => Ok(self.contains_value(value)),
+            // like a set, which holds no such int, rather than raising
+            Err(_) if value.is_instance_of::<PyInt>() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Inserts a value into the Splinter64
+    ///
+    /// This method is overloaded. It can accept either a single integer or an
+    /// iterable of integers.
+    ///
+    /// A `range` with a step of 1 or -1 is inserted as a whole, like `add_range()`.
+    ///
+    /// Args:
+    ///     values (int | list[int] | range): The value or values to insert.
+    ///
+    /// Raises:
+    ///     OverflowError: If a value is outside of 0 to 2**64 - 1
+    pub fn add(&mut self, values: &Bound<PyAny>) -> PyResult<()> {
+        let mut vals = match Values64::extract(values, "add")? {
+            Values64::One(value) => vec![value],
+            Values64::Range(start, stop) => return self.add_range(start, stop),
+            Values64::Many(vals) => vals,
+        };
+        vals.sort_unstable();
+        let touched = self.insert_sorted(&vals);
+        self.optimize_if_auto(touched);
+        Ok(())
+    }
+
+    /// Removes a value from the Splinter64 and returns an error if the value is missing.
+    ///
+    /// This method is overloaded. It can accept either a single integer or an
+    /// iterable of integers. Nothing is removed unless every value is present.
+    ///
+    /// A `range` with a step of 1 or -1 is removed as a whole, like `remove_range()`.
+    ///
+    /// Args:
+    ///     values (int | list[int] | range): The value or values to remove.
+    ///
+    /// Raises:
+    ///     OverflowError: If a value is outside of 0 to 2**64 - 1
+    pub fn remove(&mut self, value: &Bound<PyAny>) -> PyResult<()> {
+        let vals = match Values64::extract(value, "remove")? {
+            Values64::One(value) => vec![value],
+            Values64::Range(start, stop) => return self.remove_range(start, stop),
+            Values64::Many(vals) => vals,
+        };
+        if let Some(missing) = vals.iter().find(|&&val| !self.contains_value(val)) {
+            return Err(PyKeyError::new_err(
+                format!(
+                    "remove() could not find the key {missing} in the splinter. For a fault-tolerant alternative to remove(), consider discard()"
+                )
+            ));
+        }
+        self.discard_values(vals);
+        Ok(())
+    }
+
+    /// Removes a value from the Splinter64, or does nothing if the value is missing.
+    ///
+    /// This method is overloaded. It can accept either a single integer or an
+    /// iterable of integers.
+    ///
+    /// A `range` with a step of 1 or -1 is removed as a whole, like `discard_range()`.
+    ///
+    /// Args:
+    ///     values (int | list[int] | range): The value or values to remove.
+    ///
+    /// Raises:
+    ///     OverflowError: If a value is outside of 0 to 2**64 - 1
+    pub fn discard(&mut self, value: &Bound<PyAny>) -> PyResult<()> {
+        match Values64::extract(value, "discard")? {
+            Values64::One(value) => self.discard_values(vec![value]),
+            Values64::Range(start, stop) => self.discard_range(start, stop),
+            Values64::Many(vals) => self.discard_values(vals),
+        }
+        Ok(())
+    }
+
+    /// Inserts every integer in [start, stop) into the Splinter64.
+    ///
+    /// Whole partitions of the range are inserted at once, rather than one value at a time.
+    ///
+    /// Args:
+    ///     start (int): The first value to insert.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Raises:
+    ///     OverflowError: If the range reaches outside of 0 to 2**64 - 1
+    pub fn add_range(&mut self, start: i128, stop: i128) -> PyResult<()> {
+        if let Some((lo, hi)) = insertable(start, stop, "add_range")? {
+            insert_range(self.partitions_mut(), lo, hi);
+        }
+        Ok(())
+    }
+
+    /// Removes every integer in [start, stop) from the Splinter64, and returns an error if any
+    /// of them is missing.
+    ///
+    /// Args:
+    ///     start (int): The first value to remove.
+    ///     stop (int): The end of the range, itself excluded.
+    ///
+    /// Raises:
+    ///     KeyError: If any value in the range is missing, in which case nothing is removed
+    pub fn remove_range(&mut self, start: i128, stop: i128) -> PyResult<()> {
+        if self.range_count(start, stop) as u128 != stop.saturating_sub(start).max(0) as u128 {
+            return Err(PyKeyError::new_err(
+                format!(
+                    "remove_range() could not find every key in [{start}, {stop}) in the splinter. For a fault-tolerant alternative to remove_range(), consider discard_range()"
+                )
+            ));
+        }
+        self.discard_range(start, stop);
+        Ok(())
+    }
+
+    /// Removes every integer in [start, stop) from the Splinter64, skipping any that are missing.
+    ///
+    /// Partitions covered by the range are dropped whole, rather than one value at a time.
+    ///
+    /// Args:
+    ///     start (int): The first value to remove.
+    ///     stop (int): The end of the range, itself excluded.
+    pub fn discard_range(&mut self, start: i128, stop: i128) {
+        let Some((lo, hi)) = clamped(start, stop) else { return };
+        let highs: Vec<u32> = self.partitions.range(split(lo).0..=split(hi).0).map(|(&high, _)| high).collect();
+        for high in highs {
+            let range = low_range(high, lo, hi);
+            let partition = &self.partitions[&high];
+            // leave serialized partitions untouched if there's nothing to remove
+            let count = count_within(partition, &range);
+            if count == 0 {
+                continue;
+            }
+            let whole = count == partition.cardinality();
+            let partitions = self.partitions_mut();
+            if whole {
+                partitions.remove(&high);
+            } else {
+                ranges::remove_range(partitions.get_mut(&high).expect("the partition was just found").to_mut(), range);
+            }
+        }
+    }
+
+    /// Merges one or more splinters into the Splinter64
+    ///
+    /// This method is overloaded. It can accept either a single splinter or a list of them.
+    ///
+    /// Args:
+    ///     splinters (Splinter64 | Splinter | FrozenSplinter | list[Splinter64 | Splinter | FrozenSplinter]): The object or objects to merge with
+    pub fn merge(slf: &Bound<'_, Self>, splinters: &Bound<PyAny>) -> PyResult<()> {
+        let args = match is_splinter(splinters) {
+            true => PyTuple::new(slf.py(), [splinters])?,
+            false => match splinters.extract::<Vec<Bound<PyAny>>>() {
+                Ok(list) if list.iter().all(is_splinter) => PyTuple::new(slf.py(), list)?,
+                _ => return Err(PyTypeError::new_err(format!(
+                    "merge() argument must be a Splinter64, Splinter or FrozenSplinter, or a list of them, but received an object of type {:#?}",
+                    splinters.get_type().name()?
+                ))),
+            },
+        };
+        Self::fold_in_place(slf, &args, or_assign)
+    }
+
+    /// Compacts the Splinter64's internal representation, choosing the smallest encoding for
+    /// each of its partitions.
+    ///
+    /// This runs automatically on the partitions touched by every modification, such as add()
+    /// or update(), unless auto_optimize has been turned off. Partitions loaded from bytes and
+    /// not yet modified are left as they are.
+    pub fn optimize(&mut self) {
+        self.partitions.values_mut().for_each(optimize);
+    }
+
+    /// Whether methods which modify the Splinter64, such as add() and discard(), optimize the
+    /// partitions they touch after every call. Defaults to True.
+    #[getter]
+    fn get_auto_optimize(&self) -> bool { self.auto_optimize }
+
+    #[setter]
+    fn set_auto_optimize(&mut self, auto_optimize: bool) { self.auto_optimize = auto_optimize }
+
+    /// Removes and returns the intersection between self and splinter.
+    ///
+    /// If self and splinter have no overlap, it returns an empty Splinter64 and does not modify
+    /// self. Otherwise, any elements in common between the two will be removed from self and
+    /// returned to the caller.
+    ///
+    /// Args:
+    ///     splinter (Splinter64 | Splinter | FrozenSplinter): A splinter to intersect with
+    ///
+    /// Returns:
+    ///     Splinter64
+    pub fn cut(slf: &Bound<'_, Self>, rhs: &Bound<PyAny>) -> PyResult<Self> {
+        if !is_splinter(rhs) {
+            return Err(PyTypeError::new_err(format!(
+                "cut() argument must be a Splinter64, Splinter or FrozenSplinter, but received an object of type {:#?}",
+                rhs.get_type().name()?
+            )));
+        }
+        let rhs = Self::operands(slf, &PyTuple::new(slf.py(), [rhs])?)?.remove(0);
+        let mut this = slf.borrow_mut();
+        // only the partitions which overlap are decoded and modified
+        let overlapping: Vec<u32> = this
+            .partitions
+            .iter()
+            .filter(|(high, x)| rhs.partitions().get(high).is_some_and(|y| !overlap::is_disjoint(&x.encoded(), &y.encoded())))
+            .map(|(&high, _)| high)
+            .collect();
+        let mut out = Partitions::new();
+        for &high in &overlapping {
+            let partitions = this.partitions_mut();
+            let partition = partitions.get_mut(&high).expect("the partition was just found");
+            let mut cut = partition.to_mut().cut(&**rhs.partitions().get(&high).expect("the partitions overlap"));
+            if partition.is_empty() {
+                partitions.remove(&high);
+            }
+            cut.optimize();
+            out.insert(high, CowSplinter::from_owned(cut).into());
+        }
+        this.optimize_if_auto(overlapping);
+        Ok(Self::new(out))
+    }
+
+    /// Returns the number of elements in the Splinter64 that are less than or equal to the
+    /// given value.
+    ///
+    /// Args:
+    ///     value int: the value to compare against. Cannot be negative
+    ///
+    /// Returns:
+    ///     int: the number of elements less than or equal to the given value
+    pub fn rank(&self, value: u64) -> usize {
+        let (high, low) = split(value);
+        let below: usize = self.partitions.range(..high).map(|(_, p)| p.cardinality()).sum();
+        below + self.partitions.get(&high).map_or(0, |p| p.rank(low))
+    }
+
+    /// Returns the element at the given index in the sorted sequence, or None if it is out of
+    /// bounds.
+    ///
+    /// Args:
+    ///     idx int: the index of the sequence to grab. Negative indices count back
+    ///     from the end.
+    ///
+    /// Returns:
+    ///     (int | None): the element at the relevant index, or else a None if overflowed
+    pub fn select(&self, idx: i64) -> Option<u64> { self.select_index(idx) }
+
+    pub fn position(&self, value: u64) -> PyResult<usize> {
+        if !self.contains_value(value) {
+            return Err(PyValueError::new_err(format!("element {value} does not exist in this Splinter64")));
+        }
+        Ok(self.rank(value) - 1)
+    }
+
+    /// Ranks each of a list or NumPy array of values, like rank().
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to rank, as a list or an array of
+    ///     dtype uint64, int64 or uint32. Negative values rank 0.
+    ///     parallel (bool): Rank the values in parallel, which as with contains_many_parallel()
+    ///     is only worthwhile for at least 10,000 values
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A uint64 array of the same shape as values
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn rank_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        arrays::require_numpy(py, "rank_many")?;
+        let index = self.index();
+        let ranks = WideValues::extract(values, "rank_many")?
+            .map(parallel, |val| u64::try_from(val).map_or(0, |val| index.rank(val) as u64));
+        Ok(ranks.into_pyarray(py))
+    }
+
+    /// Selects the element at each of a list or NumPy array of indices, like select().
+    ///
+    /// Unlike Splinter.select_many(), there is no -1 to stand for an index out of bounds, since
+    /// every uint64 may be an element, so any such index raises an error instead.
+    ///
+    /// Args:
+    ///     indices (list[int] | numpy.ndarray): The indices to select. Negative indices count
+    ///     back from the end.
+    ///     parallel (bool): Select the elements in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: A uint64 array of the same shape as indices
+    ///
+    /// Raises:
+    ///     IndexError: If any index is out of bounds
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (indices, parallel = false))]
+    pub fn select_many<'py>(&self, py: Python<'py>, indices: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        arrays::require_numpy(py, "select_many")?;
+        let index = self.index();
+        let len = index.len as i128;
+        let selected = WideValues::extract(indices, "select_many")?.map(parallel, |idx| {
+            let idx = if idx < 0 { idx + len } else { idx };
+            usize::try_from(idx).ok().and_then(|idx| index.select(idx))
+        });
+        if selected.iter().any(Option::is_none) {
+            return Err(PyIndexError::new_err(format!(
+                "select_many() received an index out of range for a Splinter64 of {len} elements"
+            )));
+        }
+        Ok(selected.mapv(|value| value.unwrap_or_default()).into_pyarray(py))
+    }
+
+    /// Finds the index of each of a list or NumPy array of values, like position().
+    ///
+    /// Args:
+    ///     values (list[int] | numpy.ndarray): The values to look for
+    ///     parallel (bool): Look for the values in parallel
+    ///
+    /// Returns:
+    ///     numpy.ndarray: An int64 array of the same shape as values, holding -1 rather than
+    ///     raising an error for values which are absent
+    ///
+    /// Raises:
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn position_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        arrays::require_numpy(py, "position_many")?;
+        let index = self.index();
+        let positions = WideValues::extract(values, "position_many")?.map(parallel, |val| {
+            let value = u64::try_from(val).ok().filter(|&val| self.contains_value(val));
+            value.map_or(-1, |val| index.rank(val) as i64 - 1)
+        });
+        Ok(positions.into_pyarray(py))
+    }
+
+    /// Returns the smallest element of the Splinter64, or None if it is empty.
+    pub fn min(&self) -> Option<u64> {
+        let (&high, partition) = self.partitions.first_key_value()?;
+        partition.select(0).map(|low| join(high, low))
+    }
+
+    /// Returns the largest element of the Splinter64, or None if it is empty.
+    pub fn max(&self) -> Option<u64> {
+        let (&high, partition) = self.partitions.last_key_value()?;
+        partition.last().map(|low| join(high, low))
+    }
+
+    /// Returns the smallest element of the Splinter64 greater than the given value.
+    ///
+    /// Args:
+    ///     value (int): the value to search after, which does not need to be in the Splinter64
+    ///
+    /// Returns:
+    ///     (int | None): the next element, or None if no element is greater than value
+    pub fn successor(&self, value: i128) -> Option<u64> {
+        let Ok(value) = u64::try_from(value) else { return if value < 0 { self.min() } else { None } };
+        let (high, low) = split(value);
+        self.partitions.range(high..).find_map(|(&h, partition)| {
+            let next = if h == high { successor(partition, low as i64) } else { partition.select(0) };
+            next.map(|low| join(h, low))
+        })
+    }
+
+    /// Returns the largest element of the Splinter64 less than the given value.
+    ///
+    /// Args:
+    ///     value (int): the value to search before, which does not need to be in the Splinter64
+    ///
+    /// Returns:
+    ///     (int | None): the previous element, or None if no element is less than value
+    pub fn predecessor(&self, value: i128) -> Option<u64> {
+        let Ok(value) = u64::try_from(value) else { return if value < 0 { None } else { self.max() } };
+        let (high, low) = split(value);
+        self.partitions.range(..=high).rev().find_map(|(&h, partition)| {
+            let prev = if h == high { predecessor(partition, low as i64) } else { partition.last() };
+            prev.map(|low| join(h, low))
+        })
+    }
+
+    /// Returns the smallest value greater than or equal to the given value which is not in the
+    /// Splinter64.
+    ///
+    /// As with Splinter.next_absent(), runs of consecutive elements are skipped over with a
+    /// binary search, a partition at a time.
+    ///
+    /// Args:
+    ///     value (int): the value to start searching from
+    ///
+    /// Returns:
+    ///     (int | None): the next absent value, or None if every value from here up to
+    ///     2**64 - 1 is in the Splinter64
+    pub fn next_absent(&self, value: i128) -> Option<u64> {
+        let (mut high, mut low) = split(u64::try_from(value.max(0)).ok()?);
+        loop {
+            let Some(partition) = self.partitions.get(&high) else { return Some(join(high, low)) };
+            if let Some(absent) = next_absent(partition, low as i64) {
+                return Some(join(high, absent));
+            }
+            // the rest of this partition is full, so carry on from the start of the next one
+            (high, low) = (high.checked_add(1)?, 0);
+        }
+    }
+
+    /// Counts the elements of the Splinter64 between lo and hi, including lo and excluding hi.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to count.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     int: The number of elements within [lo, hi)
+    pub fn range_count(&self, lo: i128, hi: i128) -> usize {
+        clamped(lo, hi).map_or(0, |(lo, hi)| self.count_within(lo, hi))
+    }
+
+    /// Returns the elements of the Splinter64 between lo and hi as a new Splinter64.
+    ///
+    /// As with Splinter.range(), this selects by value, including lo and excluding hi, and
+    /// each partition of the result stays compressed.
+    ///
+    /// Args:
+    ///     lo (int): The smallest value to include.
+    ///     hi (int): The end of the range, itself excluded.
+    ///
+    /// Returns:
+    ///     Splinter64: The elements within [lo, hi)
+    pub fn range(&self, lo: i128, hi: i128) -> Self {
+        let Some((lo, hi)) = clamped(lo, hi) else { return Self::new(Partitions::new()) };
+        let partitions = self
+            .partitions
+            .range(split(lo).0..=split(hi).0)
+            .map(|(&high, partition)| (high, ranges::slice(partition, low_range(high, lo, hi))))
+            .filter(|(_, slice)| !slice.is_empty())
+            .map(|(high, slice)| (high, CowSplinter::from_owned(slice).into()))
+            .collect();
+        Self::new(partitions)
+    }
+
+    // basic bitwise set operators
+    // as with Splinter, the right-hand side may also be a Splinter, FrozenSplinter, set,
+    // frozenset, range, list or NumPy array of ints, and anything else returns NotImplemented
+    fn __and__(&self, rhs: Operand64) -> Self { self.apply(rhs.0.partitions(), and_assign) }
+    fn __or__(&self, rhs: Operand64) -> Self { self.apply(rhs.0.partitions(), or_assign) }
+    fn __xor__(&self, rhs: Operand64) -> Self { self.apply(rhs.0.partitions(), xor_assign) }
+    fn __sub__(&self, rhs: Operand64) -> Self { self.apply(rhs.0.partitions(), sub_assign) }
+
+    fn __rand__(&self, rhs: Operand64) -> Self { self.__and__(rhs) }
+    fn __ror__(&self, rhs: Operand64) -> Self { self.__or__(rhs) }
+    fn __rxor__(&self, rhs: Operand64) -> Self { self.__xor__(rhs) }
+    // unlike the others, subtraction doesn't commute: this computes rhs - self
+    fn __rsub__(&self, rhs: Operand64) -> Self {
+        let mut out = rhs.0.into_partitions();
+        sub_assign(&mut out, &self.partitions);
+        Self::new(out)
+    }
+
+    fn __iand__(&mut self, rhs: Operand64) { and_assign(self.partitions_mut(), rhs.0.partitions()) }
+    fn __ior__(&mut self, rhs: Operand64) { or_assign(self.partitions_mut(), rhs.0.partitions()) }
+    fn __ixor__(&mut self, rhs: Operand64) { xor_assign(self.partitions_mut(), rhs.0.partitions()) }
+    fn __isub__(&mut self, rhs: Operand64) { sub_assign(self.partitions_mut(), rhs.0.partitions()) }
+
+    // set comparison operations
+    // only other sets compare equal, as with Python's set and Splinter
+    fn __eq__(&self, rhs: SetOperand64) -> bool { self.partitions == *rhs.0.partitions() }
+    fn __ne__(&self, rhs: SetOperand64) -> bool { !self.__eq__(rhs) }
+    fn __le__(&self, rhs: Operand64) -> bool { is_subset(&self.partitions, rhs.0.partitions()) }
+    fn __lt__(&self, rhs: Operand64) -> bool { self.len() < rhs.0.len() && self.__le__(rhs) }
+    fn __ge__(&self, rhs: Operand64) -> bool { is_subset(rhs.0.partitions(), &self.partitions) }
+    fn __gt__(&self, rhs: Operand64) -> bool { self.len() > rhs.0.len() && self.__ge__(rhs) }
+
+    // stops NumPy from applying operators element by element when an array is on the left
+    #[classattr]
+    fn __array_ufunc__(py: Python) -> PyObject { py.None() }
+
+    // for serialization with pickle, along with whether the Splinter64 optimizes itself
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        Ok((self.to_bytes(py), self.auto_optimize).into_pyobject(py)?.into_any().unbind())
+    }
+    // for deserializing from pickle, which also reads the bare bytes pickled by earlier versions
+    fn __setstate__(&mut self, state: &Bound<PyAny>) -> PyResult<()> {
+        let (bytes, auto_optimize) = match state.downcast::<PyTuple>() {
+            Ok(state) => state.extract::<(Bound<PyAny>, bool)>()?,
+            Err(_) => (state.clone(), true),
+        };
+        let partitions = decode(bytes_from_buffer(&bytes)?).map_err(|e| {
+            PyValueError::new_err(format!("Failed to deserialize Splinter64 from bytes: {e}"))
+        })?;
+        *self.partitions_mut() = partitions;
+        self.auto_optimize = auto_optimize;
+        Ok(())
+    }
+
+    /// tells pickle how to find the class and serialize it
+    fn __reduce__(&self, py: Python) -> PyResult<(PyObject, PyObject, PyObject)> {
+        let class = Self::type_object(py).into();
+        let args = PyTuple::empty(py).into();
+        Ok((class, args, self.__getstate__(py)?))
+    }
+
+    // copy protocol
+    fn copy(&self) -> Self { Self { auto_optimize: self.auto_optimize, ..Self::new(self.partitions.clone()) } }
+    fn __copy__(&self) -> Self { self.copy() }
+    // a Splinter64 holds no Python objects, so a deep copy is the same as a shallow one
+    fn __deepcopy__(&self, _memo: &Bound<PyAny>) -> Self { self.copy() }
+
+    /// Returns an immutable FrozenSplinter64 holding the same elements.
+    ///
+    /// Partitions loaded from bytes and not modified since are shared without copying, while
+    /// the others are encoded once.
+    ///
+    /// Returns:
+    ///     FrozenSplinter64: an immutable, hashable copy of this Splinter64
+    pub fn freeze(&self, py: Python) -> PyResult<FrozenSplinter64Wrapper> { FrozenSplinter64Wrapper::new(py, &self.partitions) }
+
+    /// Returns true if self and rhs have no overlap, and false otherwise.
+    ///
+    /// Only the partitions with the same high bits on both sides are compared, in their
+    /// serialized form. A modified partition is encoded the first time it's compared and
+    /// reused until it next changes, while an iterable rhs is collected and encoded on every
+    /// call. The same goes for issubset(), issuperset() and the *_len methods.
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     bool: true if there is no overlap, false otherwise
+    fn isdisjoint(&self, rhs: Set64Arg) -> bool {
+        shared(&self.partitions, rhs.partitions()).all(|(a, b)| overlap::is_disjoint(&a, &b))
+    }
+
+    /// Returns true if self is a subset of rhs, and false otherwise.
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     bool: true if self is a subset of rhs, false otherwise
+    fn issubset(&self, rhs: Set64Arg) -> bool { is_subset(&self.partitions, rhs.partitions()) }
+
+    /// Returns true if self is a superset of rhs, and false otherwise.
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     bool: true if self is a superset of rhs, false otherwise
+    fn issuperset(&self, rhs: Set64Arg) -> bool { is_subset(rhs.partitions(), &self.partitions) }
+
+    /// Returns the number of elements in both self and rhs, without building the intersection.
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     int: len(self & rhs)
+    fn intersection_len(&self, rhs: Set64Arg) -> usize {
+        shared(&self.partitions, rhs.partitions()).map(|(a, b)| overlap::intersection_len(&a, &b)).sum()
+    }
+
+    /// Returns the number of elements in either self or rhs, without building the union.
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     int: len(self | rhs)
+    fn union_len(&self, rhs: Set64Arg) -> usize { self.len() + rhs.len() - self.intersection_len(rhs) }
+
+    /// Returns the number of elements in self but not in rhs, without building the difference.
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     int: len(self - rhs)
+    fn difference_len(&self, rhs: Set64Arg) -> usize { self.len() - self.intersection_len(rhs) }
+
+    /// Returns the Jaccard similarity of self and rhs, the size of their intersection divided by
+    /// the size of their union.
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     float: a similarity between 0.0 and 1.0, which is 1.0 if both are empty
+    fn jaccard(&self, rhs: Set64Arg) -> f64 {
+        let (len, rhs_len) = (self.len(), rhs.len());
+        overlap::jaccard(self.intersection_len(rhs), len, rhs_len)
+    }
+
+    /// Returns the union of self and one or more others
+    ///
+    /// Args:
+    ///     rhs list[Splinter64 | Splinter | FrozenSplinter | Iterable[int]]: the values to combine
+    ///
+    /// Returns:
+    ///     Splinter64: the union of all provided values
+    #[pyo3(signature = (*rhs))]
+    fn union(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> { self.fold(rhs, or_assign) }
+
+    /// Returns the intersection of self and one or more others
+    ///
+    /// Args:
+    ///     rhs list[Splinter64 | Splinter | FrozenSplinter | Iterable[int]]: the values to combine
+    ///
+    /// Returns:
+    ///     Splinter64: the intersection of all provided values
+    #[pyo3(signature = (*rhs))]
+    fn intersection(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> { self.fold(rhs, and_assign) }
+
+    /// Returns the elements of self which are in none of the others
+    ///
+    /// Args:
+    ///     rhs list[Splinter64 | Splinter | FrozenSplinter | Iterable[int]]: the values to subtract
+    ///
+    /// Returns:
+    ///     Splinter64: the difference between self and all provided values
+    #[pyo3(signature = (*rhs))]
+    fn difference(&self, rhs: &Bound<PyTuple>) -> PyResult<Self> { self.fold(rhs, sub_assign) }
+
+    /// Returns the elements which are in exactly one of self and rhs
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    ///
+    /// Returns:
+    ///     Splinter64: the symmetric difference of self and rhs
+    fn symmetric_difference(&self, rhs: Set64Arg) -> Self { self.apply(rhs.partitions(), xor_assign) }
+
+    /// Adds the elements of one or more others to self
+    ///
+    /// Args:
+    ///     rhs list[Splinter64 | Splinter | FrozenSplinter | Iterable[int]]: the values to add
+    #[pyo3(signature = (*rhs))]
+    fn update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> { Self::fold_in_place(slf, rhs, or_assign) }
+
+    /// Keeps only the elements of self which are also in every one of the others
+    ///
+    /// Args:
+    ///     rhs list[Splinter64 | Splinter | FrozenSplinter | Iterable[int]]: the values to intersect with
+    #[pyo3(signature = (*rhs))]
+    fn intersection_update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> {
+        Self::fold_in_place(slf, rhs, and_assign)
+    }
+
+    /// Removes the elements of one or more others from self
+    ///
+    /// Args:
+    ///     rhs list[Splinter64 | Splinter | FrozenSplinter | Iterable[int]]: the values to subtract
+    #[pyo3(signature = (*rhs))]
+    fn difference_update(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>) -> PyResult<()> {
+        Self::fold_in_place(slf, rhs, sub_assign)
+    }
+
+    /// Keeps only the elements which are in exactly one of self and rhs
+    ///
+    /// Args:
+    ///     rhs (Splinter64 | Splinter | FrozenSplinter | Iterable[int]): the values to compare against
+    fn symmetric_difference_update(slf: &Bound<'_, Self>, rhs: &Bound<PyAny>) -> PyResult<()> {
+        Self::fold_in_place(slf, &PyTuple::new(slf.py(), [rhs])?, xor_assign)
+    }
+
+    /// Removes and returns the largest element of the Splinter64
+    ///
+    /// Returns:
+    ///     int: the removed element
+    ///
+    /// Raises:
+    ///     KeyError: if the Splinter64 is empty
+    fn pop(&mut self) -> PyResult<u64> {
+        let Some(value) = self.max() else {
+            return Err(PyKeyError::new_err("pop from an empty Splinter64"));
+        };
+        self.remove_value(value);
+        self.optimize_if_auto([split(value).0]);
+        Ok(value)
+    }
+
+    /// Removes every element from the Splinter64
+    fn clear(&mut self) { self.partitions_mut().clear() }
+}
+
+impl Splinter64Wrapper {
+    fn discard_values(&mut self, mut values: Vec<u64>) {
+        values.sort_unstable();
+        let mut touched = Vec::new();
+        for value in values {
+            if self.remove_value(value) && touched.last() != Some(&split(value).0) {
+                touched.push(split(value).0);
+            }
+        }
+        self.optimize_if_auto(touched);
+    }
+
+    /// Applies an in-place set operation to a copy of the partitions
+    fn apply(&self, rhs: &Partitions, op: fn(&mut Partitions, &Partitions)) -> Self {
+        let mut out = self.partitions.clone();
+        op(&mut out, rhs);
+        Self::new(out)
+    }
+
+    /// Applies an in-place set operation with each argument in turn to a copy of the partitions
+    fn fold(&self, rhs: &Bound<PyTuple>, op: fn(&mut Partitions, &Partitions)) -> PyResult<Self> {
+        let mut out = self.partitions.clone();
+        for other in rhs.iter() {
+            op(&mut out, other.extract::<Set64Arg>()?.partitions());
+        }
+        Ok(Self::new(out))
+    }
+
+    fn fold_in_place(slf: &Bound<'_, Self>, rhs: &Bound<PyTuple>, op: fn(&mut Partitions, &Partitions)) -> PyResult<()> {
+        let others = Self::operands(slf, rhs)?;
+        let mut this = slf.borrow_mut();
+        for other in &others {
+            op(this.partitions_mut(), other.partitions());
+        }
+        // every operation only changes the partitions which share their high bits with an
+        // argument, apart from those it drops
+        let touched: BTreeSet<u32> = others.iter().flat_map(|other| other.partitions().keys().copied()).collect();
+        this.optimize_if_auto(touched);
+        Ok(())
+    }
+}
+
+/// Groups sorted values by their high bits into optimized partitions
+fn from_sorted(values: Vec<u64>) -> Partitions {
+    let mut partitions = Partitions::new();
+    for chunk in values.chunk_by(|a, b| split(*a).0 == split(*b).0) {
+        let mut splinter: Splinter = chunk.iter().map(|&value| value as u32).collect();
+        splinter.optimize();
+        partitions.insert(split(chunk[0]).0, CowSplinter::from_owned(splinter).into());
+    }
+    partitions
+}
+
+/// Converts [start, stop) bounds into the first and last values to insert, raising an
+/// OverflowError if they reach outside of the values a Splinter64 can hold
+fn insertable(start: i128, stop: i128, method: &str) -> PyResult<Option<(u64, u64)>> {
+    if start >= stop {
+        return Ok(None);
+    }
+    if start < 0 || stop > 1 << 64 {
+        return Err(PyOverflowError::new_err(format!(
+            "{method}() can only insert values between 0 and 2**64 - 1, but received the range [{start}, {stop})"
+        )));
+    }
+    Ok(Some((start as u64, (stop - 1) as u64)))
+}
+
+/// Clamps [start, stop) bounds to the first and last values a Splinter64 can hold, or returns
+/// None if they hold none of them
+fn clamped(start: i128, stop: i128) -> Option<(u64, u64)> {
+    let (start, stop) = (start.max(0), stop.min(1 << 64));
+    (start < stop).then(|| (start as u64, (stop - 1) as u64))
+}
+
+/// The low bits of the values in [lo, hi] which fall in the partition with the given high bits
+fn low_range(high: u32, lo: u64, hi: u64) -> std::ops::RangeInclusive<u32> {
+    let start = if split(lo).0 == high { split(lo).1 } else { 0 };
+    let end = if split(hi).0 == high { split(hi).1 } else { u32::MAX };
+    start..=end
+}
+
+/// Inserts every value in [lo, hi], a partition at a time
+fn insert_range(partitions: &mut Partitions, lo: u64, hi: u64) {
+    for high in split(lo).0..=split(hi).0 {
+        let range = low_range(high, lo, hi);
+        if range == (0..=u32::MAX) {
+            // a full partition replaces whatever was there, without decoding it first
+            partitions.insert(high, CowSplinter::from_owned(ranges::splinter_from_range(range)).into());
+        } else {
+            ranges::insert_range(partitions.entry(high).or_default().to_mut(), range);
+        }
+    }
+}
+
+fn and_assign(a: &mut Partitions, b: &Partitions) {
+    a.retain(|high, x| match b.get(high) {
+        Some(y) => {
+            *x.to_mut() &= &**y;
+            !x.is_empty()
+        }
+        None => false,
+    });
+}
+
+fn or_assign(a: &mut Partitions, b: &Partitions) {
+    for (&high, y) in b {
+        match a.get_mut(&high) {
+            Some(x) => *x.to_mut() |= &**y,
+            None => { a.insert(high, y.clone()); }
+        }
+    }
+}
+
+fn xor_assign(a: &mut Partitions, b: &Partitions) {
+    for (&high, y) in b {
+        match a.get_mut(&high) {
+            Some(x) => {
+                *x.to_mut() ^= &**y;
+                if x.is_empty() {
+                    a.remove(&high);
+                }
+            }
+            None => { a.insert(high, y.clone()); }
+        }
+    }
+}
+
+fn sub_assign(a: &mut Partitions, b: &Partitions) {
+    for (&high, y) in b {
+        if let Some(x) = a.get_mut(&high) {
+            *x.to_mut() -= &**y;
+            if x.is_empty() {
+                a.remove(&high);
+            }
+        }
+    }
+}
+
+/// Yields the encodings of the partitions with the same high bits in a and b, which are only
+/// encoded if they've changed since they were last needed
+fn shared<'a>(a: &'a Partitions, b: &'a Partitions) -> impl Iterator<Item = (Bytes, Bytes)> + 'a {
+    a.iter().filter_map(|(high, x)| Some((x.encoded(), b.get(high)?.encoded())))
+}
+
+fn is_subset(a: &Partitions, b: &Partitions) -> bool {
+    a.iter().all(|(high, x)| b.get(high).is_some_and(|y| overlap::is_subset(&x.encoded(), &y.encoded())))
+}
+
+/// Optimizes a partition which has been modified, leaving serialized ones as they are
+fn optimize(partition: &mut CachedSplinter) {
+    if let CowSplinter::Owned(_) = **partition {
+        partition.to_mut().optimize();
+    }
+}
+
+fn encoded_size(partitions: &Partitions) -> usize {
+    8 + partitions.values().map(|p| 8 + p.encoded_size()).sum::<usize>()
+}
+
+fn encode(partitions: &Partitions) -> Vec<u8> {
+    let encodings: Vec<(u32, Bytes)> = partitions.iter().map(|(&high, p)| (high, p.encoded())).collect();
+    let mut out = Vec::with_capacity(8 + encodings.iter().map(|(_, data)| 8 + data.len()).sum::<usize>());
+    out.extend(MAGIC);
+    out.extend((encodings.len() as u32).to_le_bytes());
+    for (high, data) in &encodings {
+        out.extend(high.to_le_bytes());
+        out.extend((data.len() as u32).to_le_bytes());
+    }
+    for (_, data) in &encodings {
+        out.extend_from_slice(data);
+    }
+    out
+}
+
+/// Reads the partitions of a serialized Splinter64, each sharing the memory of bytes
+fn decode(bytes: Bytes) -> Result<Partitions, String> {
+    let word = |pos: usize| bytes.get(pos..pos + 4).map(|b| u32::from_le_bytes(b.try_into().expect("words are 4 bytes")));
+    if bytes.get(..4) != Some(&MAGIC[..]) {
+        return Err("the data does not start with a Splinter64 header".into());
+    }
+    let count = word(4).ok_or("the header is truncated")? as usize;
+    let mut offset = 8 + 8 * count;
+    if bytes.len() < offset {
+        return Err(format!("the directory of {count} partitions is truncated"));
+    }
+
+    let mut partitions = Partitions::new();
+    for i in 0..count {
+        let (high, len) = (word(8 + 8 * i).expect("checked above"), word(12 + 8 * i).expect("checked above") as usize);
+        if partitions.last_key_value().is_some_and(|(&last, _)| last >= high) {
+            return Err(format!("partition {high} is out of order"));
+        }
+        if bytes.len() - offset < len {
+            return Err(format!("partition {high} is truncated"));
+        }
+        let splinter = CowSplinter::from_bytes(bytes.slice(offset..offset + len))
+            .map_err(|e| format!("partition {high} is invalid: {e}"))?;
+        offset += len;
+        if !splinter.is_empty() {
+            partitions.insert(high, splinter.into());
+        }
+    }
+    if offset != bytes.len() {
+        return Err(format!("{} bytes follow the last partition", bytes.len() - offset));
+    }
+    Ok(partitions)
+}
+
+/// Collects a range, NumPy array or any other iterable of ints into partitions
+fn partitions_from_values(values: &Bound<PyAny>) -> PyResult<Partitions> {
+    if let Some((start, stop)) = ranges::contiguous_wide(values)? {
+        if start < stop && (start < 0 || stop > 1 << 64) {
+            return Err(PyOverflowError::new_err(format!(
+                "Splinter64s can only hold values between 0 and 2**64 - 1, but received the range [{start}, {stop})"
+            )));
+        }
+        let mut partitions = Partitions::new();
+        if let Some((lo, hi)) = clamped(start, stop) {
+            insert_range(&mut partitions, lo, hi);
+        }
+        return Ok(partitions);
+    }
+    let mut vals = match arrays::wide_values(values) {
+        Some(vals) => vals.map_err(|val| {
+            PyOverflowError::new_err(format!("Splinter64s can only hold values between 0 and 2**64 - 1, but received {val}"))
+        })?,
+        None => values.try_iter()?.map(|value| element(&value?)).collect::<PyResult<Vec<u64>>>()?,
+    };
+    vals.sort_unstable();
+    Ok(from_sorted(vals))
+}
+
+/// Reads an int as a value of a Splinter64, raising an OverflowError for ints it can't hold
+fn element(value: &Bound<PyAny>) -> PyResult<u64> {
+    value.extract().map_err(|err| {
+        if value.is_instance_of::<PyInt>() {
+            PyOverflowError::new_err("Splinter64 elements must be between 0 and 2**64 - 1")
+        } else {
+            err
+        }
+    })
+}
+
+/// The argument of add(), remove() and discard(): an int, a range with a step of 1 or -1, or a
+/// list of ints
+enum Values64 {
+    One(u64),
+    Range(i128, i128),
+    Many(Vec<u64>),
+}
+
+impl Values64 {
+    fn extract(values: &Bound<PyAny>, method: &str) -> PyResult<Self> {
+        if values.is_instance_of::<PyInt>() {
+            element(values).map(Self::One)
+        } else if let Some((start, stop)) = ranges::contiguous_wide(values)? {
+            Ok(Self::Range(start, stop))
+        } else if let Ok(vals) = values.extract::<Vec<Bound<PyAny>>>() {
+            vals.iter().map(element).collect::<PyResult<_>>().map(Self::Many)
+        } else {
+            Err(PyTypeError::new_err(format!(
+                "{method}() argument must be an integer or a list of integers, but received an object of type {:#?}",
+                values.get_type().name()?
+            )))
+        }
+    }
+}
+
+/// An argument to a named set method of Splinter64, which can be any iterable of ints as well as
+/// a Splinter64, Splinter or FrozenSplinter
+pub enum Set64Arg<'py> {
+    Splinter64(PyRef<'py, Splinter64Wrapper>),
+    Values(Partitions),
+}
+
+impl<'py> FromPyObject<'py> for Set64Arg<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(wrapper) = obj.extract::<PyRef<Splinter64Wrapper>>() {
+            return Ok(Self::Splinter64(wrapper));
+        }
+        if let Ok(frozen) = obj.downcast::<FrozenSplinter64Wrapper>() {
+            return Ok(Self::Splinter64(frozen.borrow().inner.clone_ref(obj.py()).into_bound(obj.py()).borrow()));
+        }
+        // the values of a Splinter are the partition of a Splinter64 with high bits of 0
+        let splinter = match obj.extract::<SplinterArg>() {
+            // sharing the Splinter's cached encoding, if it has one
+            Ok(SplinterArg::Splinter(wrapper)) => Some(wrapper.0.clone()),
+            Ok(SplinterArg::Frozen(wrapper)) => Some(CowSplinter::Ref(wrapper.0.clone()).into()),
+            Err(_) => None,
+        };
+        match splinter {
+            Some(splinter) if splinter.is_empty() => Ok(Self::Values(Partitions::new())),
+            Some(splinter) => Ok(Self::Values(Partitions::from([(0, splinter)]))),
+            None => partitions_from_values(obj).map(Self::Values),
+        }
+    }
+}
+
+impl Set64Arg<'_> {
+    fn partitions(&self) -> &Partitions {
+        match self {
+            Set64Arg::Splinter64(wrapper) => &wrapper.partitions,
+            Set64Arg::Values(partitions) => partitions,
+        }
+    }
+
+    fn len(&self) -> usize { self.partitions().values().map(|p| p.cardinality()).sum() }
+
+    /// Converts the argument into owned partitions, copying them only if they're borrowed
+    fn into_partitions(self) -> Partitions {
+        match self {
+            Set64Arg::Splinter64(wrapper) => wrapper.partitions.clone(),
+            Set64Arg::Values(partitions) => partitions,
+        }
+    }
+}
+
+/// The right-hand side of a Splinter64 operator, which accepts the same types as a Splinter
+/// operator as well as a Splinter64. Any other type fails to extract, which makes pyo3 return
+/// NotImplemented
+pub struct Operand64<'py>(Set64Arg<'py>);
+
+impl<'py> FromPyObject<'py> for Operand64<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let convertible = is_splinter(obj)
+            || obj.is_instance_of::<PySet>()
+            || obj.is_instance_of::<PyFrozenSet>()
+            || obj.is_instance_of::<PyList>()
+            || arrays::is_array(obj)
+            || ranges::contiguous_wide(obj)?.is_some();
+        if !convertible {
+            return Err(PyTypeError::new_err(format!("cannot use an object of type {} as a set operand", obj.get_type().name()?)));
+        }
+        obj.extract().map(Self)
+    }
+}
+
+/// The right-hand side of == and !=: a Splinter64, Splinter or FrozenSplinter, or a set or
+/// frozenset of ints. Anything else fails to extract, which makes pyo3 return NotImplemented
+pub struct SetOperand64<'py>(Set64Arg<'py>);
+
+impl<'py> FromPyObject<'py> for SetOperand64<'py> {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if !is_splinter(obj) && !obj.is_instance_of::<PySet>() && !obj.is_instance_of::<PyFrozenSet>() {
+            return Err(PyTypeError::new_err(format!("cannot compare a Splinter64 with an object of type {}", obj.get_type().name()?)));
+        }
+        obj.extract().map(Self)
+    }
+}
+
+/// Whether obj is a Splinter64, FrozenSplinter64, Splinter or FrozenSplinter
+fn is_splinter(obj: &Bound<PyAny>) -> bool {
+    obj.is_instance_of::<Splinter64Wrapper>() || obj.is_instance_of::<FrozenSplinter64Wrapper>() || obj.extract::<SplinterArg>().is_ok()
+}
+
+/// The partitions of a Splinter64 in ascending order, each with the number of values in the
+/// partitions before it
+struct Index<'a> {
+    partitions: Vec<(u32, usize, &'a CachedSplinter)>,
+    len: usize,
+}
+
+impl Index<'_> {
+    /// Counts the values less than or equal to value
+    fn rank(&self, value: u64) -> usize {
+        let (high, low) = split(value);
+        let i = self.partitions.partition_point(|&(h, ..)| h < high);
+        match self.partitions.get(i) {
+            Some(&(h, before, partition)) if h == high => before + partition.rank(low),
+            Some(&(_, before, _)) => before,
+            None => self.len,
+        }
+    }
+
+    /// Selects the value at an index, or returns None if it's out of bounds
+    fn select(&self, idx: usize) -> Option<u64> {
+        // partitions are never empty, so the one holding idx is the last to start at or before it
+        let i = self.partitions.partition_point(|&(_, before, _)| before <= idx).checked_sub(1)?;
+        let (high, before, partition) = self.partitions[i];
+        partition.select(idx - before).map(|low| join(high, low))
+    }
+}
+
+/// An immutable Splinter64, every partition of which is always queried directly from its
+/// serialized form.
+///
+/// Supports the read-only methods and operators of Splinter64, and the two types can be freely
+/// mixed in set operations, with the result taking the type of the left operand.
+/// FrozenSplinter64s are hashable, and hash the same as an equal frozenset.
+#[pyclass(name="FrozenSplinter64", module="splynters")]
+pub struct FrozenSplinter64Wrapper {
+    // a Splinter64 of serialized partitions, which is never handed out and so never modified,
+    // and which iterators read from directly
+    inner: Py<Splinter64Wrapper>,
+    // content hash, computed on the first call to __hash__
+    hash: OnceLock<isize>,
+}
+
+impl FrozenSplinter64Wrapper {
+    /// Freezes partitions, sharing the encodings of any which are serialized or have been
+    /// encoded since they last changed
+    fn new(py: Python, partitions: &Partitions) -> PyResult<Self> {
+        let frozen = partitions
+            .iter()
+            .map(|(&high, partition)| {
+                let splinter_ref = SplinterRef::from_bytes(partition.encoded()).expect("encoded splinters are always valid");
+                (high, CowSplinter::Ref(splinter_ref).into())
+            })
+            .collect();
+        Ok(Self { inner: Py::new(py, Splinter64Wrapper::new(frozen))?, hash: OnceLock::new() })
+    }
+
+    fn get<'py>(&'py self, py: Python<'py>) -> PyRef<'py, Splinter64Wrapper> { self.inner.borrow(py) }
+
+    fn from_wrapper(py: Python, wrapper: Splinter64Wrapper) -> PyResult<Self> { Self::new(py, &wrapper.partitions) }
+}
+
+#[pymethods]
+impl FrozenSplinter64Wrapper {
+    #[new]
+    pub fn __new__(py: Python) -> PyResult<Self> { Self::new(py, &Partitions::new()) }
+    pub fn __len__(&self, py: Python) -> usize { self.get(py).len() }
+    pub fn __sizeof__(&self, py: Python) -> usize { encoded_size(&self.get(py).partitions) }
+    pub fn __repr__(&self, py: Python) -> String {
+        let inner = self.get(py);
+        format!(
+            "FrozenSplinter64Wrapper(len = {}, partitions = {}, compressed_byte_size = {})",
+            inner.len(), inner.partitions.len(), encoded_size(&inner.partitions)
+        )
+    }
+    fn __iter__(&self, py: Python) -> Splinter64Iter { Splinter64Iter::new(self.inner.bind(py), Some((0, u64::MAX)), false) }
+    fn __reversed__(&self, py: Python) -> Splinter64Iter { Splinter64Iter::new(self.inner.bind(py), Some((0, u64::MAX)), true) }
+
+    #[pyo3(signature = (value, reverse = false))]
+    /// Iterates over the elements greater than or equal to value in ascending order, or, if
+    /// reverse is set, over the elements less than or equal to value in descending order.
+    fn iter_from(&self, py: Python, value: i128, reverse: bool) -> Splinter64Iter {
+        Splinter64Wrapper::iter_from(self.inner.bind(py), value, reverse)
+    }
+
+    #[pyo3(signature = (start, stop, reverse = false))]
+    /// Iterates over the elements in the range [start, stop), in ascending order or in
+    /// descending order if reverse is set.
+    fn iter_range(&self, py: Python, start: i128, stop: i128, reverse: bool) -> Splinter64Iter {
+        Splinter64Wrapper::iter_range(self.inner.bind(py), start, stop, reverse)
+    }
+
+    /// Returns an element or list of elements based on the input index or slice
+    fn __getitem__(&self, index: &Bound<PyAny>) -> PyResult<PyObject> { self.get(index.py()).__getitem__(index) }
+
+    #[staticmethod]
+    /// Constructs a FrozenSplinter64 from an iterator of unsigned integers.
+    ///
+    /// Args:
+    ///     data list[int] | range: The iterator from which to construct the FrozenSplinter64.
+    ///
+    /// Returns:
+    ///     FrozenSplinter64: A FrozenSplinter64 object constructed from the input items
+    pub fn from_list(data: &Bound<PyAny>) -> PyResult<Self> {
+        let mut wrapper = Splinter64Wrapper::from_list(data)?;
+        wrapper.optimize();
+        Self::from_wrapper(data.py(), wrapper)
+    }
+
+    pub fn to_list(&self, py: Python) -> Vec<u64> { self.get(py).to_list() }
+    pub fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<u64>>> { self.get(py).to_numpy(py) }
+    pub fn to_bytes(&self, py: Python) -> Py<PyBytes> { self.get(py).to_bytes(py) }
+
+    #[classmethod]
+    /// Constructs a FrozenSplinter64 from raw byte data written by to_bytes().
+    ///
+    /// As with Splinter64.from_bytes(), a read-only buffer is read in place, while a writable
+    /// buffer is copied.
+    ///
+    /// Args:
+    ///     data (bytes | memoryview | buffer): The byte data from which to construct the FrozenSplinter64.
+    ///
+    /// Returns:
+    ///     FrozenSplinter64: A FrozenSplinter64 object, or else an error explaining why
+    ///     construction failed.
+    pub fn from_bytes(cls: &Bound<'_, PyType>, data: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Self::from_wrapper(cls.py(), Splinter64Wrapper::from_bytes(cls, data)?)
+    }
+
+    /// Checks if the FrozenSplinter64 contains a single value or multiple values.
+    pub fn contains(&self, value: &Bound<PyAny>) -> PyResult<BoolOrVec> { self.get(value.py()).contains(value) }
+    /// Checks if the FrozenSplinter64 contains multiple values in parallel.
+    pub fn contains_many_parallel(&self, py: Python, values: Vec<u64>) -> Vec<bool> { self.get(py).contains_many_parallel(values) }
+    /// Checks if the FrozenSplinter64 contains each element of a NumPy array.
+    pub fn contains_numpy<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyArrayDyn<bool>>> {
+        self.get(py).contains_numpy(py, values)
+    }
+    /// Checks if the FrozenSplinter64 contains each of a list or NumPy array of values.
+    #[pyo3(signature = (values, packed = false, parallel = false))]
+    pub fn contains_mask(&self, py: Python, values: &Bound<PyAny>, packed: bool, parallel: bool) -> PyResult<PyObject> {
+        self.get(py).contains_mask(py, values, packed, parallel)
+    }
+    /// Returns the values which are in the FrozenSplinter64, in their original order.
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_present(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        self.get(py).filter_present(py, values, indices, parallel)
+    }
+    /// Returns the values which are not in the FrozenSplinter64, in their original order.
+    #[pyo3(signature = (values, indices = false, parallel = false))]
+    pub fn filter_absent(&self, py: Python, values: &Bound<PyAny>, indices: bool, parallel: bool) -> PyResult<PyObject> {
+        self.get(py).filter_absent(py, values, indices, parallel)
+    }
+    fn __contains__(&self, py: Python, value: &Bound<PyAny>) -> PyResult<bool> { self.get(py).__contains__(value) }
+
+    /// Returns the number of elements less than or equal to the given value.
+    pub fn rank(&self, py: Python, value: u64) -> usize { self.get(py).rank(value) }
+    /// Returns the element at the given index in the sorted sequence, or None if it is out of
+    /// bounds.
+    pub fn select(&self, py: Python, idx: i64) -> Option<u64> { self.get(py).select_index(idx) }
+    pub fn position(&self, py: Python, value: u64) -> PyResult<usize> { self.get(py).position(value) }
+    /// Ranks each of a list or NumPy array of values, like rank().
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn rank_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        self.get(py).rank_many(py, values, parallel)
+    }
+    /// Selects the element at each of a list or NumPy array of indices, like select().
+    #[pyo3(signature = (indices, parallel = false))]
+    pub fn select_many<'py>(&self, py: Python<'py>, indices: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<u64>>> {
+        self.get(py).select_many(py, indices, parallel)
+    }
+    /// Finds the index of each of a list or NumPy array of values, like position().
+    #[pyo3(signature = (values, parallel = false))]
+    pub fn position_many<'py>(&self, py: Python<'py>, values: &Bound<'py, PyAny>, parallel: bool) -> PyResult<Bound<'py, PyArrayDyn<i64>>> {
+        self.get(py).position_many(py, values, parallel)
+    }
+    /// Returns the smallest element, or None if the FrozenSplinter64 is empty.
+    pub fn min(&self, py: Python) -> Option<u64> { self.get(py).min() }
+    /// Returns the largest element, or None if the FrozenSplinter64 is empty.
+    pub fn max(&self, py: Python) -> Option<u64> { self.get(py).max() }
+    /// Returns the smallest element greater than the given value.
+    pub fn successor(&self, py: Python, value: i128) -> Option<u64> { self.get(py).successor(value) }
+    /// Returns the largest element less than the given value.
+    pub fn predecessor(&self, py: Python, value: i128) -> Option<u64> { self.get(py).predecessor(value) }
+    /// Returns the smallest value greater than or equal to the given value which is absent.
+    pub fn next_absent(&self, py: Python, value: i128) -> Option<u64> { self.get(py).next_absent(value) }
+    /// Counts the elements between lo and hi, including lo and excluding hi.
+    pub fn range_count(&self, py: Python, lo: i128, hi: i128) -> usize { self.get(py).range_count(lo, hi) }
+    /// Returns the elements between lo and hi, including lo and excluding hi, as a new
+    /// FrozenSplinter64.
+    pub fn range(&self, py: Python, lo: i128, hi: i128) -> PyResult<Self> {
+        let range = self.get(py).range(lo, hi);
+        Self::from_wrapper(py, range)
+    }
+
+    // as with Splinter64, set operators return the type of the left operand
+    fn __and__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { Self::from_wrapper(py, self.get(py).__and__(rhs)) }
+    fn __or__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { Self::from_wrapper(py, self.get(py).__or__(rhs)) }
+    fn __xor__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { Self::from_wrapper(py, self.get(py).__xor__(rhs)) }
+    fn __sub__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { Self::from_wrapper(py, self.get(py).__sub__(rhs)) }
+    fn __rand__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { self.__and__(py, rhs) }
+    fn __ror__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { self.__or__(py, rhs) }
+    fn __rxor__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { self.__xor__(py, rhs) }
+    fn __rsub__(&self, py: Python, rhs: Operand64) -> PyResult<Self> { Self::from_wrapper(py, self.get(py).__rsub__(rhs)) }
+
+    fn __eq__(&self, py: Python, rhs: SetOperand64) -> bool { self.get(py).__eq__(rhs) }
+    fn __ne__(&self, py: Python, rhs: SetOperand64) -> bool { self.get(py).__ne__(rhs) }
+    fn __le__(&self, py: Python, rhs: Operand64) -> bool { self.get(py).__le__(rhs) }
+    fn __lt__(&self, py: Python, rhs: Operand64) -> bool { self.get(py).__lt__(rhs) }
+    fn __ge__(&self, py: Python, rhs: Operand64) -> bool { self.get(py).__ge__(rhs) }
+    fn __gt__(&self, py: Python, rhs: Operand64) -> bool { self.get(py).__gt__(rhs) }
+
+    // stops NumPy from applying operators element by element when an array is on the left
+    #[classattr]
+    fn __array_ufunc__(py: Python) -> PyObject { py.None() }
+
+    // hashes like the equal frozenset, as FrozenSplinter does
+    fn __hash__(&self, py: Python) -> isize {
+        *self.hash.get_or_init(|| {
+            let inner = self.get(py);
+            frozenset_hash(inner.values().map(|value| int_hash(value as i128)), inner.len())
+        })
+    }
+
+    /// tells pickle to rebuild the FrozenSplinter64 from its serialized bytes
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(PyObject, Py<PyTuple>)> {
+        let constructor = Self::type_object(py).getattr("from_bytes")?.unbind();
+        let args = PyTuple::new(py, [self.to_bytes(py)])?.unbind();
+        Ok((constructor, args))
+    }
+
+    // copy protocol, which as with frozenset can hand back the same object
+    fn copy(slf: Py<Self>) -> Py<Self> { slf }
+    fn __copy__(slf: Py<Self>) -> Py<Self> { slf }
+    fn __deepcopy__(slf: Py<Self>, _memo: &Bound<PyAny>) -> Py<Self> { slf }
+
+    /// Returns a mutable Splinter64 holding the same elements.
+    ///
+    /// The Splinter64 shares this FrozenSplinter64's partitions until they're modified.
+    ///
+    /// Returns:
+    ///     Splinter64: a mutable copy of this FrozenSplinter64
+    pub fn thaw(&self, py: Python) -> Splinter64Wrapper { self.get(py).copy() }
+
+    /// Returns true if self and rhs have no overlap, and false otherwise.
+    fn isdisjoint(&self, py: Python, rhs: Set64Arg) -> bool { self.get(py).isdisjoint(rhs) }
+    /// Returns true if every element of self is in rhs.
+    fn issubset(&self, py: Python, rhs: Set64Arg) -> bool { self.get(py).issubset(rhs) }
+    /// Returns true if every element of rhs is in self.
+    fn issuperset(&self, py: Python, rhs: Set64Arg) -> bool { self.get(py).issuperset(rhs) }
+    /// Returns the number of elements in both self and rhs.
+    fn intersection_len(&self, py: Python, rhs: Set64Arg) -> usize { self.get(py).intersection_len(rhs) }
+    /// Returns the number of elements in either self or rhs.
+    fn union_len(&self, py: Python, rhs: Set64Arg) -> usize { self.get(py).union_len(rhs) }
+    /// Returns the number of elements in self but not in rhs.
+    fn difference_len(&self, py: Python, rhs: Set64Arg) -> usize { self.get(py).difference_len(rhs) }
+    /// Returns the Jaccard similarity of self and rhs.
+    fn jaccard(&self, py: Python, rhs: Set64Arg) -> f64 { self.get(py).jaccard(rhs) }
+
+    /// Returns the union of self and every argument as a new FrozenSplinter64.
+    #[pyo3(signature = (*rhs))]
+    fn union(&self, py: Python, rhs: &Bound<PyTuple>) -> PyResult<Self> { Self::from_wrapper(py, self.get(py).union(rhs)?) }
+    /// Returns the elements in self and every argument as a new FrozenSplinter64.
+    #[pyo3(signature = (*rhs))]
+    fn intersection(&self, py: Python, rhs: &Bound<PyTuple>) -> PyResult<Self> {
+        Self::from_wrapper(py, self.get(py).intersection(rhs)?)
+    }
+    /// Returns the elements in self but in none of the arguments as a new FrozenSplinter64.
+    #[pyo3(signature = (*rhs))]
+    fn difference(&self, py: Python, rhs: &Bound<PyTuple>) -> PyResult<Self> { Self::from_wrapper(py, self.get(py).difference(rhs)?) }
+    /// Returns the elements in exactly one of self and rhs as a new FrozenSplinter64.
+    fn symmetric_difference(&self, py: Python, rhs: Set64Arg) -> PyResult<Self> {
+        Self::from_wrapper(py, self.get(py).symmetric_difference(rhs))
+    }
+}
+
+/// Iterator class to implement __iter__ on Splinter64.
+///
+/// Each partition is encoded only once iteration reaches it, unless its encoding is already at
+/// hand, and is then decoded lazily, a batch at a time.
+#[pyclass(name = "Splinter64Iter", module = "splynters")]
+pub struct Splinter64Iter {
+    current: Option<(u32, Cursor)>,
+    // the values yet to be read from the partitions after the current one, or None once every
+    // partition in range has been reached
+    range: Option<(u64, u64)>,
+    rev: bool,
+    // the Splinter64 being iterated over, which is checked on every step in case it changes
+    source: Py<Splinter64Wrapper>,
+    version: u64,
+    remaining: usize,
+}
+
+impl Splinter64Iter {
+    /// Iterates over the values in [lo, hi], or over nothing if range is None
+    fn new(splinter: &Bound<'_, Splinter64Wrapper>, range: Option<(u64, u64)>, rev: bool) -> Self {
+        let source = splinter.borrow();
+        let remaining = range.map_or(0, |(lo, hi)| source.count_within(lo, hi));
+        Self {
+            current: None,
+            range,
+            rev,
+            source: splinter.clone().unbind(),
+            version: source.version,
+            remaining,
+        }
+    }
+
+    /// Starts reading the next partition in range, returning false if there are none left
+    fn advance(&mut self, py: Python<'_>) -> bool {
+        let Some((lo, hi)) = self.range else { return false };
+        let source = self.source.borrow(py);
+        let mut in_range = source.partitions.range(split(lo).0..=split(hi).0);
+        let next = if self.rev { in_range.next_back() } else { in_range.next() };
+        let Some((&high, partition)) = next else {
+            self.range = None;
+            return false;
+        };
+        self.current = Some((high, Cursor::new(partition.encoded(), low_range(high, lo, hi), self.rev)));
+        self.range = match self.rev {
+            false if high < split(hi).0 => Some((join(high + 1, 0), hi)),
+            true if high > split(lo).0 => Some((lo, join(high - 1, u32::MAX))),
+            _ => None,
+        };
+        true
+    }
+}
+
+#[pymethods]
+impl Splinter64Iter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> { slf }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<u64>> {
+        if self.source.borrow(py).version != self.version {
+            return Err(PyRuntimeError::new_err("Splinter64 changed during iteration"));
+        }
+        loop {
+            if let Some((high, cursor)) = &mut self.current {
+                if let Some(low) = cursor.next() {
+                    self.remaining -= 1;
+                    return Ok(Some(join(*high, low)));
+                }
+            }
+            if !self.advance(py) {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Returns the number of elements left to iterate over
+    fn __length_hint__(&self) -> usize { self.remaining }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::ffi::c_str;
+
+    use super::*;
+    use crate::testing::{optimized, run_python};
+
+    #[test]
+    fn encodes_partitions_as_iteration_reaches_them() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let partitions = Partitions::from([
+                (0, CowSplinter::from_owned(optimized(0..10)).into()),
+                (7, CowSplinter::from_owned(optimized([1, 2])).into()),
+            ]);
+            let wrapper = Bound::new(py, Splinter64Wrapper::new(partitions)).unwrap();
+            let cached = |high| wrapper.borrow().partitions[&high].encoded_if_cached().is_some();
+
+            let mut iter = Splinter64Iter::new(&wrapper, Some((0, u64::MAX)), false);
+            assert_eq!(iter.__next__(py).unwrap(), Some(0));
+            assert!(cached(0) && !cached(7));
+            let rest: Vec<u64> = std::iter::from_fn(|| iter.__next__(py).unwrap()).collect();
+            assert_eq!(rest, (1..10).chain([join(7, 1), join(7, 2)]).collect::<Vec<_>>());
+            assert!(cached(7));
+
+            // a range skips the partitions outside it without encoding them
+            wrapper.borrow_mut().partitions_mut().values_mut().for_each(|p| { p.to_mut(); });
+            let mut iter = Splinter64Iter::new(&wrapper, Some((join(0, 5), join(7, 1))), true);
+            assert_eq!(iter.__length_hint__(), 6);
+            assert_eq!(iter.__next__(py).unwrap(), Some(join(7, 1)));
+            assert!(!cached(0) && cached(7));
+        });
+    }
+
+    #[test]
+    fn matches_the_surface_of_splinter() {
+        run_python(c_str!(
+            r#"
+S = Splinter64
+vals = [1, 5, 6, 7, 2**32 - 1, 2**32, 2**32 + 1, 2**40, 2**40 + 3, 2**64 - 2, 2**64 - 1]
+s = S.from_list(vals)
+for v in [-5, 0, 6, 2**32, 2**33, 2**64 - 1, 2**70]:
+    assert list(s.iter_from(v)) == [x for x in vals if x >= v]
+    assert list(s.iter_from(v, reverse=True)) == [x for x in vals if x <= v][::-1]
+for lo, hi in [(0, 2**64), (5, 2**32 + 1), (2**32, 2**32), (6, 2**40 + 4), (-3, 3)]:
+    expected = [x for x in vals if lo <= x < hi]
+    it = s.iter_range(lo, hi)
+    assert it.__length_hint__() == len(expected) and list(it) == expected
+    assert list(s.iter_range(lo, hi, True)) == expected[::-1]
+
+assert [s.next_absent(v) for v in [-1, 5, 2**32 - 1, 2**64 - 2]] == [0, 8, 2**32 + 2, None]
+assert S.from_range(2**32 - 2, 2**33 + 5).next_absent(0) == 0
+assert S.from_range(2**32 - 2, 2**33 + 5).next_absent(2**32 - 2) == 2**33 + 5
+assert s.contains_many_parallel([1, 2, 2**40]) == [True, False, True]
+
+t = S.from_list(vals)
+t.merge([Splinter.from_list([3]), FrozenSplinter.from_list([4]), S.from_list([2**50])])
+assert sorted(set(t) - set(vals)) == [3, 4, 2**50]
+t.merge(t)
+assert len(t) == len(vals) + 3
+cut = t.cut(S.from_list([3, 4, 2**50, 99]))
+assert list(cut) == [3, 4, 2**50] and list(t) == vals
+assert len(t.cut(Splinter.from_list([2]))) == 0 and list(t) == vals
+assert list(t.cut(t)) == vals and len(t) == 0
+for bad in [[1], 5, [Splinter.from_list([1]), 2]]:
+    try:
+        t.merge(bad)
+        raise AssertionError("merge() accepted", bad)
+    except TypeError:
+        pass
+
+assert S.from_list([1, 2]) == {1, 2} and S.from_list([1]) == Splinter.from_list([1])
+assert S.from_list([1, 2]) != [1, 2] and S.from_list([1]) != range(1, 2)
+
+t = S.from_list(vals)
+for method, bad in [(m, b) for m in [t.add, t.remove, t.discard] for b in [2**64, -1, [1, 2**64], [-1]]] + [(S.from_list, [2**64]), (S.from_list, [-1])]:
+    try:
+        method(bad)
+        raise AssertionError(method, "accepted", bad)
+    except OverflowError as e:
+        assert str(e) == "Splinter64 elements must be between 0 and 2**64 - 1", e
+assert list(t) == vals
+assert -1 not in t and 2**64 not in t and 2**200 not in t and -1 not in t.freeze()
+for bad in ["1", 1.0, None]:
+    try:
+        t.add(bad)
+        raise AssertionError("add() accepted", bad)
+    except TypeError:
+        pass
+t.remove(range(5, 8))
+assert 6 not in t
+t.discard([2**64 - 1, 9])
+assert t.max() == 2**64 - 2
+
+assert list(s.range(6, 2**40 + 1)) == [x for x in vals if 6 <= x < 2**40 + 1]
+assert list(s.range(-5, 2**70)) == vals and len(s.range(7, 7)) == 0 and len(s.range(2**64, 2**65)) == 0
+assert type(s.range(0, 1)) is S
+
+import collections.abc, pickle
+f = s.freeze()
+assert type(f) is FrozenSplinter64 and f == s and s == f and list(f) == vals and len(f) == len(vals)
+assert hash(f) == hash(frozenset(vals)) and hash(S.from_list([2**63, 5]).freeze()) == hash(frozenset([2**63, 5]))
+assert {f: 1}[FrozenSplinter64.from_list(vals)] == 1
+assert list(reversed(f)) == vals[::-1] and list(f.iter_from(2**32)) == [x for x in vals if x >= 2**32]
+assert list(f.iter_range(5, 7)) == [5, 6] and f[3] == 7 and f[-1] == 2**64 - 1 and f[1:3] == [5, 6]
+assert 2**40 in f and 2**41 not in f and f.rank(2**32) == 6 and f.select(-2) == 2**64 - 2
+assert (f.min(), f.max(), f.successor(7), f.predecessor(2**32), f.next_absent(5)) == (1, 2**64 - 1, 2**32 - 1, 2**32 - 1, 8)
+assert type(f & s) is FrozenSplinter64 and type(s & f) is S and type(f | [3]) is FrozenSplinter64
+assert list(f - Splinter.from_list([1, 5])) == vals[2:] and type(f.range(5, 7)) is FrozenSplinter64
+assert f.issubset(s) and f.intersection_len([1, 2]) == 1 and type(f.union([2])) is FrozenSplinter64
+assert pickle.loads(pickle.dumps(f)) == f and type(pickle.loads(pickle.dumps(f))) is FrozenSplinter64
+t = f.thaw()
+t.add(3)
+assert 3 not in f and 3 in t and type(t) is S
+assert isinstance(f, collections.abc.Set) and not isinstance(f, collections.abc.MutableSet)
+assert not hasattr(f, "add") and not hasattr(f, "update")
+"#
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(not(numpy), ignore = "requires NumPy")]
+    fn answers_batch_queries_with_numpy() {
+        run_python(c_str!(
+            r#"
+import numpy as np
+
+vals = [1, 5, 6, 7, 2**32 - 1, 2**32, 2**32 + 1, 2**40, 2**40 + 3, 2**64 - 2, 2**64 - 1]
+s = Splinter64.from_list(vals)
+f = s.freeze()
+for dtype in [np.uint64, np.int64, np.uint32]:
+    small = [v for v in vals if v < 2**31][::-1]
+    assert Splinter64.from_numpy(np.array(small, dtype=dtype)).to_list() == small[::-1]
+for t in [s, f]:
+    out = t.to_numpy()
+    assert out.dtype == np.uint64 and out.tolist() == vals
+try:
+    Splinter64.from_numpy(np.array([1, -1], dtype=np.int64))
+    raise AssertionError("from_numpy() accepted -1")
+except ValueError:
+    pass
+
+probes = [0, 1, 2, 7, 2**32, 2**40 + 2, 2**64 - 1]
+assert s.rank_many(np.array(probes, dtype=np.uint64)).tolist() == [sum(x <= p for x in vals) for p in probes]
+assert s.rank_many([-1, 2**64 - 1]).tolist() == [0, len(vals)]
+assert s.select_many(np.array([0, -1, 4])).tolist() == [1, 2**64 - 1, 2**32 - 1]
+try:
+    s.select_many([len(vals)])
+    raise AssertionError("select_many() accepted an index out of range")
+except IndexError:
+    pass
+assert s.position_many([5, 4, 2**64 - 1]).tolist() == [1, -1, len(vals) - 1]
+assert s.contains_mask([-1, 1, 2**40]).tolist() == [False, True, True]
+assert s.contains_mask(np.array([1, 2, 5, 6, 7, 8, 0, 0, 2**40], dtype=np.uint64), packed=True).tolist() == [0b10111000, 0b10000000]
+assert s.contains_numpy(np.array([[1, 2], [2**32, 3]], dtype=np.uint64)).tolist() == [[True, False], [True, False]]
+present = s.filter_present(np.array([2**64 - 1, 3, 5], dtype=np.uint64))
+assert present.dtype == np.uint64 and present.tolist() == [2**64 - 1, 5]
+assert s.filter_absent([-4, 5, 9]).tolist() == [-4, 9] and s.filter_absent([4, 5], indices=True).tolist() == [0]
+assert f.rank_many([2**40], parallel=True).tolist() == [s.rank(2**40)]
+"#
+        ));
+    }
+
+    #[test]
+    fn indexes_partitions_for_batch_queries() {
+        let vals: Vec<u64> = vec![1, 5, 6, 7, u32::MAX as u64, 1 << 32, (1 << 32) + 1, 1 << 40, (1 << 40) + 3, u64::MAX - 1, u64::MAX];
+        let splinter = Splinter64Wrapper::new(from_sorted(vals.clone()));
+        let index = splinter.index();
+        assert_eq!(index.len, vals.len());
+        for probe in [0, 1, 2, 7, u32::MAX as u64, 1 << 32, (1 << 40) + 2, u64::MAX - 2, u64::MAX] {
+            assert_eq!(index.rank(probe), vals.iter().filter(|&&v| v <= probe).count(), "{probe}");
+        }
+        for (i, &value) in vals.iter().enumerate() {
+            assert_eq!(index.select(i), Some(value));
+        }
+        assert_eq!(index.select(vals.len()), None);
+        assert_eq!(Splinter64Wrapper::new(Partitions::new()).index().select(0), None);
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            // lists are read as uint64 when they can be, and as int64 when they hold negative values
+            let probes = PyList::new(py, [u64::MAX, 5, 0]).unwrap();
+            let values = WideValues::extract(&probes, "contains_mask").unwrap();
+            assert!(matches!(values, WideValues::U64List(_)));
+            assert_eq!(splinter.mask(&values, true, false).into_raw_vec_and_offset().0, [true, true, false]);
+            let probes = PyList::new(py, [-1i64, 5]).unwrap();
+            let values = WideValues::extract(&probes, "contains_mask").unwrap();
+            assert!(matches!(values, WideValues::I64List(_)));
+            assert_eq!(splinter.mask(&values, false, true).into_raw_vec_and_offset().0, [true, false]);
+            let err = WideValues::extract(&PyList::new(py, [-1i128, 1 << 64]).unwrap(), "rank_many").err().unwrap();
+            assert!(err.to_string().contains("rank_many() argument must be a list or numpy array"), "{err}");
+        });
+    }
+
+    #[test]
+    fn optimizes_only_the_partitions_an_update_touches() {
+        run_python(c_str!(
+            r#"
+s = Splinter64()
+s.auto_optimize = False
+s.add(list(range(1000)))
+s.auto_optimize = True
+before = s.__sizeof__()
+s.update([2**40])
+# the first partition is left unoptimized, and only the new one is added
+assert s.__sizeof__() - before == Splinter64.from_list([2**40]).__sizeof__() - 8
+s.optimize()
+assert s.__sizeof__() < before
+
+import copy, pickle
+s.auto_optimize = False
+for t in [pickle.loads(pickle.dumps(s)), copy.copy(s), copy.deepcopy(s)]:
+    assert t == s and t.auto_optimize is False
+s.auto_optimize = True
+assert pickle.loads(pickle.dumps(s)).auto_optimize is True
+# the bare bytes pickled before the state held the flag
+t = Splinter64()
+t.__setstate__(s.to_bytes())
+assert t == s and t.auto_optimize is True
+"#
+        ));
+    }
+}